@@ -1,11 +1,16 @@
 use criterion::{
     black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput,
 };
-use palmtree::StdPalmTree as PalmTree;
+use palmtree::tests::{
+    workload::{Keys, Mix, Workload},
+    Action,
+};
+use palmtree::{ImPalmTree, Op, StdPalmTree as PalmTree};
 use rand::prelude::SliceRandom;
 use rand::{Rng, SeedableRng};
 use std::collections::BTreeMap;
 use std::iter::FromIterator;
+use std::ops::Bound;
 
 const SIZES: &[usize] = &[64, 256, 1024, 4096, 16384, 32768, 65536];
 // const SIZES: &[usize] = &[256, 65536];
@@ -93,6 +98,48 @@ fn insert_random(c: &mut Criterion) {
     group.finish();
 }
 
+fn insert_batch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert_batch");
+    for size in SIZES {
+        let mut input_data: Vec<(usize, usize)> = rand::rngs::StdRng::seed_from_u64(31337)
+            .sample_iter(rand::distributions::Standard)
+            .take(*size)
+            .collect();
+        input_data.sort_unstable();
+        let tree = PalmTree::<usize, usize>::load((0..65536).map(|i| (i << 48, i)));
+        group.throughput(Throughput::Elements(*size as u64));
+        group.bench_with_input(
+            BenchmarkId::new("b+tree::insert", size),
+            &input_data,
+            |b, input_data| {
+                b.iter_batched_ref(
+                    || tree.clone(),
+                    |map| {
+                        for (k, v) in input_data {
+                            map.insert(*k, *v);
+                        }
+                    },
+                    BatchSize::SmallInput,
+                )
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("b+tree::apply_batch", size),
+            &input_data,
+            |b, input_data| {
+                b.iter_batched_ref(
+                    || tree.clone(),
+                    |map| {
+                        map.apply_batch(input_data.iter().map(|(k, v)| (*k, Op::Insert(*v))));
+                    },
+                    BatchSize::SmallInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
 fn remove_sequence(c: &mut Criterion) {
     let mut group = c.benchmark_group("remove_sequence");
     for size in SIZES {
@@ -163,6 +210,39 @@ fn remove_random(c: &mut Criterion) {
     group.finish();
 }
 
+fn snapshot(c: &mut Criterion) {
+    let mut group = c.benchmark_group("snapshot");
+    for size in SIZES {
+        let btree = BTreeMap::<usize, usize>::from_iter((0..*size).map(|i| (i, i)));
+        let unique = PalmTree::<usize, usize>::load((0..*size).map(|i| (i, i)));
+        let shared = ImPalmTree::<usize, usize>::load((0..*size).map(|i| (i, i)));
+        group.bench_with_input(BenchmarkId::new("std::btree", size), &btree, |b, map| {
+            b.iter(|| map.clone())
+        });
+        group.bench_with_input(BenchmarkId::new("b+tree", size), &unique, |b, map| {
+            b.iter(|| map.clone())
+        });
+        group.bench_with_input(
+            BenchmarkId::new("b+tree/shared", size),
+            &shared,
+            |b, map| b.iter(|| map.clone()),
+        );
+        // A write to a snapshot copies the nodes on the path down to the key.
+        group.bench_with_input(
+            BenchmarkId::new("b+tree/shared+insert", size),
+            &(&shared, size),
+            |b, &(map, &size)| {
+                b.iter(|| {
+                    let mut snapshot = map.clone();
+                    snapshot.insert(size / 2, 0);
+                    snapshot
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
 fn lookup(c: &mut Criterion) {
     let mut group = c.benchmark_group("lookup");
     for size in SIZES {
@@ -264,6 +344,97 @@ fn iterate_owned(c: &mut Criterion) {
     group.finish();
 }
 
+const MIXES: &[(&str, Mix)] = &[
+    ("read_heavy", Mix::READ_HEAVY),
+    ("balanced", Mix::BALANCED),
+    ("scan_heavy", Mix::SCAN_HEAVY),
+    ("churn", Mix::CHURN),
+];
+
+fn bounds(start: Option<u64>, end: Option<u64>) -> (Bound<u64>, Bound<u64>) {
+    (
+        start.map_or(Bound::Unbounded, Bound::Included),
+        end.map_or(Bound::Unbounded, Bound::Excluded),
+    )
+}
+
+fn apply_btree(map: &mut BTreeMap<u64, u64>, action: &Action<u64, u64>) {
+    match *action {
+        Action::Insert(key, value) => {
+            black_box(map.insert(key, value));
+        }
+        Action::Lookup(key) => {
+            black_box(map.get(&key));
+        }
+        Action::Remove(key) => {
+            black_box(map.remove(&key));
+        }
+        Action::Range(start, end) => {
+            black_box(map.range(bounds(start, end)).count());
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn apply_palmtree(map: &mut PalmTree<u64, u64>, action: &Action<u64, u64>) {
+    match *action {
+        Action::Insert(key, value) => {
+            black_box(map.insert(key, value));
+        }
+        Action::Lookup(key) => {
+            black_box(map.get(&key));
+        }
+        Action::Remove(key) => {
+            black_box(map.remove(&key));
+        }
+        Action::Range(start, end) => {
+            black_box(map.range(bounds(start, end)).count());
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn mixed(c: &mut Criterion) {
+    for (name, mix) in MIXES {
+        let mut group = c.benchmark_group(format!("mixed_{}", name));
+        for size in SIZES {
+            // Start half full, so lookups and removes hit about half the time.
+            let key_space = *size as u64 * 2;
+            let actions: Vec<_> = Workload::new(Keys::Zipfian(0.99), key_space, *mix, 31337)
+                .take(*size)
+                .collect();
+            group.throughput(Throughput::Elements(*size as u64));
+            group.bench_with_input(
+                BenchmarkId::new("std::btree", size),
+                &actions,
+                |b, actions| {
+                    b.iter_batched_ref(
+                        || BTreeMap::from_iter((0..key_space).step_by(2).map(|i| (i, i))),
+                        |map| {
+                            for action in actions {
+                                apply_btree(map, action);
+                            }
+                        },
+                        BatchSize::SmallInput,
+                    )
+                },
+            );
+            group.bench_with_input(BenchmarkId::new("b+tree", size), &actions, |b, actions| {
+                b.iter_batched_ref(
+                    || PalmTree::load((0..key_space).step_by(2).map(|i| (i, i))),
+                    |map| {
+                        for action in actions {
+                            apply_palmtree(map, action);
+                        }
+                    },
+                    BatchSize::SmallInput,
+                )
+            });
+        }
+        group.finish();
+    }
+}
+
 fn find_key_binary<K>(keys: &[K], key: &K) -> usize
 where
     K: Ord,
@@ -305,10 +476,14 @@ pub fn search_strategies(c: &mut Criterion) {
         let keys = Vec::<u64>::from_iter(0..(*size as u64));
         group.bench_with_input(BenchmarkId::new("binary", size), size, |b, &size| {
             b.iter_batched_ref(
-                || Vec::from_iter((0..256u64).map(|i| i % (size as u64))),
+                || {
+                    let mut lookup = Vec::from_iter((0..256u64).map(|i| i % (size as u64)));
+                    lookup.shuffle(&mut rand::rngs::StdRng::seed_from_u64(31337));
+                    lookup
+                },
                 |lookup| {
                     for key in lookup {
-                        let index = find_key_binary(&keys, &key);
+                        let index = find_key_binary(&keys, key);
                         assert_eq!(keys[index], *key);
                     }
                 },
@@ -317,10 +492,14 @@ pub fn search_strategies(c: &mut Criterion) {
         });
         group.bench_with_input(BenchmarkId::new("branchless", size), size, |b, &size| {
             b.iter_batched_ref(
-                || Vec::from_iter((0..256u64).map(|i| i % (size as u64))),
+                || {
+                    let mut lookup = Vec::from_iter((0..256u64).map(|i| i % (size as u64)));
+                    lookup.shuffle(&mut rand::rngs::StdRng::seed_from_u64(31337));
+                    lookup
+                },
                 |lookup| {
                     for key in lookup {
-                        let index = branchless_binary_search(&keys, &key);
+                        let index = branchless_binary_search(&keys, key);
                         assert_eq!(keys[index], *key);
                     }
                 },
@@ -334,11 +513,14 @@ criterion_group!(
     palmtree,
     insert_sequence,
     insert_random,
+    insert_batch,
     remove_sequence,
     remove_random,
+    snapshot,
     lookup,
     iterate,
     iterate_owned,
+    mixed,
     search_strategies,
 );
 criterion_main!(palmtree);
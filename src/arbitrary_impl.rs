@@ -0,0 +1,68 @@
+use crate::{config::TreeConfig, PalmTree};
+use alloc::collections::BTreeMap;
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+/// Build a tree by loading an arbitrary map, and then inserting or removing
+/// an arbitrary run of keys, so the shape of the tree varies as well as its
+/// contents: loading packs leaves full, and removals thin them out again.
+impl<K, V, C> Arbitrary for PalmTree<K, V, C>
+where
+    K: Arbitrary + Ord + Clone,
+    V: Arbitrary + Clone,
+    C: 'static + TreeConfig<K, V>,
+{
+    fn arbitrary(u: &mut Unstructured<'_>) -> Result<Self> {
+        let mut tree = Self::load(u.arbitrary::<BTreeMap<K, V>>()?);
+        for change in u.arbitrary_iter::<(bool, K, V)>()? {
+            tree.apply_change(change?);
+        }
+        Ok(tree)
+    }
+
+    fn arbitrary_take_rest(mut u: Unstructured<'_>) -> Result<Self> {
+        let mut tree = Self::load(u.arbitrary::<BTreeMap<K, V>>()?);
+        for change in u.arbitrary_take_rest_iter::<(bool, K, V)>()? {
+            tree.apply_change(change?);
+        }
+        Ok(tree)
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        <BTreeMap<K, V> as Arbitrary>::size_hint(depth)
+    }
+}
+
+impl<K, V, C> PalmTree<K, V, C>
+where
+    K: Ord + Clone,
+    V: Clone,
+    C: TreeConfig<K, V>,
+{
+    fn apply_change(&mut self, (insert, key, value): (bool, K, V)) {
+        if insert {
+            self.insert(key, value);
+        } else {
+            self.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Tree64, Unique};
+
+    #[test]
+    fn arbitrary_trees_are_sound() {
+        let bytes: Vec<u8> = (0..20_000u32).map(|i| (i * 7919 % 251) as u8).collect();
+        let mut u = Unstructured::new(&bytes);
+        while !u.is_empty() {
+            let tree: PalmTree<u8, u16, Tree64<Unique>> = u.arbitrary().unwrap();
+            tree.check_invariants().unwrap();
+        }
+        let tree: PalmTree<u16, u8, Tree64<Unique>> =
+            Arbitrary::arbitrary_take_rest(Unstructured::new(&bytes)).unwrap();
+        tree.check_invariants().unwrap();
+        assert!(!tree.is_empty());
+    }
+}
@@ -13,12 +13,12 @@
 pub(crate) unsafe fn prefetch<A>(data: &A) {
     // TODO think more carefully about the locality values.
     #[cfg(core_intrinsics)]
-    std::intrinsics::prefetch_read_data(data, 2);
+    core::intrinsics::prefetch_read_data(data, 2);
     #[cfg(all(not(core_intrinsics), target_arch = "x86"))]
-    std::arch::x86::_mm_prefetch(data as *const _ as *const i8, std::arch::x86::_MM_HINT_T1);
+    core::arch::x86::_mm_prefetch(data as *const _ as *const i8, core::arch::x86::_MM_HINT_T1);
     #[cfg(all(not(core_intrinsics), target_arch = "x86_64"))]
-    std::arch::x86_64::_mm_prefetch(
+    core::arch::x86_64::_mm_prefetch(
         data as *const _ as *const i8,
-        std::arch::x86_64::_MM_HINT_T1,
+        core::arch::x86_64::_MM_HINT_T1,
     );
 }
@@ -1,8 +1,10 @@
-use generic_array::ArrayLength;
-use std::{
+#![allow(deprecated)] // generic-array 0.14 deprecates `ArrayLength` in favour of 1.x
+
+use core::{
     fmt::{Debug, Error, Formatter},
     mem::MaybeUninit,
 };
+use generic_array::ArrayLength;
 
 pub(crate) struct Array<A, N>
 where
@@ -11,30 +13,52 @@ where
     data: MaybeUninit<N::ArrayType>,
 }
 
+// `N::ArrayType` is always `[A; N]`, but the compiler can't see that through
+// the associated type, so we say so ourselves.
+unsafe impl<A, N> Send for Array<A, N>
+where
+    A: Send,
+    N: ArrayLength<A>,
+{
+}
+
+unsafe impl<A, N> Sync for Array<A, N>
+where
+    A: Sync,
+    N: ArrayLength<A>,
+{
+}
+
 impl<A, N> Array<A, N>
 where
     N: ArrayLength<A>,
 {
     #[inline(always)]
-    fn ptr(&self) -> *const A {
+    pub(crate) fn ptr(&self) -> *const A {
         self.data.as_ptr().cast()
     }
 
     #[inline(always)]
-    fn mut_ptr(&mut self) -> *mut A {
+    pub(crate) fn mut_ptr(&mut self) -> *mut A {
         self.data.as_mut_ptr().cast()
     }
 
+    /// Get a pointer to the first element without borrowing the array.
+    #[inline(always)]
+    pub(crate) unsafe fn raw_ptr(this: *mut Self) -> *mut A {
+        core::ptr::addr_of_mut!((*this).data).cast()
+    }
+
     #[inline(always)]
     pub(crate) unsafe fn deref(&self, length: usize) -> &[A] {
         debug_assert!(length <= N::USIZE);
-        std::slice::from_raw_parts(self.ptr(), length)
+        core::slice::from_raw_parts(self.ptr(), length)
     }
 
     #[inline(always)]
     pub(crate) unsafe fn deref_mut(&mut self, length: usize) -> &mut [A] {
         debug_assert!(length <= N::USIZE);
-        std::slice::from_raw_parts_mut(self.mut_ptr(), length)
+        core::slice::from_raw_parts_mut(self.mut_ptr(), length)
     }
 
     pub(crate) fn new() -> Self {
@@ -44,7 +68,7 @@ where
     }
 
     pub(crate) unsafe fn drop(&mut self, length: usize) {
-        std::ptr::drop_in_place(self.deref_mut(length))
+        core::ptr::drop_in_place(self.deref_mut(length))
     }
 
     pub(crate) unsafe fn unit(value: A) -> Self {
@@ -68,28 +92,19 @@ where
         out
     }
 
-    pub(crate) unsafe fn clone(&self, length: usize) -> Self
-    where
-        A: Clone,
-    {
-        debug_assert!(length <= N::USIZE);
-        let mut out = Self::new();
-        for (index, element) in self.deref(length).iter().enumerate() {
-            out.mut_ptr().add(index).write(element.clone());
-        }
-        out
-    }
-
-    pub(crate) unsafe fn clone_with<F>(&self, length: usize, f: F) -> Self
-    where
-        F: Fn(&A) -> A,
-    {
-        debug_assert!(length <= N::USIZE);
-        let mut out = Self::new();
-        for (index, element) in self.deref(length).iter().enumerate() {
-            out.mut_ptr().add(index).write(f(element));
-        }
-        out
+    /// Move the first `other_length` values out of `other` onto the end of
+    /// this array, leaving them uninitialised in `other`.
+    pub(crate) unsafe fn append<N2: ArrayLength<A>>(
+        &mut self,
+        length: usize,
+        other: &mut Array<A, N2>,
+        other_length: usize,
+    ) {
+        debug_assert!(other_length <= N2::USIZE);
+        debug_assert!(length + other_length <= N::USIZE);
+        self.mut_ptr()
+            .add(length)
+            .copy_from_nonoverlapping(other.ptr(), other_length);
     }
 
     pub(crate) unsafe fn push(&mut self, length: usize, value: A) {
@@ -145,6 +160,6 @@ where
     N: ArrayLength<A>,
 {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
-        write!(f, "Array[{}; {}]", std::any::type_name::<A>(), N::USIZE)
+        write!(f, "Array[{}; {}]", core::any::type_name::<A>(), N::USIZE)
     }
 }
@@ -0,0 +1,128 @@
+/// A change to make to a key, for `PalmTree::apply_batch`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Op<V> {
+    /// Insert the value, replacing any value already there.
+    Insert(V),
+    /// Replace the value if the key is present, and do nothing otherwise.
+    Update(V),
+    /// Remove the key if it's present.
+    Remove,
+}
+
+/// Whether `key` falls above `lower` and no higher than `upper`, which is
+/// the range of keys a child of a branch covers.
+pub(crate) fn in_bounds<K: Ord>(key: &K, lower: Option<&K>, upper: Option<&K>) -> bool {
+    lower.is_none_or(|lower| key > lower) && upper.is_none_or(|upper| key <= upper)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ImPalmTree, Tree64, Unique};
+    use std::collections::BTreeMap;
+
+    type Tree = crate::PalmTree<u32, u32, Tree64<Unique>>;
+
+    fn apply(nat: &mut BTreeMap<u32, u32>, batch: &[(u32, Op<u32>)]) {
+        for (key, op) in batch {
+            match op {
+                Op::Insert(value) => {
+                    nat.insert(*key, *value);
+                }
+                Op::Update(value) => {
+                    if let Some(old) = nat.get_mut(key) {
+                        *old = *value;
+                    }
+                }
+                Op::Remove => {
+                    nat.remove(key);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn batches_match_btreemap() {
+        let mut tree = Tree::load((0..10_000).map(|i| (i * 2, i)));
+        let mut nat: BTreeMap<u32, u32> = tree.iter().map(|(k, v)| (*k, *v)).collect();
+        for round in 1..20u32 {
+            let batch: Vec<(u32, Op<u32>)> = (0..25_000)
+                .step_by(round as usize * 3)
+                .map(|key| {
+                    let op = match (key / 7 + round) % 3 {
+                        0 => Op::Insert(round),
+                        1 => Op::Update(round + 100),
+                        _ => Op::Remove,
+                    };
+                    (key, op)
+                })
+                .collect();
+            tree.apply_batch(batch.iter().copied());
+            apply(&mut nat, &batch);
+            tree.check_invariants().unwrap();
+            assert_eq!(nat.len(), tree.len());
+            assert!(nat.iter().eq(tree.iter()));
+        }
+
+        // Anything goes for order, including repeated keys, which are
+        // applied in turn.
+        let batch = vec![
+            (30_000, Op::Insert(1)),
+            (5, Op::Remove),
+            (30_000, Op::Update(2)),
+            (0, Op::Insert(3)),
+            (30_000, Op::Remove),
+            (30_001, Op::Insert(4)),
+        ];
+        tree.apply_batch(batch.iter().copied());
+        apply(&mut nat, &batch);
+        tree.check_invariants().unwrap();
+        assert!(nat.iter().eq(tree.iter()));
+
+        let everything: Vec<_> = nat.keys().map(|key| (*key, Op::Remove)).collect();
+        tree.apply_batch(everything);
+        assert!(tree.is_empty());
+        tree.apply_batch(vec![
+            (1, Op::Update(1)),
+            (2, Op::Insert(2)),
+            (3, Op::Remove),
+        ]);
+        assert_eq!(vec![(&2, &2)], tree.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn batches_carry_on_after_emptying_the_tree() {
+        let mut tree = Tree::load((0..5000).map(|i| (i, i)));
+        let batch = (0..5000)
+            .map(|key| (key, Op::Remove))
+            .chain((0..100).map(|key| (key * 2, Op::Insert(key))));
+        tree.apply_batch(batch);
+        tree.check_invariants().unwrap();
+        assert_eq!(100, tree.len());
+        assert!(tree
+            .iter()
+            .map(|(k, v)| (*k, *v))
+            .eq((0..100).map(|i| (i * 2, i))));
+
+        let mut tree = Tree::load(vec![(0, 0)]);
+        tree.apply_batch(vec![
+            (0, Op::Remove),
+            (5, Op::Insert(5)),
+            (6, Op::Insert(6)),
+        ]);
+        tree.check_invariants().unwrap();
+        assert_eq!(vec![(&5, &5), (&6, &6)], tree.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn batches_leave_clones_alone() {
+        let tree: ImPalmTree<u32, u32> = ImPalmTree::load((0..10_000).map(|i| (i, i)));
+        let mut changed = tree.clone();
+        changed.apply_batch((5000..5100).map(|key| (key, Op::Remove)));
+        changed.apply_batch((0..100).map(|key| (key * 100, Op::Update(0))));
+        changed.check_invariants().unwrap();
+        assert_eq!(9900, changed.len());
+        assert!(tree.iter().all(|(k, v)| k == v));
+        assert_eq!(10_000, tree.len());
+    }
+}
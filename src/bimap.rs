@@ -0,0 +1,277 @@
+use crate::{config::TreeConfig, Iter, PalmTree};
+use alloc::vec::Vec;
+use core::{
+    borrow::Borrow,
+    fmt::{Debug, Error, Formatter},
+};
+
+/// A one to one map, which can look up keys by value as well as values by
+/// key.
+///
+/// It's a pair of trees, one in each direction, kept in step, so every
+/// key and every value is stored twice.
+///
+/// ```
+/// # use palmtree::{BiPalmMap, Tree64, Unique};
+/// let mut symbols: BiPalmMap<&str, u32, Tree64<Unique>> = BiPalmMap::new();
+/// symbols.insert("main", 0);
+/// symbols.insert("exit", 1);
+/// assert_eq!(Some(&1), symbols.get_by_key(&"exit"));
+/// assert_eq!(Some(&"main"), symbols.get_by_value(&0));
+///
+/// // Another key for an existing value takes its place.
+/// symbols.insert("start", 0);
+/// assert_eq!(None, symbols.get_by_key(&"main"));
+/// assert_eq!(Some(&"start"), symbols.get_by_value(&0));
+/// ```
+pub struct BiPalmMap<K, V, C>
+where
+    C: TreeConfig<K, V> + TreeConfig<V, K>,
+{
+    by_key: PalmTree<K, V, C>,
+    by_value: PalmTree<V, K, C>,
+}
+
+impl<K, V, C> BiPalmMap<K, V, C>
+where
+    K: Clone + Ord,
+    V: Clone + Ord,
+    C: TreeConfig<K, V> + TreeConfig<V, K>,
+{
+    pub fn new() -> Self {
+        Self {
+            by_key: PalmTree::new(),
+            by_value: PalmTree::new(),
+        }
+    }
+
+    /// Construct a map efficiently from an iterator of pairs in key order.
+    ///
+    /// Like `PalmTree::load`, the keys must be sorted with no duplicates,
+    /// and so must the values once they're sorted in turn. Both directions
+    /// are built with `load`.
+    pub fn load<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut pairs: Vec<(V, K)> = Vec::new();
+        let by_key = PalmTree::load(iter.into_iter().inspect(|(key, value)| {
+            pairs.push((value.clone(), key.clone()));
+        }));
+        pairs.sort_unstable_by(|(left, _), (right, _)| left.cmp(right));
+        debug_assert!(
+            pairs.windows(2).all(|pair| pair[0].0 != pair[1].0),
+            "BiPalmMap::load: duplicate value"
+        );
+        Self {
+            by_key,
+            by_value: PalmTree::load(pairs),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_key.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_key.is_empty()
+    }
+
+    pub fn get_by_key<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.by_key.get(key)
+    }
+
+    pub fn get_by_value<Q>(&self, value: &Q) -> Option<&K>
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.by_value.get(value)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.by_key.contains_key(key)
+    }
+
+    pub fn contains_value<Q>(&self, value: &Q) -> bool
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.by_value.contains_key(value)
+    }
+
+    /// Map `key` to `value` and back.
+    ///
+    /// Any pair already holding either of them has to go to keep the map
+    /// one to one, so we return the pair which held `key` and the pair
+    /// which held `value`, or the same pair once if they were mapped to
+    /// each other already.
+    pub fn insert(&mut self, key: K, value: V) -> (Option<(K, V)>, Option<(K, V)>) {
+        let by_key = self.remove_by_key(&key);
+        let by_value = self.remove_by_value(&value);
+        self.by_key.insert(key.clone(), value.clone());
+        self.by_value.insert(value, key);
+        (by_key, by_value)
+    }
+
+    pub fn remove_by_key<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let (key, value) = self.by_key.remove(key)?;
+        self.by_value.remove(&value);
+        Some((key, value))
+    }
+
+    pub fn remove_by_value<Q>(&mut self, value: &Q) -> Option<(K, V)>
+    where
+        V: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let (value, key) = self.by_value.remove(value)?;
+        self.by_key.remove(&key);
+        Some((key, value))
+    }
+
+    /// Iterate over the pairs in key order.
+    pub fn iter(&self) -> Iter<'_, K, V, C> {
+        self.by_key.iter()
+    }
+
+    /// Iterate over the pairs in value order, values first.
+    pub fn iter_by_value(&self) -> Iter<'_, V, K, C> {
+        self.by_value.iter()
+    }
+
+    /// The tree mapping keys to values.
+    pub fn by_key(&self) -> &PalmTree<K, V, C> {
+        &self.by_key
+    }
+
+    /// The tree mapping values to keys.
+    pub fn by_value(&self) -> &PalmTree<V, K, C> {
+        &self.by_value
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl<K, V, C> Default for BiPalmMap<K, V, C>
+where
+    K: Clone + Ord,
+    V: Clone + Ord,
+    C: TreeConfig<K, V> + TreeConfig<V, K>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, C> Clone for BiPalmMap<K, V, C>
+where
+    K: Clone + Ord,
+    V: Clone + Ord,
+    C: TreeConfig<K, V> + TreeConfig<V, K>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            by_key: self.by_key.clone(),
+            by_value: self.by_value.clone(),
+        }
+    }
+}
+
+impl<K, V, C> PartialEq for BiPalmMap<K, V, C>
+where
+    K: Clone + Ord,
+    V: Clone + Ord,
+    C: TreeConfig<K, V> + TreeConfig<V, K>,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.by_key == other.by_key
+    }
+}
+
+impl<K, V, C> Debug for BiPalmMap<K, V, C>
+where
+    K: Clone + Ord + Debug,
+    V: Clone + Ord + Debug,
+    C: TreeConfig<K, V> + TreeConfig<V, K>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Tree64, Unique};
+    use alloc::string::String;
+
+    type Map = BiPalmMap<u32, u32, Tree64<Unique>>;
+
+    fn check(map: &Map) {
+        assert_eq!(map.by_key().len(), map.by_value().len());
+        for (key, value) in map.iter() {
+            assert_eq!(Some(key), map.get_by_value(value));
+        }
+    }
+
+    #[test]
+    fn both_directions() {
+        // Values run in the opposite order to keys.
+        let mut map = Map::load((0..10_000).map(|i| (i, 20_000 - i * 2)));
+        check(&map);
+        assert_eq!(10_000, map.len());
+        assert_eq!(Some(&20_000), map.get_by_key(&0));
+        assert_eq!(Some(&9999), map.get_by_value(&2));
+        assert_eq!(Some((&2, &9999)), map.iter_by_value().next());
+
+        assert_eq!((None, None), map.insert(20_000, 1));
+        assert_eq!((Some((1, 19_998)), None), map.insert(1, 3));
+        assert_eq!((Some((5, 19_990)), Some((9999, 2))), map.insert(5, 2));
+        assert_eq!((Some((5, 2)), None), map.insert(5, 2));
+        assert_eq!(10_000, map.len());
+        check(&map);
+
+        assert_eq!(Some((0, 20_000)), map.remove_by_key(&0));
+        assert_eq!(Some((20_000, 1)), map.remove_by_value(&1));
+        assert_eq!(None, map.remove_by_key(&0));
+        assert_eq!(None, map.remove_by_value(&1));
+        assert!(!map.contains_key(&0));
+        assert!(!map.contains_value(&20_000));
+        assert_eq!(9998, map.len());
+        check(&map);
+    }
+
+    #[test]
+    fn borrowed_lookups() {
+        let mut map: BiPalmMap<String, String, Tree64<Unique>> = BiPalmMap::new();
+        map.insert("one".into(), "uno".into());
+        map.insert("two".into(), "dos".into());
+        assert_eq!(Some("uno"), map.get_by_key("one").map(String::as_str));
+        assert_eq!(Some("two"), map.get_by_value("dos").map(String::as_str));
+        assert!(map.contains_key("two"));
+        assert!(map.contains_value("uno"));
+        assert_eq!(Some(("one".into(), "uno".into())), map.remove_by_key("one"));
+        assert_eq!(
+            Some(("two".into(), "dos".into())),
+            map.remove_by_value("dos")
+        );
+        assert!(map.is_empty());
+        assert!(!map.contains_value("uno"));
+    }
+}
@@ -0,0 +1,218 @@
+use crate::{config::TreeConfig, Iter, PalmTree};
+use core::{
+    borrow::Borrow,
+    fmt::{Debug, Error, Formatter},
+};
+
+/// Which end of a `BoundedPalmTree` gives way when it's full.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Evict {
+    /// Drop the entry with the lowest key, keeping the highest keys.
+    Lowest,
+    /// Drop the entry with the highest key, keeping the lowest keys.
+    Highest,
+}
+
+/// A tree which holds at most `capacity` entries.
+///
+/// When an insert would take it over capacity, an entry is evicted from
+/// the end given by its `Evict` policy, which makes it a cheap way to
+/// maintain the top (or bottom) N keys of a stream.
+pub struct BoundedPalmTree<K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    tree: PalmTree<K, V, C>,
+    capacity: usize,
+    evict: Evict,
+}
+
+impl<K, V, C> BoundedPalmTree<K, V, C>
+where
+    K: Clone + Ord,
+    C: TreeConfig<K, V>,
+{
+    /// Construct an empty tree holding at most `capacity` entries.
+    pub fn new(capacity: usize, evict: Evict) -> Self {
+        Self {
+            tree: PalmTree::new(),
+            capacity,
+            evict,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn evict(&self) -> Evict {
+        self.evict
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len() >= self.capacity
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.tree.get(key)
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V, C> {
+        self.tree.iter()
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        V: Clone,
+        Q: Ord + ?Sized,
+    {
+        self.tree.remove(key)
+    }
+
+    pub fn as_tree(&self) -> &PalmTree<K, V, C> {
+        &self.tree
+    }
+
+    pub fn into_tree(self) -> PalmTree<K, V, C> {
+        self.tree
+    }
+
+    /// Insert a key/value pair, evicting an entry if the tree is full.
+    ///
+    /// Returns the entry which is no longer in the tree as a result: the
+    /// evicted one, the old value if `key` was already present, or the new
+    /// entry itself if it falls on the evicting side of everything in a
+    /// full tree.
+    pub fn insert(&mut self, key: K, value: V) -> Option<(K, V)>
+    where
+        V: Clone,
+    {
+        if self.capacity == 0 {
+            return Some((key, value));
+        }
+        if self.is_full() && !self.tree.contains_key(&key) {
+            let edge = match self.evict {
+                Evict::Lowest => self.tree.iter().next(),
+                Evict::Highest => self.tree.iter().next_back(),
+            };
+            let outside = match (self.evict, edge) {
+                (Evict::Lowest, Some((edge, _))) => key < *edge,
+                (Evict::Highest, Some((edge, _))) => key > *edge,
+                (_, None) => false,
+            };
+            if outside {
+                return Some((key, value));
+            }
+            let evicted = match self.evict {
+                Evict::Lowest => self.tree.remove_lowest(),
+                Evict::Highest => self.tree.remove_highest(),
+            };
+            self.tree.insert(key, value);
+            return evicted;
+        }
+        let replaced_key = key.clone();
+        self.tree
+            .insert(key, value)
+            .map(|value| (replaced_key, value))
+    }
+}
+
+impl<K, V, C> Clone for BoundedPalmTree<K, V, C>
+where
+    K: Clone + Ord,
+    V: Clone,
+    C: TreeConfig<K, V>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            tree: self.tree.clone(),
+            capacity: self.capacity,
+            evict: self.evict,
+        }
+    }
+}
+
+impl<K, V, C> Debug for BoundedPalmTree<K, V, C>
+where
+    K: Clone + Ord + Debug,
+    V: Debug,
+    C: TreeConfig<K, V>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        f.debug_struct("BoundedPalmTree")
+            .field("capacity", &self.capacity)
+            .field("evict", &self.evict)
+            .field("tree", &self.tree)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Tree64;
+    use crate::Unique;
+
+    type Bounded<K, V> = BoundedPalmTree<K, V, Tree64<Unique>>;
+
+    #[test]
+    fn keeps_the_highest_keys() {
+        let mut tree: Bounded<usize, usize> = BoundedPalmTree::new(100, Evict::Lowest);
+        for i in 0..10_000 {
+            let key = (i * 7919) % 10_000;
+            tree.insert(key, i);
+            assert!(tree.len() <= 100);
+        }
+        assert!(tree.iter().map(|(k, _)| *k).eq(9900..10_000));
+    }
+
+    #[test]
+    fn keeps_the_lowest_keys() {
+        let mut tree: Bounded<usize, usize> = BoundedPalmTree::new(100, Evict::Highest);
+        for i in 0..10_000 {
+            tree.insert((i * 7919) % 10_000, i);
+        }
+        assert!(tree.iter().map(|(k, _)| *k).eq(0..100));
+    }
+
+    #[test]
+    fn reports_what_left_the_tree() {
+        let mut tree: Bounded<usize, &str> = BoundedPalmTree::new(2, Evict::Lowest);
+        assert_eq!(None, tree.insert(5, "five"));
+        assert_eq!(None, tree.insert(3, "three"));
+        assert_eq!(Some((5, "five")), tree.insert(5, "cinq"));
+        assert_eq!(Some((1, "one")), tree.insert(1, "one"));
+        assert_eq!(Some((3, "three")), tree.insert(8, "eight"));
+        assert_eq!(
+            vec![(&5, &"cinq"), (&8, &"eight")],
+            tree.iter().collect::<Vec<_>>()
+        );
+
+        let mut empty: Bounded<usize, ()> = BoundedPalmTree::new(0, Evict::Highest);
+        assert_eq!(Some((1, ())), empty.insert(1, ()));
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn borrowed_lookups() {
+        let mut tree: Bounded<String, usize> = BoundedPalmTree::new(2, Evict::Highest);
+        tree.insert("a".to_string(), 1);
+        tree.insert("b".to_string(), 2);
+        assert_eq!(Some(&2), tree.get("b"));
+        assert_eq!(Some(("a".to_string(), 1)), tree.remove("a"));
+        assert_eq!(None, tree.get("a"));
+    }
+}
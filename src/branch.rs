@@ -1,18 +1,106 @@
 use crate::{
     array::Array,
-    config::TreeConfig,
+    batch::{in_bounds, Op},
+    config::{split_len, TreeConfig},
+    invariant::{Invariant, InvariantError},
     leaf::Leaf,
     pointer::Pointer,
-    search::{find_key, find_key_linear},
+    search::{find_key, find_key_linear, find_key_or_next},
     InsertResult,
 };
+use alloc::{string::String, vec::Vec};
+use core::{
+    borrow::Borrow,
+    cmp::Ordering,
+    fmt::{Debug, Error, Formatter},
+    iter::Peekable,
+    ops::{Bound, Range, RangeBounds},
+    ptr::{addr_of, addr_of_mut},
+};
 use node::Node;
-use std::fmt::{Debug, Error, Formatter};
 use typenum::Unsigned;
 
 // Never leak this monster to the rest of the crate.
 pub(crate) mod node;
 
+/// Where `Branch::cut` cuts a tree: after a number of entries, in front of
+/// the first key at or above a given key, or in front of the first key
+/// above it.
+pub(crate) enum Cut<'a, Q: ?Sized> {
+    Rank(usize),
+    Key(&'a Q),
+    After(&'a Q),
+}
+
+/// A run of neighbouring children of a branch, borrowed mutably, which can
+/// be split up to hand to different threads without giving out `Node`s.
+#[cfg(feature = "rayon")]
+pub(crate) struct ChildrenMut<'a, K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    has_branches: bool,
+    children: &'a mut [Node<K, V, C>],
+}
+
+#[cfg(feature = "rayon")]
+pub(crate) enum ChildMut<'a, K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    Branch(ChildrenMut<'a, K, V, C>),
+    Leaf(&'a mut Leaf<K, V, C>),
+}
+
+#[cfg(feature = "rayon")]
+impl<'a, K, V, C> ChildrenMut<'a, K, V, C>
+where
+    K: Clone,
+    V: Clone,
+    C: TreeConfig<K, V>,
+{
+    pub(crate) fn len(&self) -> usize {
+        self.children.len()
+    }
+
+    pub(crate) fn split_at(self, index: usize) -> (Self, Self) {
+        let (left, right) = self.children.split_at_mut(index);
+        let has_branches = self.has_branches;
+        (
+            Self {
+                has_branches,
+                children: left,
+            },
+            Self {
+                has_branches,
+                children: right,
+            },
+        )
+    }
+
+    /// If this is a single branch, go down to its children, which can be
+    /// split again.
+    pub(crate) fn descend(self) -> Self {
+        if self.has_branches && self.children.len() == 1 {
+            let child = unsafe { self.children[0].as_branch_mut() };
+            child.children_run_mut().descend()
+        } else {
+            self
+        }
+    }
+
+    pub(crate) fn into_children(self) -> impl Iterator<Item = ChildMut<'a, K, V, C>> {
+        let has_branches = self.has_branches;
+        self.children.iter_mut().map(move |child| unsafe {
+            if has_branches {
+                ChildMut::Branch(child.as_branch_mut().children_run_mut())
+            } else {
+                ChildMut::Leaf(child.as_leaf_mut())
+            }
+        })
+    }
+}
+
 /// A branch node holds mappings of high keys to child nodes.
 pub(crate) struct Branch<K, V, C>
 where
@@ -54,23 +142,19 @@ where
     C: TreeConfig<K, V>,
 {
     fn clone(&self) -> Self {
-        let children = unsafe {
+        // As with `Leaf`, children are pushed one at a time, so that if a
+        // `clone` panics, what we've cloned so far is dropped along with `out`.
+        // Cloning a child's pointer deep copies it with a `Unique` config,
+        // but with a shared one, the copy shares its children with us.
+        let mut out = Self::new(self.has_branches);
+        for (key, child) in self.keys().iter().zip(self.children()) {
             if self.has_branches() {
-                self.children.clone_with(self.length, |node| {
-                    Pointer::new(node.as_branch().clone()).into()
-                })
+                out.push_branch(key.clone(), unsafe { child.clone_branch() });
             } else {
-                self.children.clone_with(self.length, |node| {
-                    Pointer::new(node.as_leaf().clone()).into()
-                })
+                out.push_leaf(key.clone(), unsafe { child.clone_leaf() });
             }
-        };
-        Self {
-            has_branches: self.has_branches,
-            length: self.length,
-            keys: unsafe { self.keys.clone(self.length) },
-            children,
         }
+        out
     }
 }
 
@@ -108,6 +192,13 @@ where
         &self.keys()[self.len() - 1]
     }
 
+    /// The key which will be the highest key of the left node after a `split`
+    /// moving `right_len` entries out.
+    #[inline(always)]
+    pub(crate) fn split_key(&self, right_len: usize) -> &K {
+        &self.keys()[self.len() - right_len - 1]
+    }
+
     #[inline(always)]
     pub(crate) fn has_leaves(&self) -> bool {
         !self.has_branches()
@@ -237,6 +328,18 @@ where
         result
     }
 
+    /// Drop the child at `index`, which has been left empty.
+    ///
+    /// If it was the last child, this lowers the branch's highest key, and
+    /// the caller has to bring the parent's separator for it down to match.
+    pub(crate) fn remove_empty_child(&mut self, index: usize) {
+        if self.has_branches() {
+            self.remove_branch(index);
+        } else {
+            self.remove_leaf(index);
+        }
+    }
+
     #[inline(always)]
     pub(crate) fn remove_last_branch(&mut self) -> (K, Pointer<Self, C::PointerKind>) {
         debug_assert!(self.has_branches());
@@ -310,27 +413,881 @@ where
         self.length += 2;
     }
 
+    /// Split this node in two, moving the last `right_len` entries into
+    /// the new right node.
     pub(crate) fn split(
         mut this: Pointer<Self, C::PointerKind>,
+        right_len: usize,
     ) -> (Pointer<Self, C::PointerKind>, Pointer<Self, C::PointerKind>)
     where
         K: Clone,
         V: Clone,
     {
-        let right = {
-            let this = Pointer::make_mut(&mut this);
-            let half = this.len() / 2;
-            let right = Pointer::new(Branch {
-                has_branches: this.has_branches,
-                length: half,
-                keys: unsafe { Array::steal_from(&mut this.keys, this.length, half) },
-                children: unsafe { Array::steal_from(&mut this.children, this.length, half) },
-            });
-            this.length -= half;
-            right
-        };
+        let this_mut = Pointer::make_mut(&mut this);
+        let right = Pointer::new(this_mut.split_off_children(this_mut.len() - right_len));
         (this, right)
     }
+
+    /// Move the children from `index` onwards into a new branch.
+    pub(crate) fn split_off_children(&mut self, index: usize) -> Self {
+        if index >= self.length {
+            return Self::new(self.has_branches);
+        }
+        let right = Branch {
+            has_branches: self.has_branches,
+            length: self.length - index,
+            keys: unsafe { Array::steal_from(&mut self.keys, self.length, index) },
+            children: unsafe { Array::steal_from(&mut self.children, self.length, index) },
+        };
+        self.length = index;
+        right
+    }
+
+    /// Move every child out of `other`, which must be of the same height,
+    /// onto the end of this branch.
+    pub(crate) fn append(&mut self, other: &mut Self) {
+        debug_assert!(self.has_branches == other.has_branches);
+        debug_assert!(self.length + other.length <= C::BranchSize::USIZE);
+        unsafe {
+            self.keys.append(self.length, &mut other.keys, other.length);
+            self.children
+                .append(self.length, &mut other.children, other.length);
+        }
+        self.length += other.length;
+        other.length = 0;
+    }
+
+    #[cfg(feature = "rayon")]
+    pub(crate) fn children_run_mut(&mut self) -> ChildrenMut<'_, K, V, C> {
+        ChildrenMut {
+            has_branches: self.has_branches,
+            children: self.children_mut(),
+        }
+    }
+
+    /// The number of levels of branches from this one down to the leaves,
+    /// counting this one.
+    pub(crate) fn height(&self) -> usize {
+        let mut height = 1;
+        let mut branch = self;
+        while branch.has_branches() {
+            branch = branch.get_branch(0);
+            height += 1;
+        }
+        height
+    }
+
+    /// Bring the separators down the last edge of this branch to the
+    /// highest key actually under it, so none of them are stale.
+    pub(crate) fn lower_highest(&mut self)
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let key = self.highest_present().clone();
+        let mut branch = self;
+        loop {
+            let index = branch.len() - 1;
+            branch.keys_mut()[index] = key.clone();
+            if !branch.has_branches() {
+                return;
+            }
+            branch = branch.get_branch_mut(index);
+        }
+    }
+
+    /// Join two branches of the same height, where every key under `left`
+    /// is below every key under `right`, into one, or into a new parent
+    /// of the two if their children don't fit in one.
+    pub(crate) fn join(
+        mut left: Pointer<Self, C::PointerKind>,
+        mut right: Pointer<Self, C::PointerKind>,
+    ) -> Pointer<Self, C::PointerKind>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        if left.len() + right.len() <= C::BranchSize::USIZE {
+            Pointer::make_mut(&mut left).append(Pointer::make_mut(&mut right));
+            left
+        } else {
+            let mut parent = Self::new(true);
+            parent.push_branch(left.highest().clone(), left);
+            parent.push_branch(right.highest().clone(), right);
+            Pointer::new(parent)
+        }
+    }
+
+    /// Graft `other`, a shorter branch whose keys all go on one side of
+    /// ours, onto the first edge of this branch if `front` is set, or the
+    /// last edge if it isn't, at the level where it fits as a child.
+    ///
+    /// Where the branch it goes into is full, it goes into a new sibling
+    /// instead, which we pass back up for the level above to take in the
+    /// same way, and if that's us, our caller.
+    pub(crate) fn graft(
+        &mut self,
+        height: usize,
+        other: Pointer<Self, C::PointerKind>,
+        other_height: usize,
+        front: bool,
+    ) -> Option<Pointer<Self, C::PointerKind>>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        debug_assert!(height > other_height);
+        let child = if height == other_height + 1 {
+            other
+        } else {
+            let index = if front { 0 } else { self.len() - 1 };
+            let sibling = self
+                .get_branch_mut(index)
+                .graft(height - 1, other, other_height, front);
+            if !front {
+                let highest = self.get_branch(index).highest().clone();
+                self.keys_mut()[index] = highest;
+            }
+            sibling?
+        };
+        let key = child.highest().clone();
+        if self.is_full() {
+            let mut sibling = Self::new(true);
+            sibling.push_branch(key, child);
+            return Some(Pointer::new(sibling));
+        }
+        if front {
+            self.push_front(key, child.into());
+        } else {
+            self.push_branch(key, child);
+        }
+        None
+    }
+
+    /// Add a child in front of the others.
+    fn push_front(&mut self, key: K, child: Node<K, V, C>) {
+        debug_assert!(!self.is_full());
+        unsafe {
+            self.keys.insert(self.length, 0, key);
+            self.children.insert(self.length, 0, child);
+        }
+        self.length += 1;
+    }
+
+    /// The number of entries under this branch.
+    pub(crate) fn count(&self) -> usize {
+        (0..self.len())
+            .map(|index| {
+                if self.has_branches() {
+                    self.get_branch(index).count()
+                } else {
+                    self.get_leaf(index).len()
+                }
+            })
+            .sum()
+    }
+
+    /// Move every entry from the cut onwards into a new branch of the same
+    /// height.
+    ///
+    /// We only cut through the nodes on the path down to the cut, and the
+    /// children past it on each level move across whole, so this copies
+    /// `O(log n)` nodes at most, plus, for a `Cut::Rank`, counting the
+    /// entries in front of the cut. Either side can end up empty, but no
+    /// child on either side is left empty.
+    pub(crate) fn cut<Q>(&mut self, cut: &mut Cut<'_, Q>) -> Self
+    where
+        K: Clone + Borrow<Q>,
+        V: Clone,
+        Q: Ord + ?Sized,
+    {
+        let index = match cut {
+            Cut::Rank(rank) => {
+                let mut index = 0;
+                while index < self.len() {
+                    let len = if self.has_branches() {
+                        self.get_branch(index).count()
+                    } else {
+                        self.get_leaf(index).len()
+                    };
+                    if *rank < len {
+                        break;
+                    }
+                    *rank -= len;
+                    index += 1;
+                }
+                index
+            }
+            Cut::Key(key) => find_key(self.keys(), *key).unwrap_or(self.len()),
+            Cut::After(key) => self.keys().partition_point(|k| k.borrow() <= *key),
+        };
+        let mut right = self.split_off_children(index + 1);
+        if index >= self.len() {
+            return right;
+        }
+        let separator = self.keys()[index].clone();
+        if self.has_branches() {
+            let child = self.get_branch_mut(index);
+            let child_right = child.cut(cut);
+            let left_highest = child.keys().last().cloned();
+            if !child_right.is_empty() {
+                right.push_front(separator, Pointer::new(child_right).into());
+            }
+            // The child we cut has to have a separator which matches its
+            // own last one, or searches would go looking past its end.
+            match left_highest {
+                Some(key) => self.keys_mut()[index] = key,
+                None => {
+                    self.remove_branch(index);
+                }
+            }
+        } else {
+            let leaf = self.get_leaf_mut(index);
+            let position = match cut {
+                Cut::Rank(rank) => *rank,
+                Cut::Key(key) => find_key(leaf.keys(), *key).unwrap_or(leaf.len()),
+                Cut::After(key) => leaf.keys().partition_point(|k| k.borrow() <= *key),
+            };
+            let leaf_right = leaf.split_off(position);
+            let left_highest = leaf.keys().last().cloned();
+            if !leaf_right.is_empty() {
+                right.push_front(separator, Pointer::new(leaf_right).into());
+            }
+            match left_highest {
+                Some(key) => self.keys_mut()[index] = key,
+                None => {
+                    self.remove_leaf(index);
+                }
+            }
+        }
+        right
+    }
+
+    /// Split the child at `index` into two adjacent children. `append` says
+    /// whether we're making room for a key past the end of that child.
+    ///
+    /// Everything which can call into user code (`make_mut` and cloning
+    /// the new separator key) happens before we take the child out of
+    /// the branch, so if it panics, the branch is left as it was.
+    pub(crate) fn split_child(&mut self, index: usize, append: bool)
+    where
+        K: Clone,
+        V: Clone,
+    {
+        debug_assert!(!self.is_full());
+        if self.has_branches() {
+            let child = self.get_branch_mut(index);
+            let right_len = split_len::<K, V, C>(child.len(), append);
+            let left_key = child.split_key(right_len).clone();
+            let (right_key, child) = self.remove_branch(index);
+            let (left, right) = Self::split(child, right_len);
+            self.insert_branch_pair(index, left_key, left, right_key, right);
+        } else {
+            let child = self.get_leaf_mut(index);
+            let right_len = split_len::<K, V, C>(child.len(), append);
+            let left_key = child.split_key(right_len).clone();
+            let (right_key, child) = self.remove_leaf(index);
+            let (left, right) = Leaf::split(child, right_len);
+            self.insert_leaf_pair(index, left_key, left, right_key, right);
+        }
+    }
+
+    /// Find the entry with the highest key for which `cmp` isn't `Greater`,
+    /// where `cmp` compares a key against some target.
+    ///
+    /// The branch keys are no help when the target falls between the keys
+    /// of two children, so then we carry on into the child before.
+    pub(crate) fn floor_by<F>(&self, cmp: &F) -> Option<(&K, &V)>
+    where
+        F: Fn(&K) -> Ordering,
+    {
+        if self.is_empty() {
+            return None;
+        }
+        let index = self
+            .keys()
+            .partition_point(|key| cmp(key) == Ordering::Less)
+            .min(self.len() - 1);
+        for index in (0..=index).rev() {
+            let found = if self.has_branches() {
+                self.get_branch(index).floor_by(cmp)
+            } else {
+                let leaf = self.get_leaf(index);
+                match leaf
+                    .keys()
+                    .partition_point(|key| cmp(key) != Ordering::Greater)
+                {
+                    0 => None,
+                    end => Some((&leaf.keys()[end - 1], &leaf.values()[end - 1])),
+                }
+            };
+            if found.is_some() {
+                return found;
+            }
+        }
+        None
+    }
+
+    /// Find the entry `rank` places from the start of this branch.
+    ///
+    /// If it's not here, `rank` is left reduced by the number of entries
+    /// below this branch, ready for the next one to carry on from.
+    pub(crate) fn get_by_rank(&self, rank: &mut usize) -> Option<(&K, &V)> {
+        for index in 0..self.len() {
+            if self.has_branches() {
+                let result = self.get_branch(index).get_by_rank(rank);
+                if result.is_some() {
+                    return result;
+                }
+            } else {
+                let leaf = self.get_leaf(index);
+                if *rank < leaf.len() {
+                    return Some((&leaf.keys()[*rank], &leaf.values()[*rank]));
+                }
+                *rank -= leaf.len();
+            }
+        }
+        None
+    }
+
+    /// Find the entry `rank` places from the start of this branch, with the
+    /// value mutable.
+    ///
+    /// We count our way past the children in front of it without touching
+    /// them, and only copy the shared nodes on the way down to the entry.
+    pub(crate) fn get_by_rank_mut(&mut self, mut rank: usize) -> Option<(&K, &mut V)>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let mut branch = self;
+        loop {
+            let mut index = 0;
+            loop {
+                if index == branch.len() {
+                    return None;
+                }
+                let len = if branch.has_branches() {
+                    branch.get_branch(index).count()
+                } else {
+                    branch.get_leaf(index).len()
+                };
+                if rank < len {
+                    break;
+                }
+                rank -= len;
+                index += 1;
+            }
+            if branch.has_branches() {
+                branch = branch.get_branch_mut(index);
+            } else {
+                let (keys, values) = branch.get_leaf_mut(index).entries_mut();
+                return Some((&keys[rank], &mut values[rank]));
+            }
+        }
+    }
+
+    /// Count the entries below this branch with keys below `key`.
+    ///
+    /// Every child in front of the one `key` would go in is below it, so we
+    /// count those whole and only go down into that one.
+    pub(crate) fn rank<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let index = find_key(self.keys(), key).unwrap_or(self.len());
+        let mut rank = 0;
+        for before in 0..index {
+            rank += if self.has_branches() {
+                self.get_branch(before).count()
+            } else {
+                self.get_leaf(before).len()
+            };
+        }
+        if index == self.len() {
+            rank
+        } else if self.has_branches() {
+            rank + self.get_branch(index).rank(key)
+        } else {
+            let leaf = self.get_leaf(index);
+            rank + leaf.keys().partition_point(|k| k.borrow() < key)
+        }
+    }
+
+    /// Write out the shape of the subtree under this branch, a node per line.
+    #[cfg(feature = "validate")]
+    pub(crate) fn shape(&self, out: &mut String, level: usize) {
+        use core::fmt::Write;
+        let indent = "    ".repeat(level);
+        let _ = writeln!(out, "{}Branch(len={})", indent, self.len());
+        for index in 0..self.len() {
+            if self.has_branches() {
+                self.get_branch(index).shape(out, level + 1);
+            } else {
+                let len = self.get_leaf(index).len();
+                let _ = writeln!(out, "{}    Leaf(len={})", indent, len);
+            }
+        }
+    }
+
+    /// Compare the entries below this branch with those below `other`, a
+    /// child at a time, skipping any child the two share if `skip_shared`
+    /// is set, which is only sound if every value is equal to itself.
+    ///
+    /// This only works while both have the same shape, since then each pair
+    /// of leaves holds the same run of positions in both trees, so we check
+    /// that first, and return `None` if they don't, before comparing any
+    /// entries.
+    pub(crate) fn shared_eq(&self, other: &Self, skip_shared: bool) -> Option<bool>
+    where
+        K: PartialEq,
+        V: PartialEq,
+    {
+        if self.same_shape(other) {
+            Some(self.same_shape_eq(other, skip_shared))
+        } else {
+            None
+        }
+    }
+
+    fn same_shape(&self, other: &Self) -> bool {
+        if core::ptr::eq(self, other) {
+            return true;
+        }
+        if self.has_branches() != other.has_branches() || self.len() != other.len() {
+            return false;
+        }
+        (0..self.len()).all(|index| {
+            if self.has_branches() {
+                self.get_branch(index).same_shape(other.get_branch(index))
+            } else {
+                self.get_leaf(index).len() == other.get_leaf(index).len()
+            }
+        })
+    }
+
+    fn same_shape_eq(&self, other: &Self, skip_shared: bool) -> bool
+    where
+        K: PartialEq,
+        V: PartialEq,
+    {
+        if skip_shared && core::ptr::eq(self, other) {
+            return true;
+        }
+        (0..self.len()).all(|index| {
+            if self.has_branches() {
+                self.get_branch(index)
+                    .same_shape_eq(other.get_branch(index), skip_shared)
+            } else {
+                let (left, right) = (self.get_leaf(index), other.get_leaf(index));
+                (skip_shared && core::ptr::eq(left, right))
+                    || (left.keys() == right.keys() && left.values() == right.values())
+            }
+        })
+    }
+
+    /// Make sure no node below this branch is shared with another tree.
+    pub(crate) fn make_unique(&mut self)
+    where
+        K: Clone,
+        V: Clone,
+    {
+        for index in 0..self.len() {
+            if self.has_branches() {
+                self.get_branch_mut(index).make_unique();
+            } else {
+                self.get_leaf_mut(index);
+            }
+        }
+    }
+
+    /// Make sure no node on the path down from this branch is shared with
+    /// another tree, picking the child to follow at each level by calling
+    /// `choose` with that branch's keys.
+    pub(crate) fn make_path_unique<F>(&mut self, choose: &mut F)
+    where
+        K: Clone,
+        V: Clone,
+        F: FnMut(&[K]) -> usize,
+    {
+        let mut branch = self;
+        while !branch.is_empty() {
+            let index = choose(branch.keys());
+            if branch.has_branches() {
+                branch = branch.get_branch_mut(index);
+            } else {
+                branch.get_leaf_mut(index);
+                return;
+            }
+        }
+    }
+
+    /// Follow the path `choose` picks down from this branch, and on the way
+    /// back up, even out any child on it which has dropped below half full
+    /// with its neighbour, or merge the two if they fit in one node.
+    ///
+    /// As with `make_path_unique`, the path has to be the one a removal just
+    /// took, so it's the only place a node can have shrunk.
+    pub(crate) fn rebalance_path<F>(&mut self, choose: &mut F)
+    where
+        K: Clone,
+        V: Clone,
+        F: FnMut(&[K]) -> usize,
+    {
+        if self.is_empty() {
+            return;
+        }
+        let index = choose(self.keys());
+        if self.has_branches() {
+            self.get_branch_mut(index).rebalance_path(choose);
+        }
+        if self.is_sparse(index) && self.len() > 1 {
+            self.rebalance_pair(index.min(self.len() - 2));
+        }
+    }
+
+    /// Whether the child at `index` is less than half full.
+    fn is_sparse(&self, index: usize) -> bool {
+        if self.has_branches() {
+            self.get_branch(index).len() < C::BranchSize::USIZE / 2
+        } else {
+            self.get_leaf(index).len() < C::LeafSize::USIZE / 2
+        }
+    }
+
+    /// Even out the children at `left` and `left + 1`, or merge them if
+    /// they fit in one node, which we return `true` for.
+    ///
+    /// The separator for the left child of the pair is cloned before we
+    /// move anything, so a panicking `clone` leaves the branch as it was.
+    fn rebalance_pair(&mut self, left: usize) -> bool
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let key = if self.has_branches() {
+            let (left_child, right_child) = self.branch_pair_mut(left);
+            let total = left_child.len() + right_child.len();
+            if total <= C::BranchSize::USIZE {
+                left_child.append(right_child);
+                None
+            } else {
+                Some(Self::even_out(left_child, right_child, total / 2))
+            }
+        } else {
+            let (left_child, right_child) = self.leaf_pair_mut(left);
+            let total = left_child.len() + right_child.len();
+            if total <= C::LeafSize::USIZE {
+                left_child.append(right_child);
+                None
+            } else {
+                Some(Leaf::even_out(left_child, right_child, total / 2))
+            }
+        };
+        match key {
+            Some(key) => {
+                self.keys_mut()[left] = key;
+                false
+            }
+            None => {
+                // The left child has taken over everything below the right
+                // one's separator.
+                let right_key = if self.has_branches() {
+                    self.remove_branch(left + 1).0
+                } else {
+                    self.remove_leaf(left + 1).0
+                };
+                self.keys_mut()[left] = right_key;
+                true
+            }
+        }
+    }
+
+    /// Repack the leaves under the first bottom level branch with any keys
+    /// above `after`, returning that branch's highest key, or `None` if no
+    /// branch has keys above `after`.
+    ///
+    /// This is one step of an incremental compaction, which goes through
+    /// the tree a branch at a time by passing back the key it got.
+    pub(crate) fn pack_next(&mut self, after: Option<&K>) -> Option<K>
+    where
+        K: Clone + Ord,
+        V: Clone,
+    {
+        let index = after.map_or(0, |after| self.keys().partition_point(|key| key <= after));
+        if index >= self.len() {
+            None
+        } else if self.has_branches() {
+            // The child's highest key is its separator here, which is above
+            // `after`, so it has a branch for us.
+            self.get_branch_mut(index).pack_next(after)
+        } else {
+            self.pack_leaves();
+            Some(self.highest().clone())
+        }
+    }
+
+    /// Fill each leaf under this branch from the ones after it, dropping
+    /// the leaves left empty, so that every leaf but the last is full.
+    ///
+    /// The last separator stays put, so the parent's separator for this
+    /// branch still matches it.
+    fn pack_leaves(&mut self)
+    where
+        K: Clone,
+        V: Clone,
+    {
+        debug_assert!(self.has_leaves());
+        let mut index = 0;
+        while index + 1 < self.len() {
+            let (left, right) = self.leaf_pair_mut(index);
+            if left.len() + right.len() <= C::LeafSize::USIZE {
+                left.append(right);
+                let right_key = self.remove_leaf(index + 1).0;
+                self.keys_mut()[index] = right_key;
+            } else {
+                if !left.is_full() {
+                    let key = Leaf::even_out(left, right, C::LeafSize::USIZE);
+                    self.keys_mut()[index] = key;
+                }
+                index += 1;
+            }
+        }
+    }
+
+    /// Move children between two neighbours until the left one has
+    /// `left_len` of them, returning its new separator.
+    fn even_out(left: &mut Self, right: &mut Self, left_len: usize) -> K
+    where
+        K: Clone,
+    {
+        if left.len() < left_len {
+            let moving = left_len - left.len();
+            let key = right.keys()[moving - 1].clone();
+            let rest = right.split_off_children(moving);
+            left.append(right);
+            *right = rest;
+            key
+        } else {
+            let key = left.keys()[left_len - 1].clone();
+            let mut moving = left.split_off_children(left_len);
+            moving.append(right);
+            *right = moving;
+            key
+        }
+    }
+
+    fn branch_pair_mut(&mut self, index: usize) -> (&mut Self, &mut Self)
+    where
+        K: Clone,
+        V: Clone,
+    {
+        debug_assert!(self.has_branches());
+        let (left, right) = self.children_mut().split_at_mut(index + 1);
+        unsafe { (left[index].as_branch_mut(), right[0].as_branch_mut()) }
+    }
+
+    fn leaf_pair_mut(&mut self, index: usize) -> (&mut Leaf<K, V, C>, &mut Leaf<K, V, C>)
+    where
+        K: Clone,
+        V: Clone,
+    {
+        debug_assert!(self.has_leaves());
+        let (left, right) = self.children_mut().split_at_mut(index + 1);
+        unsafe { (left[index].as_leaf_mut(), right[0].as_leaf_mut()) }
+    }
+
+    /// Find the children which could hold keys inside `range`.
+    fn children_in_range<Q, R>(&self, range: &R) -> Range<usize>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        // Each child holds keys above the separator before it, up to its own.
+        let keys = self.keys();
+        let start = keys
+            .iter()
+            .position(|upper| match range.start_bound() {
+                Bound::Included(start) => start <= upper.borrow(),
+                Bound::Excluded(start) => start < upper.borrow(),
+                Bound::Unbounded => true,
+            })
+            .unwrap_or(keys.len());
+        let end = (start + 1..keys.len())
+            .find(|index| {
+                let lower = keys[index - 1].borrow();
+                match range.end_bound() {
+                    Bound::Included(end) | Bound::Excluded(end) => end <= lower,
+                    Bound::Unbounded => false,
+                }
+            })
+            .unwrap_or(keys.len());
+        start..end
+    }
+
+    /// The highest key actually present below this branch, which can be lower
+    /// than its own highest key if it's been left stale by a remove.
+    pub(crate) fn highest_present(&self) -> &K {
+        let mut branch = self;
+        loop {
+            let index = branch.len() - 1;
+            if branch.has_branches() {
+                branch = branch.get_branch(index);
+            } else {
+                return branch.get_leaf(index).highest();
+            }
+        }
+    }
+
+    /// Make sure no node below this branch which could hold a key inside
+    /// `range` is shared with another tree.
+    pub(crate) fn make_range_unique<Q, R>(&mut self, range: &R)
+    where
+        K: Clone + Ord + Borrow<Q>,
+        V: Clone,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        for index in self.children_in_range(range) {
+            if self.has_branches() {
+                self.get_branch_mut(index).make_range_unique(range);
+            } else {
+                self.get_leaf_mut(index);
+            }
+        }
+    }
+
+    /// Replace every key below this branch inside `range`, in order, with the
+    /// next of the `shifted` keys.
+    ///
+    /// The separators for every child we've touched, and for the one before
+    /// them, are set to the highest key actually in the child afterwards, so
+    /// a stale separator can't end up above keys shifted down past it.
+    pub(crate) fn shift_keys<Q, R, I>(&mut self, range: &R, shifted: &mut I)
+    where
+        K: Clone + Ord + Borrow<Q>,
+        V: Clone,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+        I: Iterator<Item = K>,
+    {
+        let children = self.children_in_range(range);
+        if children.is_empty() {
+            return;
+        }
+        let first = children.start.saturating_sub(1);
+        for index in children.clone() {
+            if self.has_branches() {
+                self.get_branch_mut(index).shift_keys(range, shifted);
+            } else {
+                for key in self.get_leaf_mut(index).keys_mut() {
+                    if range.contains((*key).borrow()) {
+                        if let Some(shifted) = shifted.next() {
+                            *key = shifted;
+                        }
+                    }
+                }
+            }
+        }
+        for index in first..children.end {
+            let highest = if self.has_branches() {
+                self.get_branch(index).highest_present().clone()
+            } else {
+                self.get_leaf(index).highest().clone()
+            };
+            self.keys_mut()[index] = highest;
+        }
+    }
+
+    /// Remove every entry below this branch for which `f` returns `false`,
+    /// dropping any children left empty and evening out or merging any left
+    /// less than half full.
+    ///
+    /// `len` is decremented by one for each entry removed.
+    pub(crate) fn retain<F>(&mut self, f: &mut F, len: &mut usize)
+    where
+        K: Clone,
+        V: Clone,
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        // The rest of the tree assumes there are no empty nodes, so if `f`
+        // panics, the guard still removes the child we were working on if
+        // it's been left empty.
+        struct Guard<'a, K, V, C>
+        where
+            C: TreeConfig<K, V>,
+        {
+            branch: &'a mut Branch<K, V, C>,
+            index: usize,
+        }
+
+        impl<'a, K, V, C> Guard<'a, K, V, C>
+        where
+            C: TreeConfig<K, V>,
+        {
+            fn remove_if_empty(&mut self) -> bool {
+                let index = self.index;
+                let branch = &mut self.branch;
+                if index >= branch.len() {
+                    return false;
+                }
+                let empty = if branch.has_branches() {
+                    branch.get_branch(index).is_empty()
+                } else {
+                    branch.get_leaf(index).is_empty()
+                };
+                if empty {
+                    branch.remove_empty_child(index);
+                }
+                empty
+            }
+        }
+
+        impl<'a, K, V, C> Drop for Guard<'a, K, V, C>
+        where
+            C: TreeConfig<K, V>,
+        {
+            fn drop(&mut self) {
+                self.remove_if_empty();
+            }
+        }
+
+        let mut guard = Guard {
+            branch: self,
+            index: 0,
+        };
+        while guard.index < guard.branch.len() {
+            let index = guard.index;
+            if guard.branch.has_branches() {
+                let child = guard.branch.get_branch_mut(index);
+                child.retain(f, len);
+                // The child may have lost its last child, and with it the
+                // key our separator has to match.
+                if !child.is_empty() {
+                    let highest = child.highest().clone();
+                    guard.branch.keys_mut()[index] = highest;
+                }
+            } else {
+                guard.branch.get_leaf_mut(index).retain(f, len);
+            }
+            if guard.remove_if_empty() {
+                continue;
+            }
+            // We pair each child with the one before it, which `f` has
+            // already been through, so no entry is seen twice. Only the
+            // last child we've done can be left less than half full.
+            let branch = &mut *guard.branch;
+            if index > 0
+                && (branch.is_sparse(index) || branch.is_sparse(index - 1))
+                && branch.rebalance_pair(index - 1)
+            {
+                continue;
+            }
+            guard.index += 1;
+        }
+    }
 }
 
 impl<K, V, C> Branch<K, V, C>
@@ -363,7 +1320,11 @@ where
         }
     }
 
-    pub(crate) fn get(&self, key: &K) -> Option<&V> {
+    pub(crate) fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
         let mut branch = self;
         loop {
             if let Some(index) = find_key(branch.keys(), key) {
@@ -378,9 +1339,11 @@ where
         }
     }
 
-    pub(crate) fn get_mut(&mut self, key: &K) -> Option<&mut V>
+    pub(crate) fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
     where
+        K: Borrow<Q>,
         V: Clone,
+        Q: Ord + ?Sized,
     {
         let mut branch = self;
         loop {
@@ -399,6 +1362,140 @@ where
         }
     }
 
+    /// Find the value for `key` through raw pointers, for `get_many_mut`,
+    /// making the path down to it unique as we go.
+    ///
+    /// Making a node unique borrows it mutably, which would invalidate any
+    /// pointer we'd taken into it before, so we only do it once for each
+    /// child: `unique` remembers the children we've already been through
+    /// and the pointers we got for them, across calls.
+    pub(crate) unsafe fn value_ptr<Q>(
+        this: *mut Self,
+        key: &Q,
+        unique: &mut Vec<(*mut Node<K, V, C>, *mut ())>,
+    ) -> Option<*mut V>
+    where
+        K: Clone + Borrow<Q>,
+        V: Clone,
+        Q: Ord + ?Sized,
+    {
+        let mut branch = this;
+        loop {
+            let length = addr_of!((*branch).length).read();
+            let keys = (*addr_of!((*branch).keys)).deref(length);
+            let child = Array::raw_ptr(addr_of_mut!((*branch).children)).add(find_key(keys, key)?);
+            let has_branches = addr_of!((*branch).has_branches).read();
+            let node = match unique.iter().find(|(seen, _)| *seen == child) {
+                Some(&(_, node)) => node,
+                None => {
+                    let node = if has_branches {
+                        Node::branch_mut_ptr(child).cast()
+                    } else {
+                        Node::leaf_mut_ptr(child).cast()
+                    };
+                    unique.push((child, node));
+                    node
+                }
+            };
+            if has_branches {
+                branch = node.cast();
+            } else {
+                return Leaf::<K, V, C>::value_ptr(node.cast(), key);
+            }
+        }
+    }
+
+    /// Find the value for `key`, which must be in the tree, without checking
+    /// whether we found it.
+    pub(crate) unsafe fn get_unchecked(&self, key: &K) -> &V {
+        let mut branch = self;
+        loop {
+            let index = find_key_or_next(branch.keys(), key);
+            if branch.has_branches() {
+                branch = branch.get_branch_unchecked(index);
+            } else {
+                let leaf = branch.get_leaf_unchecked(index);
+                let index = find_key_or_next(leaf.keys(), key);
+                debug_assert!(leaf.keys()[index] == *key, "get_unchecked: key not found");
+                return leaf.values().get_unchecked(index);
+            }
+        }
+    }
+
+    pub(crate) unsafe fn get_mut_unchecked(&mut self, key: &K) -> &mut V
+    where
+        V: Clone,
+    {
+        let mut branch = self;
+        loop {
+            let index = find_key_or_next(branch.keys(), key);
+            if branch.has_branches() {
+                branch = branch.get_branch_mut(index);
+            } else {
+                let leaf = branch.get_leaf_mut(index);
+                let index = find_key_or_next(leaf.keys(), key);
+                debug_assert!(
+                    leaf.keys()[index] == *key,
+                    "get_mut_unchecked: key not found"
+                );
+                return leaf.values_mut().get_unchecked_mut(index);
+            }
+        }
+    }
+
+    /// Check the invariants of the subtree under this branch, returning the
+    /// number of entries in it and its height.
+    ///
+    /// Every key below the branch must be above `lower` and no higher than
+    /// `upper`. Separators are allowed to be higher than the keys they guard,
+    /// because removing a key doesn't update them.
+    pub(crate) fn check(
+        &self,
+        lower: Option<&K>,
+        upper: Option<&K>,
+    ) -> Result<(usize, usize), InvariantError> {
+        let mut entries = 0;
+        let mut height = None;
+        let mut previous = lower;
+        for (index, key) in self.keys().iter().enumerate() {
+            if previous.is_some_and(|previous| previous >= key) {
+                return Err(InvariantError::new(Invariant::SeparatorOrder(index)));
+            }
+            if upper.is_some_and(|upper| key > upper) {
+                return Err(InvariantError::new(Invariant::SeparatorBound(index)));
+            }
+            let (count, child_height) = if self.has_branches() {
+                let child = self.get_branch(index);
+                if child.is_empty() {
+                    return Err(InvariantError::new(Invariant::EmptyBranch).in_child(index));
+                }
+                let (count, child_height) = child
+                    .check(previous, Some(key))
+                    .map_err(|error| error.in_child(index))?;
+                (count, child_height + 1)
+            } else {
+                let leaf = self.get_leaf(index);
+                leaf.check(previous, key)
+                    .map_err(|error| error.in_child(index))?;
+                (leaf.len(), 1)
+            };
+            if height.is_some_and(|height| height != child_height) {
+                return Err(InvariantError::new(Invariant::Depth(index)));
+            }
+            height = Some(child_height);
+            entries += count;
+            previous = Some(key);
+        }
+        // Searches trust a separator to lead them to a child which has a
+        // separator at least as high.
+        if let (Some(upper), false) = (upper, self.is_empty()) {
+            if self.highest() != upper {
+                return Err(InvariantError::new(Invariant::LastSeparator));
+            }
+        }
+        Ok((entries, height.unwrap_or(1)))
+    }
+
     pub(crate) fn insert(&mut self, key: K, value: V) -> InsertResult<K, V>
     where
         V: Clone,
@@ -422,18 +1519,11 @@ where
             if self.is_full() {
                 // Current branch is full, needs to split further up.
                 InsertResult::Full(key, value)
-            } else if self.has_branches() {
-                // Split the child branch and retry insertion from here.
-                // FIXME should determine which of the split branches to insert into instead of rechecking from the parent branch.
-                // Same for leaf splitting below, and splitting in >max case further below.
-                let (removed_key, removed_branch) = self.remove_branch(index);
-                let (left, right) = Self::split(removed_branch);
-                self.insert_branch_pair(index, left.highest().clone(), left, removed_key, right);
-                self.insert(key, value)
             } else {
-                let (removed_key, removed_leaf) = self.remove_leaf(index);
-                let (left, right) = Leaf::split(removed_leaf);
-                self.insert_leaf_pair(index, left.highest().clone(), left, removed_key, right);
+                // Split the child and retry insertion from here.
+                // FIXME should determine which of the split children to insert into instead of rechecking from the parent branch.
+                // Same for splitting in >max case further below.
+                self.split_child(index, false);
                 self.insert(key, value)
             }
         } else {
@@ -459,9 +1549,7 @@ where
             if self.is_full() {
                 InsertResult::Full(key, value)
             } else if self.has_branches() {
-                let (removed_key, removed_branch) = self.remove_last_branch();
-                let (left, right) = Self::split(removed_branch);
-                self.push_branch_pair(left.highest().clone(), left, removed_key, right);
+                self.split_child(end_index, true);
                 self.insert(key, value)
             } else {
                 let leaf = Pointer::new(Leaf::unit(key.clone(), value));
@@ -470,12 +1558,98 @@ where
             }
         }
     }
+
+    /// Apply the ops at the front of `batch` for as long as their keys fall
+    /// between `lower` and `upper`, a child at a time, returning `false` if
+    /// we stopped at an insert which needs this branch split to fit.
+    ///
+    /// We only copy the children the ops land in, split them when they
+    /// fill up, and drop, even out or merge them when they empty out, so
+    /// the tree keeps the same shape inserting or removing each entry in
+    /// turn would have left it in, but we only walk down from the root
+    /// once for a run of ops in key order.
+    pub(crate) fn apply_batch<I>(
+        &mut self,
+        batch: &mut Peekable<I>,
+        lower: Option<&K>,
+        upper: Option<&K>,
+        len: &mut usize,
+        removed: &mut usize,
+    ) -> bool
+    where
+        V: Clone,
+        I: Iterator<Item = (K, Op<V>)>,
+    {
+        while let Some((key, _)) = batch.peek() {
+            if self.is_empty() || !in_bounds(key, lower, upper) {
+                return true;
+            }
+            let index = find_key(self.keys(), key).unwrap_or(self.len() - 1);
+            let child_lower = if index > 0 {
+                Some(self.keys()[index - 1].clone())
+            } else {
+                lower.cloned()
+            };
+            let child_upper = if index + 1 < self.len() {
+                Some(self.keys()[index].clone())
+            } else {
+                upper.cloned()
+            };
+            let (child_lower, child_upper) = (child_lower.as_ref(), child_upper.as_ref());
+            let fits = if self.has_branches() {
+                let child = self.get_branch_mut(index);
+                let fits = child.apply_batch(batch, child_lower, child_upper, len, removed);
+                // The child may have lost its last child, and with it the
+                // key our separator has to match.
+                if !child.is_empty() {
+                    let highest = child.highest().clone();
+                    self.keys_mut()[index] = highest;
+                }
+                fits
+            } else {
+                let fits = self.get_leaf_mut(index).apply_batch(
+                    batch,
+                    child_lower,
+                    child_upper,
+                    len,
+                    removed,
+                );
+                // Inserting on the right edge can go past our separator.
+                let leaf = self.get_leaf(index);
+                if !leaf.is_empty() && leaf.highest() > &self.keys()[index] {
+                    let highest = leaf.highest().clone();
+                    self.keys_mut()[index] = highest;
+                }
+                fits
+            };
+            if self.has_branches() && self.get_branch(index).is_empty()
+                || self.has_leaves() && self.get_leaf(index).is_empty()
+            {
+                self.remove_empty_child(index);
+            } else if !fits {
+                if self.is_full() {
+                    return false;
+                }
+                let append = match batch.peek() {
+                    Some((key, _)) if self.has_branches() => {
+                        key > self.get_branch(index).highest_present()
+                    }
+                    Some((key, _)) => key > self.get_leaf(index).highest(),
+                    None => false,
+                };
+                self.split_child(index, append);
+            } else if self.len() > 1 && self.is_sparse(index) {
+                self.rebalance_pair(index.min(self.len() - 2));
+            }
+        }
+        true
+    }
 }
 
 impl<K, V, C> Branch<K, V, C>
 where
-    K: Clone + Debug,
-    V: Clone + Debug,
+    K: Debug,
+    V: Debug,
     C: TreeConfig<K, V>,
 {
     fn tree_fmt(&self, f: &mut Formatter<'_>, level: usize) -> Result<(), Error> {
@@ -503,8 +1677,8 @@ where
 
 impl<K, V, C> Debug for Branch<K, V, C>
 where
-    K: Clone + Debug,
-    V: Clone + Debug,
+    K: Debug,
+    V: Debug,
     C: TreeConfig<K, V>,
 {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
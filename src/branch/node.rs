@@ -1,5 +1,5 @@
 use crate::{branch::Branch, config::TreeConfig, leaf::Leaf, pointer::Pointer};
-use std::{
+use core::{
     fmt::{Debug, Error, Formatter},
     marker::PhantomData,
     mem::ManuallyDrop,
@@ -51,6 +51,25 @@ where
         Pointer::cast_into(ManuallyDrop::into_inner(self.node))
     }
 
+    /// Clone the pointer to a branch, which only copies the branch if the
+    /// pointer kind isn't a shared one.
+    pub(crate) unsafe fn clone_branch(&self) -> Pointer<Branch<K, V, C>, C::PointerKind>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        Pointer::cast_into(Pointer::clone_cast::<Branch<K, V, C>>(&self.node))
+    }
+
+    /// Clone the pointer to a leaf. See `clone_branch`.
+    pub(crate) unsafe fn clone_leaf(&self) -> Pointer<Leaf<K, V, C>, C::PointerKind>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        Pointer::cast_into(Pointer::clone_cast::<Leaf<K, V, C>>(&self.node))
+    }
+
     #[inline(always)]
     pub(crate) unsafe fn as_branch(&self) -> &Branch<K, V, C> {
         Pointer::deref_cast(&self.node)
@@ -78,6 +97,28 @@ where
     {
         Pointer::make_mut_cast(&mut self.node)
     }
+
+    /// Make the branch under `this` unique, and get a raw pointer to it
+    /// without borrowing the branch `this` sits in.
+    #[inline(always)]
+    pub(crate) unsafe fn branch_mut_ptr(this: *mut Self) -> *mut Branch<K, V, C>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        Pointer::make_mut_cast(&mut *core::ptr::addr_of_mut!((*this).node))
+    }
+
+    /// Make the leaf under `this` unique, and get a raw pointer to it
+    /// without borrowing the branch `this` sits in.
+    #[inline(always)]
+    pub(crate) unsafe fn leaf_mut_ptr(this: *mut Self) -> *mut Leaf<K, V, C>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        Pointer::make_mut_cast(&mut *core::ptr::addr_of_mut!((*this).node))
+    }
 }
 
 impl<K, V, C> Debug for Node<K, V, C>
@@ -0,0 +1,255 @@
+use crate::{branch::Branch, config::TreeConfig, leaf::Leaf, pointer::Pointer, PalmTree};
+use alloc::vec::Vec;
+use core::fmt::{Debug, Error, Formatter};
+
+/// Sort entries by key, keeping only the last of any with the same key,
+/// as inserting them one at a time would have done.
+pub(crate) fn sort_entries<K: Ord, V>(entries: &mut Vec<(K, V)>) {
+    // A stable sort keeps duplicates in the order they came in.
+    entries.sort_by(|(left, _), (right, _)| left.cmp(right));
+    dedup_sorted_entries(entries);
+}
+
+/// Drop all but the last of each run of entries with the same key, from
+/// entries put in order by a stable sort.
+pub(crate) fn dedup_sorted_entries<K: Eq, V>(entries: &mut Vec<(K, V)>) {
+    entries.dedup_by(|later, earlier| {
+        if later.0 == earlier.0 {
+            core::mem::swap(later, earlier);
+            true
+        } else {
+            false
+        }
+    });
+}
+
+/// Build a tree incrementally from keys arriving in ascending order.
+///
+/// This is the machinery behind `PalmTree::load`, for when the entries
+/// don't come out of a single iterator: push them one at a time with
+/// `push`, in order, or in chunks with `extend`, and call `finish` to get
+/// the tree.
+///
+/// Only the nodes along the right edge of the tree under construction are
+/// held open, and everything to their left is already in its final shape,
+/// so there is nothing to flush while building from a long stream.
+pub struct PalmTreeBuilder<K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    size: usize,
+    stack: Vec<Pointer<Branch<K, V, C>, C::PointerKind>>,
+    parent: Branch<K, V, C>,
+    leaf: Leaf<K, V, C>,
+}
+
+fn push_stack<K, V, C>(
+    child: Pointer<Branch<K, V, C>, C::PointerKind>,
+    stack: &mut Vec<Pointer<Branch<K, V, C>, C::PointerKind>>,
+) where
+    K: Clone,
+    V: Clone,
+    C: TreeConfig<K, V>,
+{
+    let mut parent = stack.pop().unwrap_or_else(|| Branch::new(true).into());
+    if parent.is_full() {
+        push_stack(parent, stack);
+        parent = Pointer::new(Branch::new(true));
+    }
+    Pointer::make_mut(&mut parent).push_branch(child.highest().clone(), child);
+    stack.push(parent);
+}
+
+impl<K, V, C> PalmTreeBuilder<K, V, C>
+where
+    K: Clone + Ord,
+    V: Clone,
+    C: TreeConfig<K, V>,
+{
+    pub fn new() -> Self {
+        Self {
+            size: 0,
+            stack: Vec::new(),
+            parent: Branch::new(false),
+            leaf: Leaf::new(),
+        }
+    }
+
+    /// The number of entries pushed so far.
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The highest key pushed so far.
+    pub fn last_key(&self) -> Option<&K> {
+        // The current leaf is only ever empty before the first push.
+        if self.leaf.is_empty() {
+            None
+        } else {
+            Some(self.leaf.highest())
+        }
+    }
+
+    /// Push an entry onto the end of the tree.
+    ///
+    /// Panics if `key` isn't greater than every key pushed before it.
+    pub fn push(&mut self, key: K, value: V) {
+        if let Some(last_key) = self.last_key() {
+            if last_key >= &key {
+                panic!(
+                    "PalmTreeBuilder::push: unordered key at index {}",
+                    self.size
+                );
+            }
+        }
+        self.push_sorted(key, value);
+    }
+
+    /// Push an entry without checking that it's in order.
+    pub(crate) fn push_sorted(&mut self, key: K, value: V) {
+        if self.leaf.is_full() {
+            // If parent is full, push it to the parent above it on the stack.
+            if self.parent.is_full() {
+                let parent = core::mem::replace(&mut self.parent, Branch::new(false));
+                push_stack(Pointer::new(parent), &mut self.stack);
+            }
+
+            let leaf = core::mem::replace(&mut self.leaf, Leaf::new());
+            self.parent
+                .push_leaf(leaf.highest().clone(), Pointer::new(leaf));
+        }
+
+        // Push the input into the leaf.
+        unsafe { self.leaf.push_unchecked(key, value) };
+        self.size += 1;
+    }
+
+    /// Finish building, and return the tree.
+    pub fn finish(self) -> PalmTree<K, V, C> {
+        let Self {
+            size,
+            mut stack,
+            mut parent,
+            leaf,
+        } = self;
+
+        // If the input was empty, return immediately with an empty tree.
+        if size == 0 {
+            return PalmTree::new();
+        }
+
+        // At end of input, push last leaf into parent, as above.
+        if parent.is_full() {
+            push_stack(Pointer::new(parent), &mut stack);
+            parent = Branch::new(false);
+        }
+        parent.push_leaf(leaf.highest().clone(), Pointer::new(leaf));
+
+        // Push parent into the parent above it.
+        push_stack(Pointer::new(parent), &mut stack);
+
+        // Fold parent stack into the top level parent.
+        while stack.len() > 1 {
+            let parent = stack.pop().unwrap();
+            push_stack(parent, &mut stack);
+        }
+
+        // The root is now the only item left on the stack.
+        let mut tree = PalmTree {
+            size,
+            removed: 0,
+            packed: None,
+            root: stack.pop(),
+        };
+        tree.trim_root();
+        tree.validate();
+        tree
+    }
+}
+
+impl<K, V, C> Default for PalmTreeBuilder<K, V, C>
+where
+    K: Clone + Ord,
+    V: Clone,
+    C: TreeConfig<K, V>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Push a chunk of entries, which must carry on in order from what's been
+/// pushed already.
+impl<K, V, C> Extend<(K, V)> for PalmTreeBuilder<K, V, C>
+where
+    K: Clone + Ord,
+    V: Clone,
+    C: TreeConfig<K, V>,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.push(key, value);
+        }
+    }
+}
+
+impl<K, V, C> Debug for PalmTreeBuilder<K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "PalmTreeBuilder(len={})", self.size)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::StdPalmTree;
+
+    #[test]
+    fn build_incrementally() {
+        for size in &[0usize, 1, 64, 65, 4096, 4097, 100_000] {
+            let mut builder = PalmTreeBuilder::new();
+            for i in 0..*size {
+                builder.push(i, i * 2);
+            }
+            assert_eq!(*size, builder.len());
+            let tree: StdPalmTree<usize, usize> = builder.finish();
+            assert_eq!(*size, tree.len());
+            assert!(tree
+                .iter()
+                .map(|(k, v)| (*k, *v))
+                .eq((0..*size).map(|i| (i, i * 2))));
+        }
+    }
+
+    #[test]
+    fn build_in_chunks() {
+        let mut builder = PalmTreeBuilder::new();
+        for chunk in 0..100 {
+            builder.extend((chunk * 1000..(chunk + 1) * 1000).map(|i| (i, i)));
+            assert_eq!(Some(&(chunk * 1000 + 999)), builder.last_key());
+        }
+        let tree: StdPalmTree<usize, usize> = builder.finish();
+        tree.check_invariants().unwrap();
+        assert!(tree
+            .iter()
+            .map(|(k, v)| (*k, *v))
+            .eq((0..100_000).map(|i| (i, i))));
+    }
+
+    #[test]
+    #[should_panic(expected = "unordered key at index 2")]
+    fn push_out_of_order() {
+        let mut builder: PalmTreeBuilder<usize, (), _> = PalmTreeBuilder::new();
+        builder.push(1, ());
+        builder.push(2, ());
+        builder.push(2, ());
+        let _: StdPalmTree<usize, ()> = builder.finish();
+    }
+}
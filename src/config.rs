@@ -1,18 +1,151 @@
+#![allow(deprecated)] // generic-array 0.14 deprecates `ArrayLength` in favour of 1.x
+
 use crate::{branch::node::Node, PointerKind};
 use generic_array::ArrayLength;
-use std::marker::PhantomData;
-use typenum::{IsGreater, U3, U64};
+use typenum::{IsGreater, U3};
 
 pub trait TreeConfig<K, V> {
     type BranchSize: ArrayLength<K> + ArrayLength<Node<K, V, Self>> + IsGreater<U3>;
     type LeafSize: ArrayLength<K> + ArrayLength<V> + IsGreater<U3>;
     type PointerKind: PointerKind;
+
+    /// Compact the tree automatically once the number of entries removed
+    /// since it was last compacted passes this percentage of its length.
+    ///
+    /// Removals keep nodes at least half full, but a tree that's lost a lot
+    /// of entries can still take up to twice the space a freshly loaded one
+    /// would. The default, `None`, leaves it to you to call
+    /// `PalmTree::compact` when that matters.
+    ///
+    /// Compaction goes a step at a time rather than all at once. Each
+    /// removal after it starts repacks the leaves under one bottom level
+    /// branch, until it's been through the whole tree, so on top of its own
+    /// work a removal moves up to `BranchSize * LeafSize` entries. Only the
+    /// leaves are repacked; `PalmTree::compact` repacks the branches too.
+    const AUTO_COMPACT_PERCENT: Option<usize> = None;
+
+    /// The percentage of a full node's entries which stay in the left node
+    /// when it's split to make room for a key higher than any in it.
+    ///
+    /// Nodes which split anywhere else always split down the middle. With
+    /// the default of 50, so do these, which leaves every node but the last
+    /// half empty when keys are inserted in ascending order. Set it nearer
+    /// to 100 if that's most of your inserts.
+    const APPEND_SPLIT_PERCENT: usize = 50;
+}
+
+/// How many of the `len` entries in a full node go to the right node when
+/// it's split. `append` says whether the insert causing the split is going
+/// past the end of the node.
+pub(crate) fn split_len<K, V, C>(len: usize, append: bool) -> usize
+where
+    C: TreeConfig<K, V> + ?Sized,
+{
+    if append {
+        let percent = C::APPEND_SPLIT_PERCENT.min(100);
+        (len * (100 - percent) / 100).clamp(1, len - 1)
+    } else {
+        len / 2
+    }
+}
+
+/// Define a `TreeConfig` with the given branch and leaf sizes, generic
+/// over its pointer kind like `Tree64`.
+///
+/// Leaves must hold at least 4 entries, and branches at least 16 children,
+/// or a cursor might not have room for the path down the tree. A config
+/// with smaller branches fails to compile. The config gets the default
+/// `AUTO_COMPACT_PERCENT` and `APPEND_SPLIT_PERCENT`; implement
+/// `TreeConfig` yourself to change those.
+///
+/// ```
+/// # use palmtree::{tree_config, PalmTree, Unique};
+/// tree_config! {
+///     /// Wide branches, with small leaves for big values.
+///     pub WideConfig, branch: 128, leaf: 8
+/// }
+///
+/// let mut tree: PalmTree<u32, [u8; 1024], WideConfig<Unique>> = PalmTree::new();
+/// tree.insert(1, [0; 1024]);
+/// ```
+///
+/// ```compile_fail
+/// # use palmtree::{tree_config, PalmTree, Unique};
+/// tree_config! {
+///     TinyConfig, branch: 4, leaf: 4
+/// }
+///
+/// let tree: PalmTree<u32, u32, TinyConfig<Unique>> = PalmTree::load((0..1000).map(|i| (i, i)));
+/// assert_eq!(1000, tree.iter().count());
+/// ```
+#[macro_export]
+macro_rules! tree_config {
+    ($(#[$attr:meta])* $vis:vis $name:ident, branch: $branch:literal, leaf: $leaf:literal) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Copy)]
+        $vis struct $name<Kind: $crate::PointerKind>(::core::marker::PhantomData<Kind>);
+
+        impl<K, V, Kind: $crate::PointerKind> $crate::TreeConfig<K, V> for $name<Kind> {
+            type BranchSize = $crate::__typenum::U<$branch>;
+            type LeafSize = $crate::__typenum::U<$leaf>;
+            type PointerKind = Kind;
+        }
+    };
+}
+
+tree_config! {
+    /// Nodes of 16, which keep the cost of shifting entries about in a leaf
+    /// down when values are large.
+    pub Tree16, branch: 16, leaf: 16
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct Tree64<Kind: PointerKind>(PhantomData<Kind>);
-impl<K, V, Kind: PointerKind> TreeConfig<K, V> for Tree64<Kind> {
-    type BranchSize = U64;
-    type LeafSize = U64;
-    type PointerKind = Kind;
+tree_config! {
+    /// Nodes of 64, which is a good fit for most keys and values.
+    pub Tree64, branch: 64, leaf: 64
+}
+
+tree_config! {
+    /// Nodes of 128.
+    pub Tree128, branch: 128, leaf: 128
+}
+
+tree_config! {
+    /// Nodes of 256, which make for shallower trees and, with small keys and
+    /// values such as integers, faster lookups and iteration.
+    pub Tree256, branch: 256, leaf: 256
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{PalmTree, Shared, Unique};
+    use std::collections::BTreeMap;
+
+    tree_config! {
+        Lopsided, branch: 16, leaf: 100
+    }
+
+    fn churn<C: TreeConfig<u32, u32>>() {
+        let mut tree: PalmTree<u32, u32, C> = PalmTree::new();
+        let mut nat = BTreeMap::new();
+        for i in 0..20_000u32 {
+            let key = i.wrapping_mul(7919) % 5000;
+            if i % 3 == 0 {
+                assert_eq!(nat.remove(&key), tree.remove(&key).map(|(_, v)| v));
+            } else {
+                assert_eq!(nat.insert(key, i), tree.insert(key, i));
+            }
+        }
+        tree.check_invariants().unwrap();
+        assert!(nat.iter().eq(tree.iter()));
+    }
+
+    #[test]
+    fn presets() {
+        churn::<Tree16<Unique>>();
+        churn::<Tree64<Shared>>();
+        churn::<Tree128<Unique>>();
+        churn::<Tree256<Unique>>();
+        churn::<Lopsided<Unique>>();
+    }
 }
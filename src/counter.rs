@@ -0,0 +1,266 @@
+use crate::{config::TreeConfig, Entry, Iter, PalmTree};
+use core::{
+    borrow::Borrow,
+    fmt::{Debug, Error, Formatter},
+    iter::FromIterator,
+};
+
+/// A count for each of a set of keys, kept in key order.
+///
+/// Keys which aren't in the counter have a count of zero, and a key whose
+/// count drops to zero is removed, so the tree underneath only ever holds
+/// counts above zero.
+///
+/// ```
+/// # use palmtree::{PalmCounter, Tree64, Unique};
+/// let mut words: PalmCounter<&str, Tree64<Unique>> =
+///     "the cat sat on the mat".split(' ').collect();
+/// assert_eq!(2, words.get(&"the"));
+/// assert_eq!(0, words.get(&"dog"));
+/// words.decrement(&"cat", 1);
+/// assert!(!words.contains_key(&"cat"));
+/// assert_eq!(vec![("mat", 1), ("on", 1), ("sat", 1), ("the", 2)],
+///            words.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>());
+/// ```
+pub struct PalmCounter<K, C>
+where
+    C: TreeConfig<K, usize>,
+{
+    tree: PalmTree<K, usize, C>,
+}
+
+impl<K, C> PalmCounter<K, C>
+where
+    K: Clone + Ord,
+    C: TreeConfig<K, usize>,
+{
+    pub fn new() -> Self {
+        Self {
+            tree: PalmTree::new(),
+        }
+    }
+
+    /// The number of keys with a count above zero.
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.tree.get(key).copied().unwrap_or(0)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.tree.contains_key(key)
+    }
+
+    /// Add `by` to the count for `key`, stopping at `usize::MAX`, and
+    /// return the new count.
+    pub fn increment(&mut self, key: K, by: usize) -> usize {
+        if by == 0 {
+            return self.get(&key);
+        }
+        match self.tree.entry(key) {
+            Entry::Occupied(mut entry) => {
+                let count = entry.get_mut();
+                *count = count.saturating_add(by);
+                *count
+            }
+            Entry::Vacant(entry) => *entry.insert(by),
+        }
+    }
+
+    /// Add `by` to the count for `key` and return the new count, or leave
+    /// it alone and return `None` if it would overflow.
+    pub fn checked_increment(&mut self, key: K, by: usize) -> Option<usize> {
+        let count = self.get(&key).checked_add(by)?;
+        self.increment(key, by);
+        Some(count)
+    }
+
+    /// Take `by` off the count for `key`, stopping at zero and removing the
+    /// key if it gets there, and return the new count.
+    pub fn decrement<Q>(&mut self, key: &Q, by: usize) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let count = match self.tree.get_mut(key) {
+            Some(count) => {
+                *count = count.saturating_sub(by);
+                *count
+            }
+            None => return 0,
+        };
+        if count == 0 {
+            self.tree.remove(key);
+        }
+        count
+    }
+
+    /// Take `by` off the count for `key` and return the new count, or leave
+    /// it alone and return `None` if it's less than `by`.
+    pub fn checked_decrement<Q>(&mut self, key: &Q, by: usize) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(key).checked_sub(by)?;
+        Some(self.decrement(key, by))
+    }
+
+    /// Remove `key`, returning the count it had.
+    pub fn remove<Q>(&mut self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.tree.remove(key).map(|(_, count)| count).unwrap_or(0)
+    }
+
+    /// The sum of all the counts, which has to visit every key.
+    pub fn total(&self) -> usize {
+        self.tree
+            .iter()
+            .fold(0, |total: usize, (_, count)| total.saturating_add(*count))
+    }
+
+    /// Iterate over the keys and their counts, in key order.
+    pub fn iter(&self) -> Iter<'_, K, usize, C> {
+        self.tree.iter()
+    }
+
+    /// The tree of counts underneath.
+    pub fn as_tree(&self) -> &PalmTree<K, usize, C> {
+        &self.tree
+    }
+
+    pub fn into_tree(self) -> PalmTree<K, usize, C> {
+        self.tree
+    }
+
+    pub fn clear(&mut self) {
+        self.tree = PalmTree::new();
+    }
+}
+
+impl<K, C> Default for PalmCounter<K, C>
+where
+    K: Clone + Ord,
+    C: TreeConfig<K, usize>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, C> Clone for PalmCounter<K, C>
+where
+    K: Clone + Ord,
+    C: TreeConfig<K, usize>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            tree: self.tree.clone(),
+        }
+    }
+}
+
+impl<K, C> PartialEq for PalmCounter<K, C>
+where
+    K: Clone + Ord,
+    C: TreeConfig<K, usize>,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.tree == other.tree
+    }
+}
+
+impl<K, C> Debug for PalmCounter<K, C>
+where
+    K: Clone + Ord + Debug,
+    C: TreeConfig<K, usize>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, C> Extend<K> for PalmCounter<K, C>
+where
+    K: Clone + Ord,
+    C: TreeConfig<K, usize>,
+{
+    fn extend<I: IntoIterator<Item = K>>(&mut self, iter: I) {
+        for key in iter {
+            self.increment(key, 1);
+        }
+    }
+}
+
+impl<K, C> FromIterator<K> for PalmCounter<K, C>
+where
+    K: Clone + Ord,
+    C: TreeConfig<K, usize>,
+{
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        let mut counter = Self::new();
+        counter.extend(iter);
+        counter
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Tree64, Unique};
+
+    #[test]
+    fn count_and_prune() {
+        let mut counter: PalmCounter<u32, Tree64<Unique>> = (0..10_000).map(|i| i % 1000).collect();
+        assert_eq!(1000, counter.len());
+        assert_eq!(10_000, counter.total());
+        assert!(counter.iter().all(|(_, count)| *count == 10));
+
+        for key in (0..1000).step_by(2) {
+            assert_eq!(5, counter.decrement(&key, 5));
+            assert_eq!(0, counter.decrement(&key, 50));
+            assert_eq!(None, counter.checked_decrement(&key, 1));
+        }
+        assert_eq!(500, counter.len());
+        assert!(counter.iter().all(|(key, _)| key % 2 == 1));
+        assert_eq!(Some(0), counter.checked_decrement(&1, 10));
+        assert!(!counter.contains_key(&1));
+        assert_eq!(None, counter.checked_decrement(&3, 11));
+        assert_eq!(10, counter.get(&3));
+
+        assert_eq!(usize::MAX, counter.increment(5, usize::MAX));
+        assert_eq!(None, counter.checked_increment(5, 1));
+        assert_eq!(Some(usize::MAX), counter.checked_increment(5, 0));
+        assert_eq!(Some(7), counter.checked_increment(2000, 7));
+        assert_eq!(0, counter.increment(2001, 0));
+        assert!(!counter.contains_key(&2001));
+        assert_eq!(usize::MAX, counter.remove(&5));
+        assert_eq!(0, counter.remove(&5));
+
+        // Owned keys can be counted down by borrowed ones.
+        let mut words: PalmCounter<String, Tree64<Unique>> =
+            "a b a".split(' ').map(String::from).collect();
+        assert_eq!(2, words.get("a"));
+        assert_eq!(1, words.decrement("a", 1));
+        assert_eq!(Some(0), words.checked_decrement("b", 1));
+        assert!(!words.contains_key("b"));
+        assert_eq!(1, words.remove("a"));
+    }
+}
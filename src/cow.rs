@@ -0,0 +1,135 @@
+use crate::{config::TreeConfig, PalmTree};
+use core::{
+    borrow::Borrow,
+    fmt::{Debug, Error, Formatter},
+    ops::Deref,
+    ptr::NonNull,
+};
+
+/// A value in a tree which isn't copied out of any nodes it shares with
+/// other trees until you ask to change it. See `PalmTree::get_cow`.
+///
+/// It derefs to the value as it stands. `to_mut` copies the path down to
+/// the value, as `get_mut` would have done, the first time it's called.
+///
+/// `Q` is the type of the key it was looked up by, which is `K` unless
+/// you looked it up by something `K` borrows as.
+pub struct CowValue<'a, K, V, C, Q: ?Sized = K>
+where
+    C: TreeConfig<K, V>,
+{
+    tree: &'a mut PalmTree<K, V, C>,
+    key: &'a Q,
+    value: NonNull<V>,
+    /// Whether we've already copied the path to the value.
+    unique: bool,
+}
+
+impl<'a, K, V, C, Q> CowValue<'a, K, V, C, Q>
+where
+    K: Borrow<Q> + Clone + Ord,
+    V: Clone,
+    C: TreeConfig<K, V>,
+    Q: Ord + ?Sized,
+{
+    pub(crate) fn new(tree: &'a mut PalmTree<K, V, C>, key: &'a Q) -> Option<Self> {
+        let value = NonNull::from(tree.get(key)?);
+        Some(Self {
+            tree,
+            key,
+            value,
+            unique: false,
+        })
+    }
+
+    pub fn key(&self) -> &Q {
+        self.key
+    }
+
+    /// Get a mutable reference to the value, copying the nodes leading to
+    /// it first if they're shared with another tree.
+    pub fn to_mut(&mut self) -> &mut V {
+        if !self.unique {
+            self.value = NonNull::from(self.tree.get_mut(self.key).unwrap());
+            self.unique = true;
+        }
+        // We hold the only reference to the tree, so nothing can have moved
+        // the value since we found it.
+        unsafe { self.value.as_mut() }
+    }
+
+    /// Turn this into a mutable reference to the value, copying the nodes
+    /// leading to it first if they're shared with another tree.
+    pub fn into_mut(mut self) -> &'a mut V {
+        self.to_mut();
+        unsafe { &mut *self.value.as_ptr() }
+    }
+}
+
+impl<'a, K, V, C, Q> Deref for CowValue<'a, K, V, C, Q>
+where
+    Q: ?Sized,
+    C: TreeConfig<K, V>,
+{
+    type Target = V;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.value.as_ref() }
+    }
+}
+
+impl<'a, K, V, C, Q> Debug for CowValue<'a, K, V, C, Q>
+where
+    V: Debug,
+    Q: ?Sized,
+    C: TreeConfig<K, V>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        Debug::fmt(self.deref(), f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ImPalmTree;
+
+    #[test]
+    fn copy_on_write() {
+        let mut tree: ImPalmTree<usize, usize> = ImPalmTree::load((0..10_000).map(|i| (i, i)));
+        let copy = tree.clone();
+        assert!(tree.get_cow(&10_000).is_none());
+
+        // Reading doesn't change anything.
+        let value = tree.get_cow(&5000).unwrap();
+        assert_eq!(5000, *value);
+        assert_eq!(&5000, value.key());
+        assert!(std::ptr::eq(&*value, copy.get(&5000).unwrap()));
+
+        let mut value = tree.get_cow(&5000).unwrap();
+        *value.to_mut() += 1;
+        assert_eq!(5001, *value);
+        *value.to_mut() += 1;
+        assert_eq!(5002, *value);
+        *tree.get_cow(&6000).unwrap().into_mut() = 0;
+
+        assert_eq!(Some(&5002), tree.get(&5000));
+        assert_eq!(Some(&0), tree.get(&6000));
+        assert_eq!(Some(&5000), copy.get(&5000));
+        assert_eq!(Some(&6000), copy.get(&6000));
+        assert!(copy.iter().all(|(k, v)| k == v));
+    }
+
+    #[test]
+    fn borrowed_key() {
+        use alloc::string::{String, ToString};
+        let mut tree: ImPalmTree<String, usize> =
+            ImPalmTree::load((1000..2000).map(|i| (i.to_string(), i)));
+        let copy = tree.clone();
+        assert!(tree.get_cow("2000").is_none());
+        let mut value = tree.get_cow("1500").unwrap();
+        assert_eq!("1500", value.key());
+        *value.to_mut() = 0;
+        assert_eq!(Some(&0), tree.get("1500"));
+        assert_eq!(Some(&1500), copy.get("1500"));
+    }
+}
@@ -0,0 +1,462 @@
+use crate::{
+    branch::Branch,
+    config::TreeConfig,
+    pointer::Pointer,
+    search::{find_key, PathedPointer},
+    PalmTree,
+};
+use core::{
+    borrow::Borrow,
+    fmt::{Debug, Error, Formatter},
+    ops::Bound,
+};
+
+/// Find the first entry inside `bound` for a lower bound, or the last one
+/// for an upper bound.
+fn seek<Lifetime, K, V, C, Q>(
+    tree: &Branch<K, V, C>,
+    bound: Bound<&Q>,
+    upper: bool,
+) -> PathedPointer<Lifetime, K, V, C>
+where
+    K: Clone + Ord + Borrow<Q>,
+    Q: Ord + ?Sized,
+    C: TreeConfig<K, V>,
+{
+    match (bound, upper) {
+        (Bound::Included(key), false) => PathedPointer::key_or_higher(tree, key),
+        (Bound::Excluded(key), false) => PathedPointer::higher_than_key(tree, key),
+        (Bound::Unbounded, false) => PathedPointer::lowest(tree),
+        (Bound::Included(key), true) => PathedPointer::key_or_lower(tree, key),
+        (Bound::Excluded(key), true) => PathedPointer::lower_than_key(tree, key),
+        (Bound::Unbounded, true) => PathedPointer::highest(tree),
+    }
+}
+
+/// A cursor over the entries of a tree, which can move back and forth
+/// between them a step at a time.
+///
+/// A cursor points at an entry, or at nothing, which sits between the
+/// highest entry and the lowest: stepping forward from the highest entry
+/// gets you nothing, and stepping forward again gets you the lowest. Get
+/// one with `PalmTree::lower_bound` or `PalmTree::upper_bound`.
+///
+/// ```
+/// # use palmtree::StdPalmTree;
+/// # use std::ops::Bound;
+/// let tree = StdPalmTree::load((0..10).map(|i| (i * 2, i)));
+/// let mut cursor = tree.lower_bound(Bound::Included(&5));
+/// assert_eq!(Some((&6, &3)), cursor.key_value());
+/// cursor.move_prev();
+/// assert_eq!(Some(&4), cursor.key());
+/// assert_eq!(Some((&2, &1)), cursor.peek_prev());
+/// ```
+pub struct Cursor<'a, K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    tree: &'a PalmTree<K, V, C>,
+    ptr: PathedPointer<&'a (K, V), K, V, C>,
+}
+
+impl<'a, K, V, C> Cursor<'a, K, V, C>
+where
+    K: Clone + Ord,
+    C: 'a + TreeConfig<K, V>,
+{
+    pub(crate) fn new<Q>(tree: &'a PalmTree<K, V, C>, bound: Bound<&Q>, upper: bool) -> Self
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let ptr = match tree.root {
+            Some(ref root) if !tree.is_empty() => seek(root, bound, upper),
+            _ => PathedPointer::null(),
+        };
+        Self { tree, ptr }
+    }
+
+    pub fn key(&self) -> Option<&'a K> {
+        self.key_value().map(|(key, _)| key)
+    }
+
+    pub fn value(&self) -> Option<&'a V> {
+        self.key_value().map(|(_, value)| value)
+    }
+
+    pub fn key_value(&self) -> Option<(&'a K, &'a V)> {
+        if self.ptr.is_null() {
+            None
+        } else {
+            Some(unsafe { self.ptr.clone().into_entry() })
+        }
+    }
+
+    /// Step forward to the next entry, or from nothing to the lowest entry.
+    pub fn move_next(&mut self) {
+        self.ptr = next(&self.tree.root, &self.ptr, self.tree.is_empty());
+    }
+
+    /// Step back to the previous entry, or from nothing to the highest
+    /// entry.
+    pub fn move_prev(&mut self) {
+        self.ptr = prev(&self.tree.root, &self.ptr, self.tree.is_empty());
+    }
+
+    /// Look at the entry `move_next` would step to, without moving.
+    pub fn peek_next(&self) -> Option<(&'a K, &'a V)> {
+        let mut cursor = self.clone();
+        cursor.move_next();
+        cursor.key_value()
+    }
+
+    /// Look at the entry `move_prev` would step to, without moving.
+    pub fn peek_prev(&self) -> Option<(&'a K, &'a V)> {
+        let mut cursor = self.clone();
+        cursor.move_prev();
+        cursor.key_value()
+    }
+}
+
+fn next<Lifetime, K, V, C>(
+    root: &Option<Pointer<Branch<K, V, C>, C::PointerKind>>,
+    ptr: &PathedPointer<Lifetime, K, V, C>,
+    empty: bool,
+) -> PathedPointer<Lifetime, K, V, C>
+where
+    K: Clone + Ord,
+    C: TreeConfig<K, V>,
+{
+    match root {
+        Some(root) if !empty => {
+            if ptr.is_null() {
+                PathedPointer::lowest(root)
+            } else {
+                let mut ptr = ptr.clone();
+                // Stepping past the end leaves the pointer null.
+                unsafe { ptr.step_forward() };
+                ptr
+            }
+        }
+        _ => PathedPointer::null(),
+    }
+}
+
+fn prev<Lifetime, K, V, C>(
+    root: &Option<Pointer<Branch<K, V, C>, C::PointerKind>>,
+    ptr: &PathedPointer<Lifetime, K, V, C>,
+    empty: bool,
+) -> PathedPointer<Lifetime, K, V, C>
+where
+    K: Clone + Ord,
+    C: TreeConfig<K, V>,
+{
+    match root {
+        Some(root) if !empty => {
+            if ptr.is_null() {
+                PathedPointer::highest(root)
+            } else {
+                let mut ptr = ptr.clone();
+                unsafe { ptr.step_back() };
+                ptr
+            }
+        }
+        _ => PathedPointer::null(),
+    }
+}
+
+impl<'a, K, V, C> Clone for Cursor<'a, K, V, C>
+where
+    K: Clone + Ord,
+    C: 'a + TreeConfig<K, V>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            tree: self.tree,
+            ptr: self.ptr.clone(),
+        }
+    }
+}
+
+impl<'a, K, V, C> Debug for Cursor<'a, K, V, C>
+where
+    K: Clone + Ord + Debug,
+    V: Debug,
+    C: 'a + TreeConfig<K, V>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        f.debug_tuple("Cursor").field(&self.key_value()).finish()
+    }
+}
+
+/// A cursor which can also change the tree: change the value it points
+/// at, remove the entry, or insert new entries either side of it.
+///
+/// It moves around the same way a `Cursor` does. Stepping is cheap, but
+/// anything which changes the tree's shape looks the cursor's entry up
+/// again from the root afterwards, since nodes can split, merge or move
+/// under it.
+///
+/// ```
+/// # use palmtree::StdPalmTree;
+/// # use std::ops::Bound;
+/// let mut tree = StdPalmTree::load((0..5).map(|i| (i * 10, i)));
+/// let mut cursor = tree.lower_bound_mut(Bound::Included(&20));
+/// cursor.insert_before(15, 100);
+/// cursor.insert_after(25, 200);
+/// assert_eq!(Some((20, 2)), cursor.remove_current());
+/// assert_eq!(Some(&25), cursor.key());
+/// let keys: Vec<_> = tree.iter().map(|(k, _)| *k).collect();
+/// assert_eq!(vec![0, 10, 15, 25, 30, 40], keys);
+/// ```
+pub struct CursorMut<'a, K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    tree: &'a mut PalmTree<K, V, C>,
+    ptr: PathedPointer<&'a mut (K, V), K, V, C>,
+}
+
+impl<'a, K, V, C> CursorMut<'a, K, V, C>
+where
+    K: Clone + Ord,
+    V: Clone,
+    C: 'a + TreeConfig<K, V>,
+{
+    /// The tree has to have been made unique already, since the cursor can
+    /// go anywhere in it.
+    pub(crate) fn new<Q>(tree: &'a mut PalmTree<K, V, C>, bound: Bound<&Q>, upper: bool) -> Self
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let ptr = match tree.root {
+            Some(ref root) if !tree.is_empty() => seek(root, bound, upper),
+            _ => PathedPointer::null(),
+        };
+        Self { tree, ptr }
+    }
+
+    pub fn key(&self) -> Option<&K> {
+        self.as_cursor().key()
+    }
+
+    pub fn value(&self) -> Option<&V> {
+        self.as_cursor().value()
+    }
+
+    pub fn key_value(&self) -> Option<(&K, &V)> {
+        self.as_cursor().key_value()
+    }
+
+    pub fn value_mut(&mut self) -> Option<&mut V> {
+        self.key_value_mut().map(|(_, value)| value)
+    }
+
+    pub fn key_value_mut(&mut self) -> Option<(&K, &mut V)> {
+        if self.ptr.is_null() {
+            None
+        } else {
+            Some(unsafe { self.ptr.clone().into_entry_mut() })
+        }
+    }
+
+    /// Step forward to the next entry, or from nothing to the lowest entry.
+    pub fn move_next(&mut self) {
+        self.ptr = next(&self.tree.root, &self.ptr, self.tree.is_empty());
+    }
+
+    /// Step back to the previous entry, or from nothing to the highest
+    /// entry.
+    pub fn move_prev(&mut self) {
+        self.ptr = prev(&self.tree.root, &self.ptr, self.tree.is_empty());
+    }
+
+    pub fn peek_next(&self) -> Option<(&K, &V)> {
+        self.as_cursor().peek_next()
+    }
+
+    pub fn peek_prev(&self) -> Option<(&K, &V)> {
+        self.as_cursor().peek_prev()
+    }
+
+    /// A read only cursor at the same place, for as long as this one is
+    /// borrowed.
+    pub fn as_cursor(&self) -> Cursor<'_, K, V, C> {
+        Cursor {
+            tree: self.tree,
+            ptr: self.ptr.clone().cast(),
+        }
+    }
+
+    /// Insert an entry just in front of the one the cursor points at, or at
+    /// the end of the tree if it points at nothing. The cursor stays where
+    /// it is.
+    ///
+    /// Panics if `key` doesn't fall strictly between the entries either
+    /// side of where it's going.
+    pub fn insert_before(&mut self, key: K, value: V) {
+        let fits = self.key().is_none_or(|next| &key < next)
+            && self.peek_prev().is_none_or(|(prev, _)| prev < &key);
+        if !fits {
+            panic!("CursorMut::insert_before: key out of order");
+        }
+        self.insert(key, value);
+    }
+
+    /// Insert an entry just after the one the cursor points at, or at the
+    /// start of the tree if it points at nothing. The cursor stays where it
+    /// is.
+    ///
+    /// Panics if `key` doesn't fall strictly between the entries either
+    /// side of where it's going.
+    pub fn insert_after(&mut self, key: K, value: V) {
+        let fits = self.key().is_none_or(|prev| prev < &key)
+            && self.peek_next().is_none_or(|(next, _)| &key < next);
+        if !fits {
+            panic!("CursorMut::insert_after: key out of order");
+        }
+        self.insert(key, value);
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        let current = self.key().cloned();
+        self.ptr = PathedPointer::null();
+        self.tree.insert(key, value);
+        if let (Some(current), Some(root)) = (current, self.tree.root.as_ref()) {
+            if let Ok(ptr) = PathedPointer::exact_key(root, &current) {
+                self.ptr = ptr;
+            }
+        }
+    }
+
+    /// Remove the entry the cursor points at, and move on to the one after
+    /// it.
+    pub fn remove_current(&mut self) -> Option<(K, V)> {
+        if self.ptr.is_null() {
+            return None;
+        }
+        let ptr = core::mem::replace(&mut self.ptr, PathedPointer::null());
+        self.tree.size -= 1;
+        let (key, value) = unsafe { ptr.remove() };
+        self.tree
+            .rebalance_path(|keys| find_key(keys, &key).unwrap_or(keys.len() - 1));
+        self.tree.entries_removed(1);
+        if !self.tree.is_empty() {
+            if let Some(ref root) = self.tree.root {
+                self.ptr = PathedPointer::higher_than_key(root, &key);
+            }
+        }
+        Some((key, value))
+    }
+}
+
+impl<'a, K, V, C> Debug for CursorMut<'a, K, V, C>
+where
+    K: Clone + Ord + Debug,
+    V: Clone + Debug,
+    C: 'a + TreeConfig<K, V>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        f.debug_tuple("CursorMut").field(&self.key_value()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{ImPalmTree, StdPalmTree};
+    use std::{collections::BTreeMap, ops::Bound};
+
+    #[test]
+    fn walk_both_ways() {
+        let tree: ImPalmTree<usize, usize> = ImPalmTree::load((0..10_000).map(|i| (i * 2, i)));
+        let mut cursor = tree.lower_bound(Bound::Unbounded);
+        for i in 0..10_000 {
+            assert_eq!(Some((&(i * 2), &i)), cursor.key_value());
+            cursor.move_next();
+        }
+        assert_eq!(None, cursor.key());
+        cursor.move_next();
+        assert_eq!(Some(&0), cursor.key());
+        cursor.move_prev();
+        assert_eq!(None, cursor.key());
+        for i in (0..10_000).rev() {
+            cursor.move_prev();
+            assert_eq!(Some(&(i * 2)), cursor.key());
+        }
+
+        assert_eq!(Some(&6), tree.lower_bound(Bound::Included(&5)).key());
+        assert_eq!(Some(&6), tree.lower_bound(Bound::Included(&6)).key());
+        assert_eq!(Some(&8), tree.lower_bound(Bound::Excluded(&6)).key());
+        assert_eq!(Some(&4), tree.upper_bound(Bound::Included(&5)).key());
+        assert_eq!(Some(&4), tree.upper_bound(Bound::Excluded(&6)).key());
+        assert_eq!(Some(&19_998), tree.upper_bound(Bound::Unbounded).key());
+        assert_eq!(None, tree.lower_bound(Bound::Excluded(&19_998)).key());
+        assert_eq!(None, tree.upper_bound(Bound::Excluded(&0)).key());
+        let cursor = tree.upper_bound(Bound::Excluded(&0));
+        assert_eq!(Some((&0, &0)), cursor.peek_next());
+        assert_eq!(Some((&19_998, &9999)), cursor.peek_prev());
+
+        let empty: StdPalmTree<usize, usize> = StdPalmTree::new();
+        let mut cursor = empty.lower_bound(Bound::Unbounded);
+        cursor.move_next();
+        assert_eq!(None, cursor.key());
+    }
+
+    #[test]
+    fn edit_in_place() {
+        let mut tree: ImPalmTree<usize, usize> = ImPalmTree::load((0..5000).map(|i| (i * 4, i)));
+        let copy = tree.clone();
+        let mut model: BTreeMap<usize, usize> = copy.iter().map(|(k, v)| (*k, *v)).collect();
+
+        // Fill in every gap, which splits most of the leaves under the cursor.
+        let mut cursor = tree.lower_bound_mut(Bound::Unbounded);
+        while let Some(key) = cursor.key().copied() {
+            cursor.insert_after(key + 2, 0);
+            cursor.insert_after(key + 1, 0);
+            *cursor.value_mut().unwrap() += 1;
+            for _ in 0..3 {
+                cursor.move_next();
+            }
+        }
+        for i in 0..5000 {
+            *model.get_mut(&(i * 4)).unwrap() += 1;
+            model.insert(i * 4 + 1, 0);
+            model.insert(i * 4 + 2, 0);
+        }
+        tree.check_invariants().unwrap();
+        assert!(tree.iter().eq(model.iter()));
+
+        // Remove every other entry going backwards.
+        let mut cursor = tree.upper_bound_mut(Bound::Unbounded);
+        let mut removed = 0;
+        while cursor.key().is_some() {
+            let (key, _) = cursor.remove_current().unwrap();
+            model.remove(&key);
+            removed += 1;
+            cursor.move_prev();
+            cursor.move_prev();
+        }
+        assert_eq!(7500, removed);
+        tree.check_invariants().unwrap();
+        assert!(tree.iter().eq(model.iter()));
+        assert!(copy.iter().all(|(k, v)| *k == v * 4));
+
+        // Inserting past either end through the empty position.
+        tree.remove(&0);
+        let mut cursor = tree.lower_bound_mut(Bound::Excluded(&usize::MAX));
+        cursor.insert_before(100_000, 1);
+        cursor.insert_after(0, 2);
+        assert_eq!(None, cursor.key());
+        assert_eq!(Some((&100_000, &1)), cursor.peek_prev());
+        assert_eq!(Some((&0, &2)), cursor.peek_next());
+    }
+
+    #[test]
+    #[should_panic(expected = "key out of order")]
+    fn insert_out_of_order() {
+        let mut tree: StdPalmTree<usize, usize> = StdPalmTree::load((0..10).map(|i| (i * 2, i)));
+        tree.lower_bound_mut(Bound::Included(&4))
+            .insert_before(5, 0);
+    }
+}
@@ -0,0 +1,136 @@
+use crate::{branch::Branch, config::TreeConfig, leaf::Leaf, PalmTree};
+use alloc::string::String;
+use core::fmt::{Debug, Error, Write};
+use typenum::Unsigned;
+
+/// Write a label in DOT's quoted string syntax.
+fn write_label<W: Write>(out: &mut W, label: &str) -> Result<(), Error> {
+    out.write_char('"')?;
+    for c in label.chars() {
+        match c {
+            '"' | '\\' => {
+                out.write_char('\\')?;
+                out.write_char(c)?;
+            }
+            '\n' => out.write_str("\\n")?,
+            _ => out.write_char(c)?,
+        }
+    }
+    out.write_char('"')
+}
+
+fn write_node<K: Debug, W: Write>(
+    out: &mut W,
+    id: usize,
+    range: Option<(&K, &K)>,
+    len: usize,
+    capacity: usize,
+) -> Result<(), Error> {
+    let mut label = String::new();
+    if let Some((lowest, highest)) = range {
+        writeln!(label, "{:?} ..= {:?}", lowest, highest)?;
+    }
+    write!(label, "{}/{}", len, capacity)?;
+    write!(out, "    n{} [label=", id)?;
+    write_label(out, &label)?;
+    writeln!(out, "];")
+}
+
+fn write_leaf<K, V, C, W>(leaf: &Leaf<K, V, C>, out: &mut W, id: usize) -> Result<(), Error>
+where
+    K: Debug,
+    C: TreeConfig<K, V>,
+    W: Write,
+{
+    let range = leaf.keys().first().map(|lowest| (lowest, leaf.highest()));
+    write_node(out, id, range, leaf.len(), C::LeafSize::USIZE)
+}
+
+/// Write out the subtree under `branch` as node `id`, numbering the nodes
+/// below it from `next_id`, and return the range of keys it holds.
+fn write_branch<'a, K, V, C, W>(
+    branch: &'a Branch<K, V, C>,
+    out: &mut W,
+    id: usize,
+    next_id: &mut usize,
+) -> Result<Option<(&'a K, &'a K)>, Error>
+where
+    K: Debug,
+    C: TreeConfig<K, V>,
+    W: Write,
+{
+    let mut range: Option<(&K, &K)> = None;
+    for index in 0..branch.len() {
+        let child = *next_id;
+        *next_id += 1;
+        let child_range = if branch.has_branches() {
+            write_branch(branch.get_branch(index), out, child, next_id)?
+        } else {
+            let leaf = branch.get_leaf(index);
+            write_leaf(leaf, out, child)?;
+            leaf.keys().first().map(|lowest| (lowest, leaf.highest()))
+        };
+        writeln!(out, "    n{} -> n{};", id, child)?;
+        if let Some((lowest, highest)) = child_range {
+            range = Some((range.map_or(lowest, |(lowest, _)| lowest), highest));
+        }
+    }
+    write_node(out, id, range, branch.len(), C::BranchSize::USIZE)?;
+    Ok(range)
+}
+
+impl<K, V, C> PalmTree<K, V, C>
+where
+    K: Debug,
+    C: TreeConfig<K, V>,
+{
+    /// Write out the structure of the tree as a Graphviz DOT graph, with a
+    /// node for each branch and leaf showing the range of keys under it
+    /// and how full it is.
+    ///
+    /// ```
+    /// # use palmtree::StdPalmTree;
+    /// let tree: StdPalmTree<u64, u64> = StdPalmTree::load((0..100).map(|i| (i, i)));
+    /// let mut dot = String::new();
+    /// tree.dump_dot(&mut dot).unwrap();
+    /// assert!(dot.starts_with("digraph palmtree {"));
+    /// assert!(dot.contains("n0 [label=\"0 ..= 99\\n2/64\"];"));
+    /// ```
+    pub fn dump_dot<W: Write>(&self, mut out: W) -> Result<(), Error> {
+        writeln!(out, "digraph palmtree {{")?;
+        writeln!(out, "    node [shape=box];")?;
+        if let Some(ref root) = self.root {
+            write_branch(root, &mut out, 0, &mut 1)?;
+        }
+        writeln!(out, "}}")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{PalmTree, StdPalmTree, Tree64, Unique};
+
+    #[test]
+    fn dot_has_every_node() {
+        let tree: StdPalmTree<usize, usize> = StdPalmTree::load((0..100_000).map(|i| (i, i)));
+        let mut dot = String::new();
+        tree.dump_dot(&mut dot).unwrap();
+        let nodes = tree.memory_usage().nodes();
+        assert_eq!(nodes, dot.matches("[label=").count());
+        assert_eq!(nodes - 1, dot.matches(" -> ").count());
+        assert!(dot.contains("n0 [label=\"0 ..= 99999\\n"));
+        assert!(dot.ends_with("}\n"));
+
+        let mut empty = String::new();
+        StdPalmTree::<usize, usize>::new()
+            .dump_dot(&mut empty)
+            .unwrap();
+        assert_eq!("digraph palmtree {\n    node [shape=box];\n}\n", empty);
+
+        // Quotes in keys have to be escaped.
+        let tree: PalmTree<&str, (), Tree64<Unique>> = vec![("a\"b", ())].into_iter().collect();
+        let mut dot = String::new();
+        tree.dump_dot(&mut dot).unwrap();
+        assert!(dot.contains(r#"[label="\"a\\\"b\" ..= \"a\\\"b\"\n1/64"]"#));
+    }
+}
@@ -1,8 +1,15 @@
 use crate::{
-    branch::Branch, config::TreeConfig, leaf::Leaf, pointer::Pointer, search::PathedPointer,
+    branch::Branch,
+    config::TreeConfig,
+    leaf::Leaf,
+    pointer::Pointer,
+    search::{find_key, PathedPointer},
     PalmTree,
 };
-use std::fmt::{Debug, Error, Formatter};
+use core::{
+    cmp::Ordering,
+    fmt::{Debug, Error, Formatter},
+};
 
 #[derive(Debug)]
 pub enum Entry<'a, K, V, C>
@@ -36,6 +43,88 @@ where
     }
 }
 
+impl<'a, K, V, C> Entry<'a, K, V, C>
+where
+    K: 'a + Ord + Clone,
+    V: 'a,
+    C: TreeConfig<K, V>,
+{
+    pub fn key(&self) -> &K {
+        match self {
+            Self::Vacant(entry) => entry.key(),
+            Self::Occupied(entry) => entry.key(),
+        }
+    }
+
+    /// Insert `value` if the entry is vacant, and return a mutable
+    /// reference to whichever value ends up in it.
+    pub fn or_insert(self, value: V) -> &'a mut V
+    where
+        V: Clone,
+    {
+        match self {
+            Self::Vacant(entry) => entry.insert(value),
+            Self::Occupied(entry) => entry.into_mut(),
+        }
+    }
+
+    /// Like `or_insert`, but only call `f` to make the value if it's needed.
+    pub fn or_insert_with<F>(self, f: F) -> &'a mut V
+    where
+        V: Clone,
+        F: FnOnce() -> V,
+    {
+        match self {
+            Self::Vacant(entry) => entry.insert(f()),
+            Self::Occupied(entry) => entry.into_mut(),
+        }
+    }
+
+    /// Like `or_insert_with`, but pass the key to `f`.
+    pub fn or_insert_with_key<F>(self, f: F) -> &'a mut V
+    where
+        V: Clone,
+        F: FnOnce(&K) -> V,
+    {
+        match self {
+            Self::Vacant(entry) => {
+                let value = f(entry.key());
+                entry.insert(value)
+            }
+            Self::Occupied(entry) => entry.into_mut(),
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Clone + Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    /// Call `f` on the value if the entry is occupied, and hand the entry
+    /// back either way.
+    ///
+    /// ```
+    /// # use palmtree::StdPalmTree;
+    /// let mut tree: StdPalmTree<&str, usize> = StdPalmTree::new();
+    /// for word in "a b a c a".split(' ') {
+    ///     tree.entry(word).and_modify(|count| *count += 1).or_insert(1);
+    /// }
+    /// assert_eq!(Some(&3), tree.get(&"a"));
+    /// assert_eq!(Some(&1), tree.get(&"c"));
+    /// ```
+    pub fn and_modify<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut V),
+    {
+        if let Self::Occupied(ref mut entry) = self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
 // Vacant entry
 
 pub struct VacantEntry<'a, K, V, C>
@@ -73,6 +162,7 @@ where
         if self.tree.is_empty() {
             self.tree.root = Some(Branch::unit(Leaf::unit(self.key, value).into()).into());
             self.tree.size = 1;
+            self.tree.validate();
             return &mut Pointer::make_mut(self.tree.root.as_mut().unwrap())
                 .get_leaf_mut(0)
                 .values_mut()[0];
@@ -91,11 +181,12 @@ where
         let ptr: *mut V = match result {
             Ok(mut ptr) => {
                 self.tree.size += 1;
+                self.tree.validate();
                 unsafe { ptr.value_mut().unwrap() }
             }
             Err((key, value)) => {
                 let root = self.tree.root.as_mut().unwrap();
-                PalmTree::split_root(root);
+                PalmTree::split_root(root, &key);
                 self.cursor = PathedPointer::exact_key(root, &key).unwrap_err();
                 self.key = key;
                 self.insert(value)
@@ -133,6 +224,13 @@ where
     V: 'a,
     C: TreeConfig<K, V>,
 {
+    pub(crate) fn new(
+        tree: &'a mut PalmTree<K, V, C>,
+        cursor: PathedPointer<&'a mut (K, V), K, V, C>,
+    ) -> Self {
+        Self { tree, cursor }
+    }
+
     pub fn key(&self) -> &K {
         unsafe { self.cursor.key() }.unwrap()
     }
@@ -145,22 +243,62 @@ where
         unsafe { self.cursor.value_mut() }.unwrap()
     }
 
+    /// Get the key along with a mutable reference to the value.
+    pub fn get_pair_mut(&mut self) -> (&K, &mut V) {
+        unsafe { self.cursor.clone().into_entry_mut() }
+    }
+
     pub fn insert(&mut self, value: V) -> V {
-        std::mem::replace(self.get_mut(), value)
+        core::mem::replace(self.get_mut(), value)
+    }
+
+    /// Replace the entry's key with `key`, returning the old one.
+    ///
+    /// `key` must compare equal to the old key, which makes this useful only
+    /// for refreshing parts of a key that don't take part in ordering. Any
+    /// copies of the key kept in the branches above are replaced too.
+    ///
+    /// This is the only way to change a key once it's in the tree. Panics
+    /// if `key` doesn't compare equal to the old key, leaving the tree
+    /// unchanged.
+    pub fn replace_key(&mut self, key: K) -> K {
+        if key.cmp(self.key()) != Ordering::Equal {
+            panic!("OccupiedEntry::replace_key: new key doesn't compare equal to the old key");
+        }
+        let old_key = unsafe { self.cursor.replace_key(key) };
+        self.tree.validate();
+        old_key
     }
 
-    pub fn remove_entry(self) -> (K, V) {
+    pub fn remove_entry(self) -> (K, V)
+    where
+        V: Clone,
+    {
         self.tree.size -= 1;
-        unsafe { self.cursor.remove() }
+        let result = unsafe { self.cursor.remove() };
+        let key = &result.0;
+        self.tree
+            .rebalance_path(|keys| find_key(keys, key).unwrap_or(keys.len() - 1));
+        self.tree.entries_removed(1);
+        result
     }
 
-    pub fn remove(self) -> V {
+    pub fn remove(self) -> V
+    where
+        V: Clone,
+    {
         self.remove_entry().1
     }
 
     pub fn into_mut(self) -> &'a mut V {
         unsafe { self.cursor.into_entry_mut() }.1
     }
+
+    /// Turn the entry into the key along with a mutable reference to the
+    /// value, borrowed for as long as the tree was.
+    pub fn into_pair(self) -> (&'a K, &'a mut V) {
+        unsafe { self.cursor.into_entry_mut() }
+    }
 }
 
 impl<'a, K, V, C> Debug for OccupiedEntry<'a, K, V, C>
@@ -177,13 +315,54 @@ where
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::StdPalmTree;
+    use crate::{tests::scaled, StdPalmTree};
+    use std::cmp::Ordering;
     use std::iter::FromIterator;
 
+    #[derive(Clone, Debug)]
+    struct Tagged {
+        key: usize,
+        tag: usize,
+    }
+
+    impl PartialEq for Tagged {
+        fn eq(&self, other: &Self) -> bool {
+            self.key == other.key
+        }
+    }
+
+    impl Eq for Tagged {}
+
+    impl PartialOrd for Tagged {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Tagged {
+        fn cmp(&self, other: &Self) -> Ordering {
+            self.key.cmp(&other.key)
+        }
+    }
+
+    fn separator_tags<V, C>(branch: &Branch<Tagged, V, C>, key: usize, tags: &mut Vec<usize>)
+    where
+        C: TreeConfig<Tagged, V>,
+    {
+        for (index, separator) in branch.keys().iter().enumerate() {
+            if separator.key == key {
+                tags.push(separator.tag);
+            }
+            if branch.has_branches() {
+                separator_tags(branch.get_branch(index), key, tags);
+            }
+        }
+    }
+
     #[test]
     fn insert_with_entry() {
         let mut tree: StdPalmTree<usize, usize> = PalmTree::new();
-        let size = 131_072;
+        let size = scaled(131_072);
         for i in 0..size {
             match tree.entry(i) {
                 Entry::Vacant(entry) => {
@@ -199,9 +378,77 @@ mod test {
         }
     }
 
+    #[test]
+    fn entry_combinators() {
+        let mut tree: StdPalmTree<usize, usize> = PalmTree::new();
+        for i in 0..10_000 {
+            *tree.entry(i % 1000).or_insert(0) += 1;
+        }
+        assert_eq!(1000, tree.len());
+        assert!(tree.values().all(|count| *count == 10));
+
+        assert_eq!(&mut 10, tree.entry(5).or_insert_with(|| panic!("occupied")));
+        assert_eq!(
+            &mut 2000,
+            tree.entry(1000).or_insert_with_key(|key| key * 2)
+        );
+        assert_eq!(&mut 0, tree.entry(1001).or_default());
+        assert_eq!(&1001, tree.entry(1001).key());
+        for i in 0..2000 {
+            tree.entry(i).and_modify(|count| *count += 1).or_insert(100);
+        }
+        assert_eq!(2000, tree.len());
+        assert_eq!(Some(&11), tree.get(&999));
+        assert_eq!(Some(&2001), tree.get(&1000));
+        assert_eq!(Some(&1), tree.get(&1001));
+        assert_eq!(Some(&100), tree.get(&1002));
+        tree.check_invariants().unwrap();
+    }
+
+    #[test]
+    fn replace_key_with_entry() {
+        let size = 100_000;
+        let mut tree: StdPalmTree<Tagged, usize> =
+            PalmTree::load((0..size).map(|key| (Tagged { key, tag: 0 }, key)));
+        // 4095 is the highest key in the first 64 leaves, so it's a separator
+        // two levels up as well as one.
+        for &key in &[0, 63, 4095, 50_000, size - 1] {
+            let mut tags = Vec::new();
+            separator_tags(tree.root.as_ref().unwrap(), key, &mut tags);
+            assert_eq!(key == 0 || key == 50_000, tags.is_empty());
+            match tree.entry(Tagged { key, tag: 0 }) {
+                Entry::Occupied(mut entry) => {
+                    let old = entry.replace_key(Tagged { key, tag: 1 });
+                    assert_eq!(0, old.tag);
+                    assert_eq!(1, entry.key().tag);
+                }
+                Entry::Vacant(_) => panic!("unexpected vacant entry at {}", key),
+            }
+            let mut new_tags = Vec::new();
+            separator_tags(tree.root.as_ref().unwrap(), key, &mut new_tags);
+            assert_eq!(tags.len(), new_tags.len());
+            assert!(new_tags.iter().all(|tag| *tag == 1));
+        }
+        let tagged: Vec<_> = tree
+            .iter()
+            .filter(|(key, _)| key.tag == 1)
+            .map(|(key, _)| key.key)
+            .collect();
+        assert_eq!(vec![0, 63, 4095, 50_000, size - 1], tagged);
+    }
+
+    #[test]
+    #[should_panic(expected = "doesn't compare equal to the old key")]
+    fn replace_key_with_unequal_key() {
+        let mut tree: StdPalmTree<usize, usize> = PalmTree::load((0..100).map(|i| (i, i)));
+        if let Entry::Occupied(mut entry) = tree.entry(50) {
+            entry.replace_key(51);
+        }
+    }
+
     #[test]
     fn delete_with_entry() {
-        let size = 131_072;
+        let size = scaled(131_072);
         let mut tree: StdPalmTree<usize, usize> = PalmTree::from_iter((0..size).map(|i| (i, i)));
         for i in 0..size {
             match tree.entry(i) {
@@ -215,4 +462,25 @@ mod test {
         }
         assert_eq!(0, tree.len());
     }
+
+    #[test]
+    fn key_value_pairs_from_entry() {
+        let mut tree: StdPalmTree<usize, usize> = PalmTree::from_iter((0..1000).map(|i| (i, i)));
+        for i in 0..1000 {
+            if let Entry::Occupied(mut entry) = tree.entry(i) {
+                let (key, value) = entry.get_pair_mut();
+                *value += key;
+            }
+        }
+        if let Entry::Occupied(entry) = tree.entry(500) {
+            let (key, value) = entry.into_pair();
+            assert_eq!(500, *key);
+            *value = 0;
+        }
+        assert_eq!(Some(&0), tree.get(&500));
+        assert!(tree
+            .iter()
+            .filter(|(k, _)| **k != 500)
+            .all(|(k, v)| *v == k * 2));
+    }
 }
@@ -0,0 +1,250 @@
+use crate::{builder::sort_entries, config::TreeConfig, PalmTree};
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    borrow::Borrow,
+    fmt::{Debug, Error, Formatter},
+    iter::{FromIterator, Zip},
+    ops::{Bound, RangeBounds},
+    slice,
+};
+
+/// A read only copy of a tree packed as tightly as it'll go.
+///
+/// A tree's leaves are allocated at their full size whether they're full
+/// or not, and after a lot of random inserts they average about 70%
+/// full. A frozen tree keeps its keys and values in exactly sized arrays
+/// instead, in one allocation each, and looks keys up with a binary search
+/// over them. Get one with `PalmTree::freeze`, and `thaw` it to make
+/// changes.
+///
+/// ```
+/// # use palmtree::{FrozenPalmTree, StdPalmTree};
+/// let tree: StdPalmTree<u32, u32> = (0..1000).map(|i| (i, i * 2)).collect();
+/// let frozen = tree.freeze();
+/// assert_eq!(Some(&20), frozen.get(&10));
+/// assert_eq!(vec![(&998, &1996), (&999, &1998)], frozen.range(998..).collect::<Vec<_>>());
+/// let mut tree: StdPalmTree<u32, u32> = frozen.thaw();
+/// tree.insert(1000, 0);
+/// ```
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct FrozenPalmTree<K, V> {
+    keys: Box<[K]>,
+    values: Box<[V]>,
+}
+
+/// An iterator over the entries of a `FrozenPalmTree`.
+pub type FrozenIter<'a, K, V> = Zip<slice::Iter<'a, K>, slice::Iter<'a, V>>;
+
+impl<K, V> FrozenPalmTree<K, V>
+where
+    K: Ord,
+{
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    fn find<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.keys.binary_search_by(|k| k.borrow().cmp(key)).ok()
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.find(key).map(|index| &self.values[index])
+    }
+
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let index = self.find(key)?;
+        Some((&self.keys[index], &self.values[index]))
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.find(key).is_some()
+    }
+
+    pub fn keys(&self) -> &[K] {
+        &self.keys
+    }
+
+    pub fn values(&self) -> &[V] {
+        &self.values
+    }
+
+    pub fn iter(&self) -> FrozenIter<'_, K, V> {
+        self.keys.iter().zip(self.values.iter())
+    }
+
+    pub fn range<Q, R>(&self, range: R) -> FrozenIter<'_, K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(key) => self.keys.partition_point(|k| k.borrow() < key),
+            Bound::Excluded(key) => self.keys.partition_point(|k| k.borrow() <= key),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(key) => self.keys.partition_point(|k| k.borrow() <= key),
+            Bound::Excluded(key) => self.keys.partition_point(|k| k.borrow() < key),
+            Bound::Unbounded => self.len(),
+        };
+        let end = end.max(start);
+        self.keys[start..end]
+            .iter()
+            .zip(self.values[start..end].iter())
+    }
+
+    /// Turn this back into a tree you can change.
+    pub fn thaw<C>(self) -> PalmTree<K, V, C>
+    where
+        K: Clone,
+        V: Clone,
+        C: TreeConfig<K, V>,
+    {
+        PalmTree::load(Vec::from(self.keys).into_iter().zip(Vec::from(self.values)))
+    }
+}
+
+impl<K, V, C> PalmTree<K, V, C>
+where
+    K: Clone + Ord,
+    V: Clone,
+    C: TreeConfig<K, V>,
+{
+    /// Pack this tree into a `FrozenPalmTree`.
+    pub fn freeze(self) -> FrozenPalmTree<K, V> {
+        let mut keys = Vec::with_capacity(self.len());
+        let mut values = Vec::with_capacity(self.len());
+        for (key, value) in self {
+            keys.push(key);
+            values.push(value);
+        }
+        FrozenPalmTree {
+            keys: keys.into_boxed_slice(),
+            values: values.into_boxed_slice(),
+        }
+    }
+}
+
+impl<K, V> Default for FrozenPalmTree<K, V> {
+    fn default() -> Self {
+        Self {
+            keys: Box::new([]),
+            values: Box::new([]),
+        }
+    }
+}
+
+impl<K, V, C> From<PalmTree<K, V, C>> for FrozenPalmTree<K, V>
+where
+    K: Clone + Ord,
+    V: Clone,
+    C: TreeConfig<K, V>,
+{
+    fn from(tree: PalmTree<K, V, C>) -> Self {
+        tree.freeze()
+    }
+}
+
+impl<K, V> FromIterator<(K, V)> for FrozenPalmTree<K, V>
+where
+    K: Ord,
+{
+    /// Collect entries in any order. Where a key turns up more than once,
+    /// the last value for it wins, as with inserting them one at a time.
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut entries: Vec<(K, V)> = iter.into_iter().collect();
+        sort_entries(&mut entries);
+        let (keys, values): (Vec<K>, Vec<V>) = entries.into_iter().unzip();
+        Self {
+            keys: keys.into_boxed_slice(),
+            values: values.into_boxed_slice(),
+        }
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a FrozenPalmTree<K, V>
+where
+    K: Ord,
+{
+    type Item = (&'a K, &'a V);
+    type IntoIter = FrozenIter<'a, K, V>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<K, V> Debug for FrozenPalmTree<K, V>
+where
+    K: Ord + Debug,
+    V: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::StdPalmTree;
+
+    #[test]
+    fn frozen_reads_match_tree() {
+        let tree: StdPalmTree<usize, usize> = (0..10_000).map(|i| (i * 3, i)).collect();
+        let frozen = tree.clone().freeze();
+        assert_eq!(tree.len(), frozen.len());
+        assert!(tree.iter().eq(frozen.iter()));
+        for key in 0..30_010 {
+            assert_eq!(tree.get(&key), frozen.get(&key));
+        }
+        assert!(tree.range(100..=200).eq(frozen.range(100..=200)));
+        assert!(tree
+            .range(..=29_997)
+            .rev()
+            .eq(frozen.range(..=29_997).rev()));
+        assert!(tree
+            .range((Bound::Excluded(99), Bound::Excluded(300)))
+            .eq(frozen.range((Bound::Excluded(99), Bound::Excluded(300)))));
+        assert_eq!(
+            0,
+            frozen
+                .range((Bound::Included(200), Bound::Excluded(100)))
+                .count()
+        );
+        assert_eq!(tree, frozen.thaw());
+
+        let frozen: FrozenPalmTree<usize, usize> =
+            vec![(3, 0), (1, 0), (3, 1), (2, 0)].into_iter().collect();
+        assert_eq!(&[1, 2, 3], frozen.keys());
+        assert_eq!(&[0, 0, 1], frozen.values());
+
+        let frozen: FrozenPalmTree<String, usize> =
+            vec![("b".to_string(), 2), ("a".to_string(), 1)]
+                .into_iter()
+                .collect();
+        assert_eq!(Some(&2), frozen.get("b"));
+        assert_eq!(Some((&"a".to_string(), &1)), frozen.get_key_value("a"));
+        assert!(!frozen.contains_key("c"));
+    }
+}
@@ -0,0 +1,206 @@
+use crate::{config::TreeConfig, RangeSet};
+use core::{
+    fmt::{Debug, Error, Formatter},
+    ops::{Add, Range, Sub},
+};
+
+/// The integer types an `IdAllocator` can hand out.
+pub trait IdKey: Copy + Ord + Add<Output = Self> + Sub<Output = Self> + From<u8> {
+    /// Add `other`, or return `None` if the sum doesn't fit.
+    fn checked_add(self, other: Self) -> Option<Self>;
+}
+
+macro_rules! impl_id_key {
+    ($($t:ty),*) => {
+        $(impl IdKey for $t {
+            fn checked_add(self, other: Self) -> Option<Self> {
+                <$t>::checked_add(self, other)
+            }
+        })*
+    };
+}
+
+impl_id_key!(u8, u16, u32, u64, u128, usize, i16, i32, i64, i128, isize);
+
+/// Hands out the lowest integer IDs which aren't already taken.
+///
+/// Taken IDs are kept as a `RangeSet`, so a run of consecutive IDs costs
+/// a single entry, and the lowest free ID is always right after the
+/// first range, or zero, which makes `allocate` `O(log n)`. Finding a free
+/// block of more than one ID has to look at each gap in turn until one's
+/// big enough.
+///
+/// ```
+/// # use palmtree::{IdAllocator, Tree64, Unique};
+/// let mut ids: IdAllocator<u32, Tree64<Unique>> = IdAllocator::new();
+/// assert_eq!(0, ids.allocate());
+/// assert_eq!(1, ids.allocate());
+/// assert_eq!(2, ids.allocate());
+/// ids.free(1);
+/// assert_eq!(1, ids.allocate());
+/// assert_eq!(3, ids.allocate_block(4));
+/// assert_eq!(7, ids.allocate());
+/// ```
+pub struct IdAllocator<K, C>
+where
+    C: TreeConfig<K, (K, ())>,
+{
+    used: RangeSet<K, C>,
+}
+
+impl<K, C> IdAllocator<K, C>
+where
+    K: IdKey,
+    C: TreeConfig<K, (K, ())>,
+{
+    pub fn new() -> Self {
+        Self {
+            used: RangeSet::new(),
+        }
+    }
+
+    /// The ranges of IDs currently taken.
+    pub fn used(&self) -> &RangeSet<K, C> {
+        &self.used
+    }
+
+    pub fn is_allocated(&self, id: K) -> bool {
+        self.used.contains(&id)
+    }
+
+    /// The lowest ID which isn't taken.
+    pub fn lowest_free(&self) -> K {
+        self.find_free_block(K::from(1))
+    }
+
+    /// The lowest ID starting a run of `len` IDs none of which are taken.
+    pub fn find_free_block(&self, len: K) -> K {
+        let mut start = K::from(0);
+        for range in self.used.iter() {
+            if *range.start - start >= len {
+                break;
+            }
+            start = *range.end;
+        }
+        start
+    }
+
+    /// Take the lowest free ID.
+    pub fn allocate(&mut self) -> K {
+        self.allocate_block(K::from(1))
+    }
+
+    /// Take the lowest run of `len` free IDs, returning the first of them.
+    ///
+    /// Taken IDs are stored as ranges ending one past the last of them, so
+    /// the highest value of `K` is never handed out, and we panic if there
+    /// isn't room for the run below it.
+    pub fn allocate_block(&mut self, len: K) -> K {
+        let start = self.find_free_block(len);
+        let end = start
+            .checked_add(len)
+            .expect("IdAllocator::allocate_block: ID space exhausted");
+        self.used.insert(start..end);
+        start
+    }
+
+    /// Mark every ID in `range` as taken, whether it was free or not.
+    pub fn reserve(&mut self, range: Range<K>) {
+        self.used.insert(range);
+    }
+
+    /// Give `id` back, returning whether it was taken.
+    pub fn free(&mut self, id: K) -> bool {
+        let taken = self.is_allocated(id);
+        if taken {
+            self.used.remove(id..id + K::from(1));
+        }
+        taken
+    }
+
+    /// Give back every ID in `range`.
+    pub fn free_block(&mut self, range: Range<K>) {
+        self.used.remove(range);
+    }
+}
+
+impl<K, C> Default for IdAllocator<K, C>
+where
+    K: IdKey,
+    C: TreeConfig<K, (K, ())>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, C> Clone for IdAllocator<K, C>
+where
+    K: Clone + Ord,
+    C: TreeConfig<K, (K, ())>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            used: self.used.clone(),
+        }
+    }
+}
+
+impl<K, C> Debug for IdAllocator<K, C>
+where
+    K: Clone + Ord + Debug,
+    C: TreeConfig<K, (K, ())>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "IdAllocator(used = {:?})", self.used)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Tree64, Unique};
+
+    #[test]
+    fn allocate_lowest_first() {
+        let mut ids: IdAllocator<u64, Tree64<Unique>> = IdAllocator::new();
+        for id in 0..1000 {
+            assert_eq!(id, ids.allocate());
+        }
+        assert_eq!(1, ids.used().len());
+        for id in (0..1000).step_by(3) {
+            assert!(ids.free(id));
+            assert!(!ids.free(id));
+        }
+        for id in (0..1000).step_by(3) {
+            assert_eq!(id, ids.allocate());
+        }
+        assert_eq!(1000, ids.lowest_free());
+
+        ids.free_block(100..110);
+        ids.free(200);
+        assert_eq!(100, ids.allocate_block(5));
+        assert_eq!(1000, ids.allocate_block(6));
+        assert_eq!(105, ids.allocate_block(5));
+        assert_eq!(200, ids.allocate());
+        assert_eq!(1006, ids.lowest_free());
+
+        ids.reserve(1006..2000);
+        ids.free(0);
+        assert_eq!(2000, ids.find_free_block(2));
+        assert_eq!(0, ids.allocate());
+        assert!(ids.is_allocated(1999));
+        assert!(!ids.is_allocated(2000));
+    }
+
+    #[test]
+    #[should_panic(expected = "IdAllocator::allocate_block: ID space exhausted")]
+    fn allocate_until_exhausted() {
+        let mut ids: IdAllocator<u8, Tree64<Unique>> = IdAllocator::new();
+        for id in 0..255 {
+            assert_eq!(id, ids.allocate());
+        }
+        assert_eq!(255, ids.lowest_free());
+        ids.allocate();
+    }
+}
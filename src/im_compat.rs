@@ -0,0 +1,62 @@
+//! Methods named after their equivalents on `im::OrdMap`, so that code
+//! written against it can be pointed at a `PalmTree` with few changes.
+//!
+//! They're meant for an `ImPalmTree`, where cloning a tree only copies its
+//! root, but they'll work with any config. The one difference you're likely
+//! to hit is that `get_min` and `get_max` return a pair of references rather
+//! than a reference to a pair, because leaves don't store their entries as
+//! tuples. `update` and `without` aren't here, because they're always
+//! available.
+
+use crate::{config::TreeConfig, PalmTree};
+
+impl<K, V, C> PalmTree<K, V, C>
+where
+    K: Clone + Ord,
+    V: Clone,
+    C: TreeConfig<K, V>,
+{
+    pub fn get_min(&self) -> Option<(&K, &V)> {
+        self.iter().next()
+    }
+
+    pub fn get_max(&self) -> Option<(&K, &V)> {
+        self.iter().next_back()
+    }
+
+    /// Construct the union of two trees, keeping the values from this one
+    /// where a key is in both.
+    #[must_use]
+    pub fn union(self, other: Self) -> Self {
+        Self::merge_left(self, other)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ImPalmTree;
+
+    #[test]
+    fn persistent_updates() {
+        let tree: ImPalmTree<usize, usize> = ImPalmTree::load((0..1000).map(|i| (i, i)));
+        let updated = tree.update(500, 0).update(1000, 1000);
+        let removed = updated.without(&0).without(&5000);
+        assert_eq!(Some(&500), tree.get(&500));
+        assert_eq!(Some(&0), updated.get(&500));
+        assert_eq!(1000, tree.len());
+        assert_eq!(1001, updated.len());
+        assert_eq!(1000, removed.len());
+
+        assert_eq!(Some((&0, &0)), tree.get_min());
+        assert_eq!(Some((&999, &999)), tree.get_max());
+        assert_eq!(Some((&1, &1)), removed.get_min());
+        assert_eq!(Some((&1000, &1000)), removed.get_max());
+        assert_eq!(None, ImPalmTree::<usize, usize>::new().get_min());
+
+        let odds = ImPalmTree::load((0..1000).filter(|i| i % 2 == 1).map(|i| (i, 0)));
+        let union = ImPalmTree::load((1000..1500).map(|i| (i, i))).union(odds.clone());
+        assert_eq!(1000, union.len());
+        let union = tree.clone().union(odds);
+        assert_eq!(tree, union);
+    }
+}
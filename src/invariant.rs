@@ -0,0 +1,122 @@
+use alloc::vec::Vec;
+use core::fmt::{Display, Error, Formatter};
+
+/// A broken structural invariant, as found by `PalmTree::check_invariants`,
+/// and where in the tree it was found.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct InvariantError {
+    /// The child indices leading from the root down to the node at fault,
+    /// which is empty if it's the root itself.
+    pub path: Vec<usize>,
+    /// Which invariant is broken.
+    pub kind: Invariant,
+}
+
+/// A structural invariant of a tree. See `InvariantError`.
+///
+/// Indices are of keys or children within the node an error's path leads
+/// to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Invariant {
+    /// A branch's separator isn't above the one before it, or above the
+    /// lower bound its parent gives it.
+    SeparatorOrder(usize),
+    /// A branch's separator is above the separator its parent holds for it.
+    SeparatorBound(usize),
+    /// A branch's last separator isn't the separator its parent holds for
+    /// it, which searches rely on to find their way down.
+    LastSeparator,
+    /// A branch below the root has no children.
+    EmptyBranch,
+    /// A branch's children aren't all the same height.
+    Depth(usize),
+    /// A leaf has no entries.
+    EmptyLeaf,
+    /// A leaf's key isn't above the one before it, or above the lower bound
+    /// its parent gives it.
+    KeyOrder(usize),
+    /// A leaf holds a key above the separator its parent holds for it.
+    KeyBound,
+    /// The tree's length isn't the number of entries it holds.
+    Length { length: usize, entries: usize },
+}
+
+impl InvariantError {
+    pub(crate) fn new(kind: Invariant) -> Self {
+        Self {
+            path: Vec::new(),
+            kind,
+        }
+    }
+
+    /// Note that the error was found under child `index` of a branch, as it
+    /// passes up through the branch.
+    pub(crate) fn in_child(mut self, index: usize) -> Self {
+        self.path.insert(0, index);
+        self
+    }
+}
+
+impl Display for Invariant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            Invariant::SeparatorOrder(index) => write!(f, "separator {} is out of order", index),
+            Invariant::SeparatorBound(index) => {
+                write!(f, "separator {} is above its parent's", index)
+            }
+            Invariant::LastSeparator => write!(f, "last separator doesn't match its parent's"),
+            Invariant::EmptyBranch => write!(f, "branch is empty"),
+            Invariant::Depth(index) => write!(f, "child {} is at the wrong depth", index),
+            Invariant::EmptyLeaf => write!(f, "leaf is empty"),
+            Invariant::KeyOrder(index) => write!(f, "leaf key {} is out of order", index),
+            Invariant::KeyBound => write!(f, "leaf holds keys above its separator"),
+            Invariant::Length { length, entries } => write!(
+                f,
+                "tree has a length of {} but holds {} entries",
+                length, entries
+            ),
+        }
+    }
+}
+
+impl Display for InvariantError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        for index in &self.path {
+            write!(f, "in child {}: ", index)?;
+        }
+        self.kind.fmt(f)
+    }
+}
+
+impl core::error::Error for InvariantError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{pointer::Pointer, PalmTree, Tree64, Unique};
+
+    #[test]
+    fn errors_say_where_they_are() {
+        let mut tree: PalmTree<u32, u32, Tree64<Unique>> =
+            PalmTree::load((0..1000).map(|i| (i, i)));
+        assert_eq!(Ok(()), tree.check_invariants());
+
+        let leaf = Pointer::make_mut(tree.root.as_mut().unwrap()).get_leaf_mut(3);
+        leaf.keys_mut().swap(5, 6);
+        let error = tree.check_invariants().unwrap_err();
+        assert_eq!(vec![3], error.path);
+        assert_eq!(Invariant::KeyOrder(6), error.kind);
+        assert_eq!("in child 3: leaf key 6 is out of order", error.to_string());
+
+        let leaf = Pointer::make_mut(tree.root.as_mut().unwrap()).get_leaf_mut(3);
+        leaf.keys_mut().swap(5, 6);
+        tree.size -= 1;
+        assert_eq!(
+            Err(InvariantError::new(Invariant::Length {
+                length: 999,
+                entries: 1000
+            })),
+            tree.check_invariants()
+        );
+    }
+}
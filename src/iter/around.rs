@@ -0,0 +1,130 @@
+use super::Iter;
+use crate::config::TreeConfig;
+use core::{
+    fmt::{Debug, Error, Formatter},
+    iter::{FusedIterator, Peekable, Rev},
+};
+
+/// An iterator over the entries nearest to a probe key, closest first.
+///
+/// It walks outwards from the probe with one cursor going down and another
+/// going up, taking whichever entry is closer by the `distance` function
+/// each time. On a tie, the lower key goes first.
+///
+/// ```
+/// # use palmtree::StdPalmTree;
+/// let tree = StdPalmTree::load(vec![(1i32, ()), (5, ()), (8, ()), (9, ()), (20, ())]);
+/// let nearest: Vec<i32> = tree
+///     .around(&7, 4, |a, b| (a - b).abs())
+///     .map(|(k, _)| *k)
+///     .collect();
+/// assert_eq!(vec![8, 5, 9, 1], nearest);
+/// ```
+pub struct Around<'a, K, V, C, F>
+where
+    K: Clone + Ord,
+    C: 'a + TreeConfig<K, V>,
+{
+    probe: K,
+    below: Peekable<Rev<Iter<'a, K, V, C>>>,
+    above: Peekable<Iter<'a, K, V, C>>,
+    distance: F,
+    remaining: usize,
+}
+
+impl<'a, K, V, C, F> Around<'a, K, V, C, F>
+where
+    K: Clone + Ord,
+    C: 'a + TreeConfig<K, V>,
+{
+    pub(crate) fn new(
+        probe: K,
+        below: Iter<'a, K, V, C>,
+        above: Iter<'a, K, V, C>,
+        limit: usize,
+        distance: F,
+    ) -> Self {
+        Self {
+            probe,
+            below: below.rev().peekable(),
+            above: above.peekable(),
+            distance,
+            remaining: limit,
+        }
+    }
+}
+
+impl<'a, K, V, C, F, D> Iterator for Around<'a, K, V, C, F>
+where
+    K: Clone + Ord,
+    C: 'a + TreeConfig<K, V>,
+    F: FnMut(&K, &K) -> D,
+    D: Ord,
+{
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let take_below = match (self.below.peek(), self.above.peek()) {
+            (None, None) => return None,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (Some((below, _)), Some((above, _))) => {
+                (self.distance)(&self.probe, below) <= (self.distance)(&self.probe, above)
+            }
+        };
+        self.remaining -= 1;
+        if take_below {
+            self.below.next()
+        } else {
+            self.above.next()
+        }
+    }
+}
+
+impl<'a, K, V, C, F, D> FusedIterator for Around<'a, K, V, C, F>
+where
+    K: Clone + Ord,
+    C: 'a + TreeConfig<K, V>,
+    F: FnMut(&K, &K) -> D,
+    D: Ord,
+{
+}
+
+impl<'a, K, V, C, F> Debug for Around<'a, K, V, C, F>
+where
+    K: Clone + Ord + Debug,
+    C: 'a + TreeConfig<K, V>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "Around({:?})", self.probe)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::StdPalmTree;
+
+    #[test]
+    fn nearest_first() {
+        let tree: StdPalmTree<i64, ()> = StdPalmTree::load((0..10_000).map(|i| (i * 3, ())));
+        let distance = |a: &i64, b: &i64| (a - b).abs();
+
+        // An exact match comes first, then alternating outwards, lower first on ties.
+        let keys: Vec<i64> = tree.around(&3000, 5, distance).map(|(k, _)| *k).collect();
+        assert_eq!(vec![3000, 2997, 3003, 2994, 3006], keys);
+        let keys: Vec<i64> = tree.around(&3001, 4, distance).map(|(k, _)| *k).collect();
+        assert_eq!(vec![3000, 3003, 2997, 3006], keys);
+
+        // Running off either end carries on from the other cursor.
+        let keys: Vec<i64> = tree.around(&-100, 3, distance).map(|(k, _)| *k).collect();
+        assert_eq!(vec![0, 3, 6], keys);
+        let keys: Vec<i64> = tree.around(&29_996, 3, distance).map(|(k, _)| *k).collect();
+        assert_eq!(vec![29_997, 29_994, 29_991], keys);
+
+        assert_eq!(10_000, tree.around(&15_000, 20_000, distance).count());
+        assert_eq!(0, tree.around(&15_000, 0, distance).count());
+    }
+}
@@ -0,0 +1,233 @@
+use super::Iter;
+use crate::config::TreeConfig;
+use core::{
+    fmt::{Debug, Error, Formatter},
+    iter::FusedIterator,
+};
+
+/// An iterator over runs of consecutive entries whose keys project to the
+/// same group.
+///
+/// Each item is the group, along with an iterator over the entries in it:
+///
+/// ```
+/// # use palmtree::StdPalmTree;
+/// let tree = StdPalmTree::load((0..10).map(|i| (i, i * 10)));
+/// let groups: Vec<(usize, Vec<usize>)> = tree
+///     .chunk_by(|key| key / 4)
+///     .map(|(group, entries)| (group, entries.map(|(_, v)| *v).collect()))
+///     .collect();
+/// assert_eq!(
+///     vec![(0, vec![0, 10, 20, 30]), (1, vec![40, 50, 60, 70]), (2, vec![80, 90])],
+///     groups
+/// );
+/// ```
+///
+/// Only consecutive entries are grouped, so a projection which isn't
+/// monotonic in the key can yield the same group more than once.
+pub struct ChunkBy<'a, K, V, C, F>
+where
+    C: TreeConfig<K, V>,
+{
+    iter: Iter<'a, K, V, C>,
+    /// The first entry which didn't belong in the last group we yielded.
+    pending: Option<(&'a K, &'a V)>,
+    project: F,
+}
+
+impl<'a, K, V, C, F> ChunkBy<'a, K, V, C, F>
+where
+    C: TreeConfig<K, V>,
+{
+    pub(crate) fn new(iter: Iter<'a, K, V, C>, project: F) -> Self {
+        Self {
+            iter,
+            pending: None,
+            project,
+        }
+    }
+}
+
+impl<'a, K, V, C, F, G> Iterator for ChunkBy<'a, K, V, C, F>
+where
+    K: Clone + Ord,
+    C: 'a + TreeConfig<K, V>,
+    F: FnMut(&K) -> G,
+    G: PartialEq,
+{
+    type Item = (G, Chunk<'a, K, V, C>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let first = self.pending.take().or_else(|| self.iter.next())?;
+        let group = (self.project)(first.0);
+        // The rest of the group starts where the iterator is now, so we keep
+        // a copy of it and walk ahead to find out how long the group is.
+        let rest = self.iter.clone();
+        let mut count = 0;
+        for entry in &mut self.iter {
+            if (self.project)(entry.0) != group {
+                self.pending = Some(entry);
+                break;
+            }
+            count += 1;
+        }
+        Some((
+            group,
+            Chunk {
+                first: Some(first),
+                rest,
+                remaining: count,
+            },
+        ))
+    }
+}
+
+impl<'a, K, V, C, F, G> FusedIterator for ChunkBy<'a, K, V, C, F>
+where
+    K: Clone + Ord,
+    C: 'a + TreeConfig<K, V>,
+    F: FnMut(&K) -> G,
+    G: PartialEq,
+{
+}
+
+impl<'a, K, V, C, F> Debug for ChunkBy<'a, K, V, C, F>
+where
+    C: 'a + TreeConfig<K, V>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "ChunkBy")
+    }
+}
+
+/// The entries in a single chunk from `ChunkBy`.
+pub struct Chunk<'a, K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    first: Option<(&'a K, &'a V)>,
+    rest: Iter<'a, K, V, C>,
+    /// How many entries are left in `rest` after `first`.
+    remaining: usize,
+}
+
+impl<'a, K, V, C> Clone for Chunk<'a, K, V, C>
+where
+    K: Clone + Ord,
+    C: TreeConfig<K, V>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            first: self.first,
+            rest: self.rest.clone(),
+            remaining: self.remaining,
+        }
+    }
+}
+
+impl<'a, K, V, C> Iterator for Chunk<'a, K, V, C>
+where
+    K: Clone + Ord,
+    C: 'a + TreeConfig<K, V>,
+{
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(first) = self.first.take() {
+            return Some(first);
+        }
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        self.rest.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.len();
+        (len, Some(len))
+    }
+}
+
+impl<'a, K, V, C> ExactSizeIterator for Chunk<'a, K, V, C>
+where
+    K: Clone + Ord,
+    C: 'a + TreeConfig<K, V>,
+{
+    fn len(&self) -> usize {
+        self.first.is_some() as usize + self.remaining
+    }
+}
+
+impl<'a, K, V, C> FusedIterator for Chunk<'a, K, V, C>
+where
+    K: Clone + Ord,
+    C: 'a + TreeConfig<K, V>,
+{
+}
+
+impl<'a, K, V, C> Debug for Chunk<'a, K, V, C>
+where
+    K: Clone + Ord + Debug,
+    V: Debug,
+    C: TreeConfig<K, V>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        f.debug_map().entries(self.clone()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::StdPalmTree;
+
+    #[test]
+    fn chunk_by_projection() {
+        let tree: StdPalmTree<usize, usize> = StdPalmTree::load((0..10_000).map(|i| (i, i)));
+        let mut expected = 0;
+        let mut groups = 0;
+        for (group, entries) in tree.chunk_by(|key| key / 100) {
+            assert_eq!(groups, group);
+            assert_eq!(100, entries.len());
+            for (key, value) in entries {
+                assert_eq!(expected, *key);
+                assert_eq!(expected, *value);
+                expected += 1;
+            }
+            groups += 1;
+        }
+        assert_eq!(100, groups);
+        assert_eq!(10_000, expected);
+
+        // Not consuming a group shouldn't throw the next one off.
+        let firsts: Vec<usize> = tree
+            .chunk_by(|key| key / 1000)
+            .map(|(_, mut entries)| *entries.next().unwrap().0)
+            .collect();
+        assert_eq!((0..10).map(|i| i * 1000).collect::<Vec<_>>(), firsts);
+    }
+
+    #[test]
+    fn chunk_by_range_and_edges() {
+        let tree: StdPalmTree<usize, usize> = StdPalmTree::load((0..1000).map(|i| (i, i)));
+        let groups: Vec<(bool, usize)> = tree
+            .range(95..=205)
+            .chunk_by(|key| (key / 10) % 2 == 0)
+            .map(|(group, entries)| (group, entries.count()))
+            .collect();
+        let mut expected = vec![(false, 5)];
+        for _ in 0..5 {
+            expected.push((true, 10));
+            expected.push((false, 10));
+        }
+        expected.push((true, 6));
+        assert_eq!(expected, groups);
+
+        assert_eq!(1, tree.chunk_by(|_| ()).count());
+        assert_eq!(1000, tree.chunk_by(|key| *key).count());
+        assert_eq!(
+            0,
+            StdPalmTree::<usize, usize>::new().chunk_by(|_| ()).count()
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use super::ScanMut;
+use crate::config::TreeConfig;
+use core::{
+    fmt::{Debug, Error, Formatter},
+    iter::FusedIterator,
+};
+
+/// An iterator which removes and yields every entry `pred` returns `true`
+/// for, in key order.
+///
+/// Entries are only looked at as the iterator gets to them, so if you drop
+/// it before it's done, the entries it hasn't reached yet stay in the tree,
+/// whether `pred` would have picked them or not.
+///
+/// ```
+/// # use palmtree::StdPalmTree;
+/// let mut tree = StdPalmTree::load((0..10).map(|i| (i, i)));
+/// let evens: Vec<_> = tree.extract_if(|key, _| key % 2 == 0).map(|(k, _)| k).collect();
+/// assert_eq!(vec![0, 2, 4, 6, 8], evens);
+/// assert_eq!(5, tree.len());
+/// ```
+pub struct ExtractIf<'a, K, V, C, F>
+where
+    C: TreeConfig<K, V>,
+{
+    scan: ScanMut<'a, K, V, C>,
+    pred: F,
+}
+
+impl<'a, K, V, C, F> ExtractIf<'a, K, V, C, F>
+where
+    C: TreeConfig<K, V>,
+{
+    pub(crate) fn new(scan: ScanMut<'a, K, V, C>, pred: F) -> Self {
+        Self { scan, pred }
+    }
+}
+
+impl<'a, K, V, C, F> Iterator for ExtractIf<'a, K, V, C, F>
+where
+    K: Clone + Ord,
+    V: Clone,
+    C: 'a + TreeConfig<K, V>,
+    F: FnMut(&K, &mut V) -> bool,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (key, value) = self.scan.next_mut()?;
+            if (self.pred)(key, value) {
+                return self.scan.remove_current();
+            }
+        }
+    }
+}
+
+impl<'a, K, V, C, F> FusedIterator for ExtractIf<'a, K, V, C, F>
+where
+    K: Clone + Ord,
+    V: Clone,
+    C: 'a + TreeConfig<K, V>,
+    F: FnMut(&K, &mut V) -> bool,
+{
+}
+
+impl<'a, K, V, C, F> Debug for ExtractIf<'a, K, V, C, F>
+where
+    C: 'a + TreeConfig<K, V>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "ExtractIf")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ImPalmTree;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn extract_lazily() {
+        let mut tree: ImPalmTree<usize, usize> = ImPalmTree::load((0..10_000).map(|i| (i, i)));
+        let copy = tree.clone();
+        let mut model: BTreeMap<usize, usize> = copy.iter().map(|(k, v)| (*k, *v)).collect();
+
+        let mut seen = 0;
+        let first: Vec<_> = tree
+            .extract_if(|key, value| {
+                seen += 1;
+                *value += 1;
+                key % 3 == 0
+            })
+            .take(1000)
+            .collect();
+        // The predicate has only seen up to the last entry we took out.
+        assert_eq!(2998, seen);
+        assert!(first
+            .iter()
+            .map(|(k, v)| (*k, *v))
+            .eq((0..1000).map(|i| (i * 3, i * 3 + 1))));
+        for (key, value) in model.iter_mut() {
+            if *key <= 2997 {
+                *value += 1;
+            }
+        }
+        model.retain(|key, _| key % 3 != 0 || *key > 2997);
+        tree.check_invariants().unwrap();
+        assert!(tree.iter().eq(model.iter()));
+
+        let rest: Vec<_> = tree.extract_if(|_, _| true).collect();
+        assert_eq!(model.len(), rest.len());
+        assert!(tree.is_empty());
+        assert!(copy.iter().all(|(k, v)| k == v));
+    }
+}
@@ -3,7 +3,11 @@ enum Next {
     Right,
 }
 use self::Next::*;
-use std::fmt::{Debug, Error, Formatter};
+use core::{
+    cmp::Ordering,
+    fmt::{Debug, Error, Formatter},
+    iter::Peekable,
+};
 
 pub struct MergeIter<A, L, R, Cmp, Eq> {
     left: L,
@@ -70,8 +74,8 @@ where
 
     fn next(&mut self) -> Option<Self::Item> {
         let next_result = match self.next {
-            Left => std::mem::replace(&mut self.next_left, self.left.next()),
-            Right => std::mem::replace(&mut self.next_right, self.right.next()),
+            Left => core::mem::replace(&mut self.next_left, self.left.next()),
+            Right => core::mem::replace(&mut self.next_right, self.right.next()),
         };
         self.next = Self::choose_next(&self.next_left, &self.next_right, &self.compare);
         self.check_eq();
@@ -84,3 +88,111 @@ impl<A, L, R, Cmp, Eq> Debug for MergeIter<A, L, R, Cmp, Eq> {
         write!(f, "MergeIter")
     }
 }
+
+/// Where an item from a `CoWalk` came from: one side, or both, when the
+/// two sides have items which compare equal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Merged<L, R> {
+    Left(L),
+    Right(R),
+    Both(L, R),
+}
+
+/// The right hand side of an ordered walk over two sequences, which we
+/// step along against each item from the left as it comes.
+pub(crate) struct Seek<R: Iterator> {
+    items: Peekable<R>,
+}
+
+impl<R: Iterator> Seek<R> {
+    pub(crate) fn new(items: R) -> Self {
+        Self {
+            items: items.peekable(),
+        }
+    }
+
+    /// Take the next step against `left`, the next item on the left, if
+    /// there is one: the next item on the right if it comes first, or
+    /// along with `left` if they're equal, or just `left` if it comes
+    /// first. Once both sides are out, we return `None`.
+    pub(crate) fn step<A, Cmp>(
+        &mut self,
+        left: Option<&A>,
+        compare: &mut Cmp,
+    ) -> Option<Merged<(), R::Item>>
+    where
+        Cmp: FnMut(&A, &R::Item) -> Ordering,
+    {
+        let ordering = match (left, self.items.peek()) {
+            (Some(left), Some(right)) => compare(left, right),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => return None,
+        };
+        Some(match ordering {
+            Ordering::Less => Merged::Left(()),
+            Ordering::Equal => Merged::Both((), self.items.next()?),
+            Ordering::Greater => Merged::Right(self.items.next()?),
+        })
+    }
+}
+
+/// Walk two sequences in ascending order together, as `MergeIter` does,
+/// but pairing up items which compare equal instead of dropping one of
+/// them, so whoever's consuming the walk gets to see both.
+///
+/// `compare` orders an item from the left against one from the right, so
+/// the two sides needn't be the same type.
+pub struct CoWalk<L, R, Cmp>
+where
+    L: Iterator,
+    R: Iterator,
+{
+    left: Peekable<L>,
+    right: Seek<R>,
+    compare: Cmp,
+}
+
+impl<L, R, Cmp> CoWalk<L, R, Cmp>
+where
+    L: Iterator,
+    R: Iterator,
+    Cmp: FnMut(&L::Item, &R::Item) -> Ordering,
+{
+    pub fn new(left: L, right: R, compare: Cmp) -> Self {
+        Self {
+            left: left.peekable(),
+            right: Seek::new(right),
+            compare,
+        }
+    }
+}
+
+impl<L, R, Cmp> Iterator for CoWalk<L, R, Cmp>
+where
+    L: Iterator,
+    R: Iterator,
+    Cmp: FnMut(&L::Item, &R::Item) -> Ordering,
+{
+    type Item = Merged<L::Item, R::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(
+            match self.right.step(self.left.peek(), &mut self.compare)? {
+                Merged::Left(()) => Merged::Left(self.left.next()?),
+                Merged::Right(right) => Merged::Right(right),
+                Merged::Both((), right) => Merged::Both(self.left.next()?, right),
+            },
+        )
+    }
+}
+
+impl<L, R, Cmp> Debug for CoWalk<L, R, Cmp>
+where
+    L: Iterator,
+    R: Iterator,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "CoWalk")
+    }
+}
@@ -1,7 +1,8 @@
 #![allow(unreachable_pub)] // pub exports below erroneously complain without this
 
 use crate::{config::TreeConfig, search::PathedPointer, PalmTree};
-use std::{
+use core::{
+    borrow::Borrow,
     cmp::Ordering,
     ops::{Bound, RangeBounds},
 };
@@ -16,18 +17,38 @@ mod owned;
 pub use owned::OwnedIter;
 
 mod merge;
-pub use merge::MergeIter;
+pub(crate) use merge::Seek;
+pub use merge::{CoWalk, MergeIter, Merged};
 
-fn paths_from_range<'a, Lifetime, K, V, C, R>(
-    tree: &'a PalmTree<K, V, C>,
+mod around;
+pub use around::Around;
+
+mod chunk_by;
+pub use chunk_by::{Chunk, ChunkBy};
+
+mod pairs;
+pub use pairs::Pairs;
+
+mod scan_mut;
+pub use scan_mut::ScanMut;
+
+mod extract_if;
+pub use extract_if::ExtractIf;
+
+mod projected;
+pub use projected::{IntoKeys, IntoValues, Keys, Values, ValuesMut};
+
+fn paths_from_range<Lifetime, K, V, C, Q, R>(
+    tree: &PalmTree<K, V, C>,
     range: R,
 ) -> Option<(
     PathedPointer<Lifetime, K, V, C>,
     PathedPointer<Lifetime, K, V, C>,
 )>
 where
-    K: Clone + Ord,
-    R: RangeBounds<K>,
+    K: Clone + Ord + Borrow<Q>,
+    Q: Ord + ?Sized,
+    R: RangeBounds<Q>,
     C: TreeConfig<K, V>,
 {
     match (range.start_bound(), range.end_bound()) {
@@ -76,7 +97,8 @@ where
 
 #[cfg(test)]
 mod test {
-    use crate::StdPalmTree;
+    use crate::{ImPalmTree, StdPalmTree};
+    use std::ops::Bound;
 
     #[test]
     fn consuming_iter() {
@@ -88,6 +110,18 @@ mod test {
         }
     }
 
+    #[test]
+    fn consuming_iter_leaves_clones_alone() {
+        let tree = ImPalmTree::load((0..4096usize).map(|i| (i, i)));
+        let snapshot = tree.clone();
+        assert!(tree.into_iter().eq((0..4096).map(|i| (i, i))));
+        assert_eq!(4096, snapshot.len());
+        assert!(snapshot
+            .iter()
+            .map(|(k, v)| (*k, *v))
+            .eq((0..4096).map(|i| (i, i))));
+    }
+
     #[test]
     fn iterate_single_leaf() {
         let size = 64usize;
@@ -141,6 +175,7 @@ mod test {
 
     #[test]
     #[should_panic]
+    #[allow(clippy::reversed_empty_ranges)]
     fn descending_range_iter() {
         let tree = StdPalmTree::load((0..1usize).map(|i| (i, i)));
         let expected = Vec::<(usize, usize)>::new();
@@ -183,6 +218,39 @@ mod test {
         assert_eq!(expected, result);
     }
 
+    #[test]
+    fn excluded_start_past_stale_branch_key() {
+        // Removing 62 and 63 leaves the first leaf's branch key at 63, above
+        // anything the leaf holds, and a search for 62 lands in that leaf.
+        let mut tree = StdPalmTree::load((0..128usize).map(|i| (i, i)));
+        tree.remove(&62);
+        tree.remove(&63);
+        let result: Vec<usize> = tree
+            .range((Bound::Excluded(62), Bound::Unbounded))
+            .map(|(k, _)| *k)
+            .collect();
+        assert_eq!((64..128).collect::<Vec<_>>(), result);
+    }
+
+    #[test]
+    fn included_end_between_keys() {
+        let tree = StdPalmTree::load((0..1000usize).map(|i| (i * 10, i)));
+        for end in &[0usize, 5, 12, 635, 640, 645, 9995, 20_000] {
+            let expected: Vec<usize> = (0..1000).map(|i| i * 10).filter(|k| k <= end).collect();
+            let result: Vec<usize> = tree.range(..=end).map(|(k, _)| *k).collect();
+            assert_eq!(expected, result, "..={}", end);
+            let result: Vec<usize> = tree.range(..=end).rev().map(|(k, _)| *k).collect();
+            assert!(
+                result.iter().rev().eq(expected.iter()),
+                "..={} reversed",
+                end
+            );
+        }
+        let tree = StdPalmTree::load((10..20usize).map(|i| (i, i)));
+        assert_eq!(None, tree.range(..=5).next());
+        assert_eq!(None, tree.range(..=5).next_back());
+    }
+
     #[test]
     fn iterate_over_emptied_tree() {
         let mut tree: StdPalmTree<u8, u8> = StdPalmTree::new();
@@ -265,7 +333,7 @@ mod test {
             (254, 242),
             (255, 54),
         ];
-        let tree: StdPalmTree<u8, u8> = StdPalmTree::load(input.clone().into_iter());
+        let tree: StdPalmTree<u8, u8> = StdPalmTree::load(input.clone());
         let result: Vec<(u8, u8)> = tree.range(..253).map(|(k, v)| (*k, *v)).collect();
         let expected: Vec<(u8, u8)> = input.into_iter().filter(|(k, _)| k < &253).collect();
         assert_eq!(expected, result);
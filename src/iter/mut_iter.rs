@@ -1,6 +1,7 @@
 use super::paths_from_range;
 use crate::{config::TreeConfig, search::PathedPointer, PalmTree};
-use std::{
+use core::{
+    borrow::Borrow,
     cmp::Ordering,
     fmt::{Debug, Formatter},
     iter::FusedIterator,
@@ -39,9 +40,11 @@ where
     /// let mut it2 = tree.iter_mut();
     /// assert_eq!(it1.next(), it2.next());
     /// ```
-    pub(crate) fn new<R>(tree: &'a mut PalmTree<K, V, C>, range: R) -> Self
+    pub(crate) fn new<Q, R>(tree: &'a mut PalmTree<K, V, C>, range: R) -> Self
     where
-        R: RangeBounds<K>,
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
     {
         if let Some((left, right)) = paths_from_range(tree, range) {
             Self { left, right }
@@ -151,7 +154,7 @@ impl<'a, K, V, C> Debug for IterMut<'a, K, V, C>
 where
     C: 'a + TreeConfig<K, V>,
 {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         write!(f, "IterMut")
     }
 }
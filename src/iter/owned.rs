@@ -1,9 +1,15 @@
 use crate::{branch::Branch, config::TreeConfig, pointer::Pointer, search::PathedPointer};
-use std::{
+use core::{
     fmt::{Debug, Formatter},
     iter::FusedIterator,
 };
 
+/// An iterator which moves the entries out of a tree.
+///
+/// Entries are moved out of the leaves as we go, so any node still shared
+/// with a clone of the tree has to be copied first, which needs `V: Clone`.
+/// A `Unique` tree never shares a node, but the bound can't depend on the
+/// pointer kind, so it applies to every tree.
 pub struct OwnedIter<K, V, C>
 where
     C: TreeConfig<K, V>,
@@ -20,13 +26,20 @@ where
     C: TreeConfig<K, V>,
 {
     pub(crate) fn new(
-        tree: Option<Pointer<Branch<K, V, C>, C::PointerKind>>,
+        mut tree: Option<Pointer<Branch<K, V, C>, C::PointerKind>>,
         remaining: usize,
-    ) -> Self {
+    ) -> Self
+    where
+        V: Clone,
+    {
+        // We move entries out of the leaves as we go, so we can't share them with anyone.
+        if let Some(ref mut root) = tree {
+            Pointer::make_mut(root).make_unique();
+        }
         if let Some(ref root) = tree {
             Self {
-                left: PathedPointer::lowest(&root),
-                right: PathedPointer::highest(&root),
+                left: PathedPointer::lowest(root),
+                right: PathedPointer::highest(root),
                 tree,
                 remaining,
             }
@@ -49,7 +62,9 @@ where
     type Item = (K, V);
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.tree.is_none() {
+        // Once the two ends have met, every leaf left between them is
+        // empty, so there's no point walking over them.
+        if self.tree.is_none() || self.remaining == 0 {
             return None;
         }
         loop {
@@ -78,7 +93,7 @@ where
     C: TreeConfig<K, V>,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
-        if self.tree.is_none() {
+        if self.tree.is_none() || self.remaining == 0 {
             return None;
         }
         loop {
@@ -87,7 +102,7 @@ where
                 Some(leaf) => leaf,
             };
             if leaf.is_empty() {
-                unsafe { self.left.step_back() };
+                unsafe { self.right.step_back() };
             } else {
                 self.remaining -= 1;
                 return leaf.pop_back();
@@ -115,7 +130,7 @@ where
     V: Debug,
     C: TreeConfig<K, V>,
 {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         write!(f, "OwnedIter")
     }
 }
@@ -0,0 +1,136 @@
+use super::Iter;
+use crate::config::TreeConfig;
+use core::{
+    fmt::{Debug, Error, Formatter},
+    iter::FusedIterator,
+};
+
+/// An iterator over each pair of adjacent entries.
+///
+/// ```
+/// # use palmtree::StdPalmTree;
+/// let tree = StdPalmTree::load(vec![(1, ()), (2, ()), (4, ()), (8, ())]);
+/// let gaps: Vec<i32> = tree.iter_pairs().map(|((a, _), (b, _))| b - a).collect();
+/// assert_eq!(vec![1, 2, 4], gaps);
+/// ```
+pub struct Pairs<'a, K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    /// Every entry but the last.
+    left: Iter<'a, K, V, C>,
+    /// Every entry but the first, so always one step ahead of `left`.
+    right: Iter<'a, K, V, C>,
+}
+
+impl<'a, K, V, C> Pairs<'a, K, V, C>
+where
+    K: Clone + Ord,
+    C: 'a + TreeConfig<K, V>,
+{
+    pub(crate) fn new(iter: Iter<'a, K, V, C>) -> Self {
+        let mut left = iter.clone();
+        let mut right = iter;
+        left.next_back();
+        right.next();
+        Self { left, right }
+    }
+}
+
+impl<'a, K, V, C> Clone for Pairs<'a, K, V, C>
+where
+    K: Clone + Ord,
+    C: TreeConfig<K, V>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            left: self.left.clone(),
+            right: self.right.clone(),
+        }
+    }
+}
+
+impl<'a, K, V, C> Iterator for Pairs<'a, K, V, C>
+where
+    K: Clone + Ord,
+    C: 'a + TreeConfig<K, V>,
+{
+    type Item = ((&'a K, &'a V), (&'a K, &'a V));
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Both cursors cover the same number of entries, so they run out at
+        // the same time.
+        Some((self.left.next()?, self.right.next()?))
+    }
+}
+
+impl<'a, K, V, C> DoubleEndedIterator for Pairs<'a, K, V, C>
+where
+    K: Clone + Ord,
+    C: 'a + TreeConfig<K, V>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        Some((self.left.next_back()?, self.right.next_back()?))
+    }
+}
+
+impl<'a, K, V, C> FusedIterator for Pairs<'a, K, V, C>
+where
+    K: Clone + Ord,
+    C: 'a + TreeConfig<K, V>,
+{
+}
+
+impl<'a, K, V, C> Debug for Pairs<'a, K, V, C>
+where
+    K: Clone + Ord + Debug,
+    V: Debug,
+    C: TreeConfig<K, V>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::StdPalmTree;
+
+    #[test]
+    fn adjacent_pairs() {
+        let tree: StdPalmTree<usize, usize> = StdPalmTree::load((0..10_000).map(|i| (i * 2, i)));
+        let pairs: Vec<(usize, usize)> =
+            tree.iter_pairs().map(|((a, _), (b, _))| (*a, *b)).collect();
+        assert_eq!(
+            (0..9_999).map(|i| (i * 2, i * 2 + 2)).collect::<Vec<_>>(),
+            pairs
+        );
+
+        let pairs: Vec<(usize, usize)> = tree
+            .range_pairs(100..=110)
+            .rev()
+            .map(|((_, a), (_, b))| (*a, *b))
+            .collect();
+        assert_eq!(
+            vec![(54, 55), (53, 54), (52, 53), (51, 52), (50, 51)],
+            pairs
+        );
+
+        // Meeting in the middle from both ends.
+        let mut pairs = tree.range_pairs(0..8);
+        assert_eq!(Some(0), pairs.next().map(|((a, _), _)| *a));
+        assert_eq!(Some(4), pairs.next_back().map(|((a, _), _)| *a));
+        assert_eq!(Some(2), pairs.next().map(|((a, _), _)| *a));
+        assert_eq!(None, pairs.next_back());
+        assert_eq!(None, pairs.next());
+    }
+
+    #[test]
+    fn too_few_for_a_pair() {
+        let tree: StdPalmTree<usize, usize> = StdPalmTree::new();
+        assert_eq!(0, tree.iter_pairs().count());
+        let tree: StdPalmTree<usize, usize> = StdPalmTree::load(vec![(1, 1), (2, 2)]);
+        assert_eq!(0, tree.range_pairs(..2).count());
+        assert_eq!(1, tree.iter_pairs().count());
+    }
+}
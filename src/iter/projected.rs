@@ -0,0 +1,362 @@
+use super::{Iter, IterMut, OwnedIter};
+use crate::config::TreeConfig;
+use core::{
+    fmt::{Debug, Error, Formatter},
+    iter::FusedIterator,
+};
+
+/// An iterator over the keys of a tree, in order.
+pub struct Keys<'a, K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    iter: Iter<'a, K, V, C>,
+}
+
+/// An iterator over the values of a tree, in key order.
+pub struct Values<'a, K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    iter: Iter<'a, K, V, C>,
+}
+
+/// An iterator over mutable references to the values of a tree, in key
+/// order.
+pub struct ValuesMut<'a, K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    iter: IterMut<'a, K, V, C>,
+}
+
+/// A consuming iterator over the keys of a tree, in order.
+pub struct IntoKeys<K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    iter: OwnedIter<K, V, C>,
+}
+
+/// A consuming iterator over the values of a tree, in key order.
+pub struct IntoValues<K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    iter: OwnedIter<K, V, C>,
+}
+
+impl<'a, K, V, C> Keys<'a, K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    pub(crate) fn new(iter: Iter<'a, K, V, C>) -> Self {
+        Self { iter }
+    }
+}
+
+impl<'a, K, V, C> Values<'a, K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    pub(crate) fn new(iter: Iter<'a, K, V, C>) -> Self {
+        Self { iter }
+    }
+}
+
+impl<'a, K, V, C> ValuesMut<'a, K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    pub(crate) fn new(iter: IterMut<'a, K, V, C>) -> Self {
+        Self { iter }
+    }
+}
+
+impl<K, V, C> IntoKeys<K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    pub(crate) fn new(iter: OwnedIter<K, V, C>) -> Self {
+        Self { iter }
+    }
+}
+
+impl<K, V, C> IntoValues<K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    pub(crate) fn new(iter: OwnedIter<K, V, C>) -> Self {
+        Self { iter }
+    }
+}
+
+impl<'a, K, V, C> Clone for Keys<'a, K, V, C>
+where
+    K: Clone + Ord,
+    C: TreeConfig<K, V>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            iter: self.iter.clone(),
+        }
+    }
+}
+
+impl<'a, K, V, C> Clone for Values<'a, K, V, C>
+where
+    K: Clone + Ord,
+    C: TreeConfig<K, V>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            iter: self.iter.clone(),
+        }
+    }
+}
+
+impl<'a, K, V, C> Iterator for Keys<'a, K, V, C>
+where
+    K: Clone + Ord,
+    C: 'a + TreeConfig<K, V>,
+{
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(key, _)| key)
+    }
+}
+
+impl<'a, K, V, C> DoubleEndedIterator for Keys<'a, K, V, C>
+where
+    K: Clone + Ord,
+    C: 'a + TreeConfig<K, V>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back().map(|(key, _)| key)
+    }
+}
+
+impl<'a, K, V, C> FusedIterator for Keys<'a, K, V, C>
+where
+    K: Clone + Ord,
+    C: 'a + TreeConfig<K, V>,
+{
+}
+
+impl<'a, K, V, C> Iterator for Values<'a, K, V, C>
+where
+    K: Clone + Ord,
+    C: 'a + TreeConfig<K, V>,
+{
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(_, value)| value)
+    }
+}
+
+impl<'a, K, V, C> DoubleEndedIterator for Values<'a, K, V, C>
+where
+    K: Clone + Ord,
+    C: 'a + TreeConfig<K, V>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back().map(|(_, value)| value)
+    }
+}
+
+impl<'a, K, V, C> FusedIterator for Values<'a, K, V, C>
+where
+    K: Clone + Ord,
+    C: 'a + TreeConfig<K, V>,
+{
+}
+
+impl<'a, K, V, C> Iterator for ValuesMut<'a, K, V, C>
+where
+    K: Clone + Ord,
+    C: 'a + TreeConfig<K, V>,
+{
+    type Item = &'a mut V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(_, value)| value)
+    }
+}
+
+impl<'a, K, V, C> DoubleEndedIterator for ValuesMut<'a, K, V, C>
+where
+    K: Clone + Ord,
+    C: 'a + TreeConfig<K, V>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back().map(|(_, value)| value)
+    }
+}
+
+impl<'a, K, V, C> FusedIterator for ValuesMut<'a, K, V, C>
+where
+    K: Clone + Ord,
+    C: 'a + TreeConfig<K, V>,
+{
+}
+
+impl<K, V, C> Iterator for IntoKeys<K, V, C>
+where
+    K: Clone + Ord,
+    C: TreeConfig<K, V>,
+{
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(key, _)| key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<K, V, C> DoubleEndedIterator for IntoKeys<K, V, C>
+where
+    K: Clone + Ord,
+    C: TreeConfig<K, V>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back().map(|(key, _)| key)
+    }
+}
+
+impl<K, V, C> ExactSizeIterator for IntoKeys<K, V, C>
+where
+    K: Clone + Ord,
+    C: TreeConfig<K, V>,
+{
+}
+
+impl<K, V, C> FusedIterator for IntoKeys<K, V, C>
+where
+    K: Clone + Ord,
+    C: TreeConfig<K, V>,
+{
+}
+
+impl<K, V, C> Iterator for IntoValues<K, V, C>
+where
+    K: Clone + Ord,
+    C: TreeConfig<K, V>,
+{
+    type Item = V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(_, value)| value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<K, V, C> DoubleEndedIterator for IntoValues<K, V, C>
+where
+    K: Clone + Ord,
+    C: TreeConfig<K, V>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back().map(|(_, value)| value)
+    }
+}
+
+impl<K, V, C> ExactSizeIterator for IntoValues<K, V, C>
+where
+    K: Clone + Ord,
+    C: TreeConfig<K, V>,
+{
+}
+
+impl<K, V, C> FusedIterator for IntoValues<K, V, C>
+where
+    K: Clone + Ord,
+    C: TreeConfig<K, V>,
+{
+}
+
+impl<'a, K, V, C> Debug for Keys<'a, K, V, C>
+where
+    K: Clone + Ord + Debug,
+    C: TreeConfig<K, V>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl<'a, K, V, C> Debug for Values<'a, K, V, C>
+where
+    K: Clone + Ord,
+    V: Debug,
+    C: TreeConfig<K, V>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl<'a, K, V, C> Debug for ValuesMut<'a, K, V, C>
+where
+    C: 'a + TreeConfig<K, V>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "ValuesMut")
+    }
+}
+
+impl<K, V, C> Debug for IntoKeys<K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "IntoKeys")
+    }
+}
+
+impl<K, V, C> Debug for IntoValues<K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "IntoValues")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::StdPalmTree;
+
+    #[test]
+    fn projections() {
+        let mut tree: StdPalmTree<usize, usize> = (0..10_000).map(|i| (i, i * 2)).collect();
+        assert!(tree.keys().copied().eq(0..10_000));
+        assert!(tree
+            .values()
+            .rev()
+            .copied()
+            .eq((0..10_000).rev().map(|i| i * 2)));
+        assert!(tree.range_keys(100..110).copied().eq(100..110));
+        assert!(tree.range_values(..=2).copied().eq(vec![0, 2, 4]));
+
+        for value in tree.values_mut().rev().take(10) {
+            *value = 0;
+        }
+        // Key 0 had a value of 0 to begin with.
+        assert_eq!(11, tree.values().filter(|value| **value == 0).count());
+
+        let mut keys = tree.clone().into_keys();
+        assert_eq!(10_000, keys.len());
+        assert_eq!(Some(9999), keys.next_back());
+        assert_eq!(Some(0), keys.next());
+        assert_eq!(9998, keys.len());
+        assert!(tree.into_values().take(3).eq(vec![0, 2, 4]));
+    }
+}
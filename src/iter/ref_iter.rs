@@ -1,6 +1,7 @@
-use super::paths_from_range;
+use super::{paths_from_range, ChunkBy};
 use crate::{config::TreeConfig, search::PathedPointer, PalmTree};
-use std::{
+use core::{
+    borrow::Borrow,
     cmp::Ordering,
     fmt::{Debug, Error, Formatter},
     iter::FusedIterator,
@@ -40,9 +41,11 @@ where
         }
     }
 
-    pub(crate) fn new<R>(tree: &'a PalmTree<K, V, C>, range: R) -> Self
+    pub(crate) fn new<Q, R>(tree: &'a PalmTree<K, V, C>, range: R) -> Self
     where
-        R: RangeBounds<K>,
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
     {
         if let Some((left, right)) = paths_from_range(tree, range) {
             Self { left, right }
@@ -51,6 +54,16 @@ where
         }
     }
 
+    /// Group runs of consecutive entries whose keys `project` to the same
+    /// value. See `ChunkBy`.
+    pub fn chunk_by<F, G>(self, project: F) -> ChunkBy<'a, K, V, C, F>
+    where
+        F: FnMut(&K) -> G,
+        G: PartialEq,
+    {
+        ChunkBy::new(self, project)
+    }
+
     fn step_forward(&mut self) {
         let result = unsafe { self.left.step_forward() };
         debug_assert!(result);
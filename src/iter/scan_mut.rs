@@ -0,0 +1,176 @@
+use crate::{
+    config::TreeConfig,
+    search::{find_key, PathedPointer},
+    PalmTree,
+};
+use core::fmt::{Debug, Error, Formatter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// We haven't handed out an entry yet.
+    Start,
+    /// The cursor points at the entry we last handed out.
+    Current,
+    /// The entry we last handed out was removed, and the cursor points at
+    /// the one after it, which we haven't handed out yet.
+    Removed,
+}
+
+/// A lending iterator over mutable entries, which can remove them as it goes.
+///
+/// Unlike `IterMut`, each entry is only borrowed until the next call to
+/// `next_mut`, which is what lets `remove_current` take the current entry
+/// out of the tree without invalidating the scan:
+///
+/// ```
+/// # use palmtree::StdPalmTree;
+/// let mut tree = StdPalmTree::load((0..10).map(|i| (i, i)));
+/// let mut scan = tree.scan_mut();
+/// while let Some((key, value)) = scan.next_mut() {
+///     if key % 2 == 0 {
+///         scan.remove_current();
+///     } else {
+///         *value *= 10;
+///     }
+/// }
+/// let entries: Vec<_> = tree.iter().map(|(k, v)| (*k, *v)).collect();
+/// assert_eq!(vec![(1, 10), (3, 30), (5, 50), (7, 70), (9, 90)], entries);
+/// ```
+pub struct ScanMut<'a, K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    tree: &'a mut PalmTree<K, V, C>,
+    cursor: PathedPointer<&'a mut (K, V), K, V, C>,
+    state: State,
+}
+
+impl<'a, K, V, C> ScanMut<'a, K, V, C>
+where
+    K: Clone + Ord,
+    C: 'a + TreeConfig<K, V>,
+{
+    pub(crate) fn new(tree: &'a mut PalmTree<K, V, C>) -> Self {
+        Self {
+            tree,
+            cursor: PathedPointer::null(),
+            state: State::Start,
+        }
+    }
+
+    /// Advance to the next entry, and return it.
+    #[allow(clippy::should_implement_trait)] // it can't be an `Iterator`, that's the point
+    pub fn next_mut(&mut self) -> Option<(&K, &mut V)> {
+        match self.state {
+            State::Start => {
+                if !self.tree.is_empty() {
+                    if let Some(ref root) = self.tree.root {
+                        self.cursor = PathedPointer::lowest(root);
+                    }
+                }
+            }
+            State::Current => {
+                unsafe { self.cursor.step_forward() };
+            }
+            State::Removed => {}
+        }
+        self.state = State::Current;
+        self.current()
+    }
+
+    /// Return the entry the last call to `next_mut` returned, unless it's
+    /// since been removed.
+    pub fn current(&mut self) -> Option<(&K, &mut V)> {
+        if self.state != State::Current || self.cursor.is_null() {
+            return None;
+        }
+        Some(unsafe { self.cursor.clone().into_entry_mut() })
+    }
+
+    /// Remove the entry the last call to `next_mut` returned from the tree.
+    ///
+    /// The next call to `next_mut` returns the entry after it.
+    pub fn remove_current(&mut self) -> Option<(K, V)>
+    where
+        V: Clone,
+    {
+        if self.state != State::Current || self.cursor.is_null() {
+            return None;
+        }
+        let cursor = core::mem::replace(&mut self.cursor, PathedPointer::null());
+        self.tree.size -= 1;
+        let (key, value) = unsafe { cursor.remove() };
+        self.tree
+            .rebalance_path(|keys| find_key(keys, &key).unwrap_or(keys.len() - 1));
+        self.tree.entries_removed(1);
+        // Removing can move entries between nodes, or even rebuild the tree
+        // if it compacts itself, so we look the next entry up again from the
+        // root.
+        if !self.tree.is_empty() {
+            if let Some(ref root) = self.tree.root {
+                self.cursor = PathedPointer::higher_than_key(root, &key);
+            }
+        }
+        self.state = State::Removed;
+        Some((key, value))
+    }
+}
+
+impl<'a, K, V, C> Debug for ScanMut<'a, K, V, C>
+where
+    C: 'a + TreeConfig<K, V>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "ScanMut")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::StdPalmTree;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn scan_and_remove() {
+        let mut tree: StdPalmTree<usize, usize> = StdPalmTree::load((0..10_000).map(|i| (i, i)));
+        let mut nat: BTreeMap<usize, usize> = (0..10_000).map(|i| (i, i)).collect();
+        let mut removed = Vec::new();
+        let mut scan = tree.scan_mut();
+        while let Some((key, value)) = scan.next_mut() {
+            // Remove long runs, so whole leaves go, as well as odd entries.
+            if key % 3 == 0 || (2000..4500).contains(key) {
+                removed.push(scan.remove_current().unwrap());
+                assert_eq!(None, scan.remove_current());
+                assert_eq!(None, scan.current());
+            } else {
+                *value += 1;
+            }
+        }
+        assert_eq!(None, scan.next_mut());
+        for (key, _) in &removed {
+            nat.remove(key);
+        }
+        for value in nat.values_mut() {
+            *value += 1;
+        }
+        assert_eq!(nat.len(), tree.len());
+        assert!(nat.iter().eq(tree.iter()));
+        assert!(removed.iter().all(|(k, v)| k == v));
+    }
+
+    #[test]
+    fn scan_removing_everything() {
+        let mut tree: StdPalmTree<usize, usize> = StdPalmTree::load((0..1000).map(|i| (i, i)));
+        let mut scan = tree.scan_mut();
+        let mut count = 0;
+        while scan.next_mut().is_some() {
+            scan.remove_current();
+            count += 1;
+        }
+        assert_eq!(1000, count);
+        assert!(tree.is_empty());
+
+        let mut scan = tree.scan_mut();
+        assert_eq!(None, scan.next_mut());
+    }
+}
@@ -0,0 +1,402 @@
+//! Order preserving key encodings.
+//!
+//! Values encoded with this module compare as byte strings in the same order
+//! as the values themselves, so a tree keyed on `Vec<u8>` can index composite
+//! keys made out of tuples of integers, floats and strings:
+//!
+//! ```
+//! use palmtree::{keycodec, StdPalmTree};
+//! let mut index = StdPalmTree::new();
+//! index.insert(keycodec::encode(&("bravo", -3i32)), 1);
+//! index.insert(keycodec::encode(&("alpha", 7i32)), 2);
+//! index.insert(keycodec::encode(&("bravo", -10i32)), 3);
+//! let keys: Vec<(String, i32)> = index
+//!     .iter()
+//!     .map(|(key, _)| keycodec::decode(key).unwrap())
+//!     .collect();
+//! assert_eq!(
+//!     vec![
+//!         ("alpha".to_string(), 7),
+//!         ("bravo".to_string(), -10),
+//!         ("bravo".to_string(), -3)
+//!     ],
+//!     keys
+//! );
+//! ```
+//!
+//! Integers are written big-endian, with the sign bit flipped for signed
+//! types. Floats are ordered by `total_cmp`, so `-0.0` sorts before `0.0`
+//! and NaNs sort at either end. Strings and byte strings are terminated, with
+//! any zero bytes inside them escaped, so that a string sorts before every
+//! longer string it's a prefix of, even inside a tuple.
+
+use alloc::{string::String, vec::Vec};
+use core::fmt::{Display, Error, Formatter};
+
+/// A type which can be written out in an order preserving encoding.
+pub trait KeyEncode {
+    /// Append the encoding of `self` to `out`.
+    fn encode_to(&self, out: &mut Vec<u8>);
+}
+
+/// A type which can be read back out of an order preserving encoding.
+pub trait KeyDecode: Sized {
+    /// Decode a value from the start of `input`, advancing it past the
+    /// bytes consumed.
+    fn decode_from(input: &mut &[u8]) -> Result<Self, DecodeError>;
+}
+
+/// The reasons decoding can fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DecodeError {
+    /// The input ended in the middle of a value.
+    UnexpectedEnd,
+    /// There were bytes left over after the value was decoded.
+    TrailingBytes,
+    /// The input doesn't hold a valid encoding of the type being decoded.
+    Invalid,
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            DecodeError::UnexpectedEnd => write!(f, "unexpected end of encoded key"),
+            DecodeError::TrailingBytes => write!(f, "trailing bytes after encoded key"),
+            DecodeError::Invalid => write!(f, "invalid encoded key"),
+        }
+    }
+}
+
+impl core::error::Error for DecodeError {}
+
+/// Encode `value` into a byte string which sorts in the same order as the value.
+pub fn encode<T>(value: &T) -> Vec<u8>
+where
+    T: KeyEncode + ?Sized,
+{
+    let mut out = Vec::new();
+    value.encode_to(&mut out);
+    out
+}
+
+/// Decode a value encoded with `encode`, which must take up all of `bytes`.
+pub fn decode<T>(mut bytes: &[u8]) -> Result<T, DecodeError>
+where
+    T: KeyDecode,
+{
+    let value = T::decode_from(&mut bytes)?;
+    if bytes.is_empty() {
+        Ok(value)
+    } else {
+        Err(DecodeError::TrailingBytes)
+    }
+}
+
+fn take<'a>(input: &mut &'a [u8], count: usize) -> Result<&'a [u8], DecodeError> {
+    if input.len() < count {
+        return Err(DecodeError::UnexpectedEnd);
+    }
+    let (head, tail) = input.split_at(count);
+    *input = tail;
+    Ok(head)
+}
+
+macro_rules! impl_unsigned {
+    ($($type:ty),*) => {
+        $(
+            impl KeyEncode for $type {
+                fn encode_to(&self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_be_bytes());
+                }
+            }
+
+            impl KeyDecode for $type {
+                fn decode_from(input: &mut &[u8]) -> Result<Self, DecodeError> {
+                    const SIZE: usize = core::mem::size_of::<$type>();
+                    let mut bytes = [0; SIZE];
+                    bytes.copy_from_slice(take(input, SIZE)?);
+                    Ok(<$type>::from_be_bytes(bytes))
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_signed {
+    ($($type:ty => $unsigned:ty),*) => {
+        $(
+            impl KeyEncode for $type {
+                fn encode_to(&self, out: &mut Vec<u8>) {
+                    // Flipping the sign bit puts negative numbers below positive ones.
+                    ((*self as $unsigned) ^ (1 << (<$unsigned>::BITS - 1))).encode_to(out)
+                }
+            }
+
+            impl KeyDecode for $type {
+                fn decode_from(input: &mut &[u8]) -> Result<Self, DecodeError> {
+                    let bits = <$unsigned>::decode_from(input)?;
+                    Ok((bits ^ (1 << (<$unsigned>::BITS - 1))) as $type)
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_float {
+    ($($type:ty => $unsigned:ty),*) => {
+        $(
+            impl KeyEncode for $type {
+                fn encode_to(&self, out: &mut Vec<u8>) {
+                    // Negative floats have all their bits flipped, so that bigger
+                    // magnitudes sort lower, and positive floats just the sign bit.
+                    let bits = self.to_bits();
+                    let sign = 1 << (<$unsigned>::BITS - 1);
+                    let bits = if bits & sign != 0 { !bits } else { bits ^ sign };
+                    bits.encode_to(out)
+                }
+            }
+
+            impl KeyDecode for $type {
+                fn decode_from(input: &mut &[u8]) -> Result<Self, DecodeError> {
+                    let bits = <$unsigned>::decode_from(input)?;
+                    let sign = 1 << (<$unsigned>::BITS - 1);
+                    let bits = if bits & sign != 0 { bits ^ sign } else { !bits };
+                    Ok(<$type>::from_bits(bits))
+                }
+            }
+        )*
+    };
+}
+
+impl_unsigned!(u8, u16, u32, u64, u128, usize);
+impl_signed!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128, isize => usize);
+impl_float!(f32 => u32, f64 => u64);
+
+impl KeyEncode for bool {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+}
+
+impl KeyDecode for bool {
+    fn decode_from(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        match take(input, 1)?[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(DecodeError::Invalid),
+        }
+    }
+}
+
+impl KeyEncode for char {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        (*self as u32).encode_to(out)
+    }
+}
+
+impl KeyDecode for char {
+    fn decode_from(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        core::char::from_u32(u32::decode_from(input)?).ok_or(DecodeError::Invalid)
+    }
+}
+
+// Byte strings are written out with every zero byte escaped as `00 FF`,
+// and terminated with `00 00`, which sorts below any escaped zero.
+
+impl KeyEncode for [u8] {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        for byte in self {
+            out.push(*byte);
+            if *byte == 0 {
+                out.push(0xff);
+            }
+        }
+        out.extend_from_slice(&[0, 0]);
+    }
+}
+
+impl KeyEncode for Vec<u8> {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        self.as_slice().encode_to(out)
+    }
+}
+
+impl KeyDecode for Vec<u8> {
+    fn decode_from(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        let mut out = Vec::new();
+        loop {
+            match take(input, 1)?[0] {
+                0 => match take(input, 1)?[0] {
+                    0 => return Ok(out),
+                    0xff => out.push(0),
+                    _ => return Err(DecodeError::Invalid),
+                },
+                byte => out.push(byte),
+            }
+        }
+    }
+}
+
+impl KeyEncode for str {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        self.as_bytes().encode_to(out)
+    }
+}
+
+impl KeyEncode for String {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        self.as_str().encode_to(out)
+    }
+}
+
+impl KeyDecode for String {
+    fn decode_from(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        String::from_utf8(Vec::decode_from(input)?).map_err(|_| DecodeError::Invalid)
+    }
+}
+
+impl<A> KeyEncode for &A
+where
+    A: KeyEncode + ?Sized,
+{
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        (**self).encode_to(out)
+    }
+}
+
+impl KeyEncode for () {
+    fn encode_to(&self, _out: &mut Vec<u8>) {}
+}
+
+impl KeyDecode for () {
+    fn decode_from(_input: &mut &[u8]) -> Result<Self, DecodeError> {
+        Ok(())
+    }
+}
+
+macro_rules! impl_tuple {
+    ($($name:ident),*) => {
+        impl<$($name),*> KeyEncode for ($($name,)*)
+        where
+            $($name: KeyEncode),*
+        {
+            #[allow(non_snake_case)]
+            fn encode_to(&self, out: &mut Vec<u8>) {
+                let ($($name,)*) = self;
+                $($name.encode_to(out);)*
+            }
+        }
+
+        impl<$($name),*> KeyDecode for ($($name,)*)
+        where
+            $($name: KeyDecode),*
+        {
+            fn decode_from(input: &mut &[u8]) -> Result<Self, DecodeError> {
+                Ok(($($name::decode_from(input)?,)*))
+            }
+        }
+    };
+}
+
+impl_tuple!(A);
+impl_tuple!(A, B);
+impl_tuple!(A, B, C);
+impl_tuple!(A, B, C, D);
+impl_tuple!(A, B, C, D, E);
+impl_tuple!(A, B, C, D, E, F);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fmt::Debug;
+
+    /// Check that `values`, given in ascending order, encode into ascending
+    /// byte strings, and decode back to themselves.
+    fn check_order<T>(values: &[T])
+    where
+        T: KeyEncode + KeyDecode + Debug + PartialEq,
+    {
+        let encoded: Vec<Vec<u8>> = values.iter().map(encode).collect();
+        for (index, pair) in encoded.windows(2).enumerate() {
+            assert!(
+                pair[0] < pair[1],
+                "{:?} doesn't encode below {:?}",
+                values[index],
+                values[index + 1]
+            );
+        }
+        for (value, bytes) in values.iter().zip(&encoded) {
+            assert_eq!(value, &decode::<T>(bytes).unwrap());
+        }
+    }
+
+    #[test]
+    fn integers() {
+        check_order(&[0u8, 1, 127, 128, 255]);
+        check_order(&[0u64, 1, 255, 256, u64::MAX - 1, u64::MAX]);
+        check_order(&[i32::MIN, -65536, -256, -1, 0, 1, 255, 256, i32::MAX]);
+        check_order(&[i128::MIN, -1, 0, i128::MAX]);
+    }
+
+    #[test]
+    fn floats() {
+        check_order(&[
+            f64::NEG_INFINITY,
+            -1e300,
+            -1.5,
+            -f64::MIN_POSITIVE,
+            -0.0,
+            0.0,
+            f64::MIN_POSITIVE,
+            1.0,
+            1.5,
+            1e300,
+            f64::INFINITY,
+        ]);
+        check_order(&[-2.5f32, -0.0, 0.0, 0.25]);
+        assert!(decode::<f64>(&encode(&f64::NAN)).unwrap().is_nan());
+    }
+
+    #[test]
+    fn strings() {
+        check_order(&[
+            String::new(),
+            "\0".to_string(),
+            "\0\0".to_string(),
+            "\0a".to_string(),
+            "a".to_string(),
+            "a\0".to_string(),
+            "ab".to_string(),
+            "b".to_string(),
+            "é".to_string(),
+        ]);
+        check_order(&[vec![], vec![0u8], vec![0, 0xff], vec![1], vec![0xff]]);
+    }
+
+    #[test]
+    fn tuples() {
+        check_order(&[
+            ("a".to_string(), 5i64),
+            ("a".to_string(), 10),
+            ("a\0".to_string(), -10),
+            ("ab".to_string(), -10),
+            ("b".to_string(), i64::MIN),
+        ]);
+        check_order(&[
+            (false, 'a', 1u16),
+            (false, 'b', 0),
+            (true, 'a', 0),
+            (true, 'a', 1),
+        ]);
+    }
+
+    #[test]
+    fn decode_errors() {
+        assert_eq!(Err(DecodeError::UnexpectedEnd), decode::<u32>(&[0, 0, 1]));
+        assert_eq!(Err(DecodeError::TrailingBytes), decode::<u8>(&[0, 0]));
+        assert_eq!(Err(DecodeError::UnexpectedEnd), decode::<String>(b"abc"));
+        assert_eq!(Err(DecodeError::Invalid), decode::<String>(&[0, 7]));
+        assert_eq!(Err(DecodeError::Invalid), decode::<String>(&[0xff, 0, 0]));
+        assert_eq!(Err(DecodeError::Invalid), decode::<bool>(&[2]));
+    }
+}
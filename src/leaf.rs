@@ -1,8 +1,23 @@
-use crate::{array::Array, config::TreeConfig, pointer::Pointer, InsertResult};
-use std::fmt::{Debug, Error, Formatter};
+use crate::{
+    array::Array,
+    batch::{in_bounds, Op},
+    config::TreeConfig,
+    invariant::{Invariant, InvariantError},
+    pointer::Pointer,
+    InsertResult,
+};
+use alloc::vec::Vec;
+use core::{
+    borrow::Borrow,
+    fmt::{Debug, Error, Formatter},
+    iter::Peekable,
+};
 use typenum::Unsigned;
 
 /// A leaf node contains an ordered sequence of direct mappings from keys to values.
+///
+/// If `V` is zero sized, so is `values`, and every operation on it compiles away,
+/// so a leaf of a set costs no more than its keys.
 pub(crate) struct Leaf<K, V, C>
 where
     C: TreeConfig<K, V>,
@@ -31,11 +46,13 @@ where
     C: TreeConfig<K, V>,
 {
     fn clone(&self) -> Self {
-        Self {
-            length: self.length,
-            keys: unsafe { self.keys.clone(self.length) },
-            values: unsafe { self.values.clone(self.length) },
+        // Entries are pushed one at a time, so that if a `clone` panics,
+        // the ones we've already cloned get dropped along with `out`.
+        let mut out = Self::new();
+        for (key, value) in self.keys().iter().zip(self.values().iter()) {
+            unsafe { out.push_unchecked(key.clone(), value.clone()) };
         }
+        out
     }
 }
 
@@ -75,6 +92,12 @@ where
         &self.keys()[self.len() - 1]
     }
 
+    /// The key which will be the highest key of the left node after a `split`
+    /// moving `right_len` entries out.
+    pub(crate) fn split_key(&self, right_len: usize) -> &K {
+        &self.keys()[self.len() - right_len - 1]
+    }
+
     pub(crate) fn keys(&self) -> &[K] {
         unsafe { self.keys.deref(self.length) }
     }
@@ -91,27 +114,79 @@ where
         unsafe { self.values.deref_mut(self.length) }
     }
 
+    /// The keys and the values side by side, with only the values mutable.
+    pub(crate) fn entries_mut(&mut self) -> (&[K], &mut [V]) {
+        unsafe {
+            (
+                self.keys.deref(self.length),
+                self.values.deref_mut(self.length),
+            )
+        }
+    }
+
+    /// Split this node in two, moving the last `right_len` entries into
+    /// the new right node.
     pub(crate) fn split(
         mut this: Pointer<Self, C::PointerKind>,
+        right_len: usize,
     ) -> (Pointer<Self, C::PointerKind>, Pointer<Self, C::PointerKind>)
     where
         K: Clone,
         V: Clone,
     {
-        let right = {
-            let this = Pointer::make_mut(&mut this);
-            let half = this.length / 2;
-            let right = Pointer::new(Leaf {
-                length: half,
-                keys: unsafe { Array::steal_from(&mut this.keys, this.length, half) },
-                values: unsafe { Array::steal_from(&mut this.values, this.length, half) },
-            });
-            this.length -= half;
-            right
-        };
+        let this_mut = Pointer::make_mut(&mut this);
+        let right = Pointer::new(this_mut.split_off(this_mut.len() - right_len));
         (this, right)
     }
 
+    /// Move the entries from `index` onwards into a new leaf.
+    pub(crate) fn split_off(&mut self, index: usize) -> Self {
+        if index >= self.length {
+            return Self::new();
+        }
+        let right = Leaf {
+            length: self.length - index,
+            keys: unsafe { Array::steal_from(&mut self.keys, self.length, index) },
+            values: unsafe { Array::steal_from(&mut self.values, self.length, index) },
+        };
+        self.length = index;
+        right
+    }
+
+    /// Move every entry out of `other` onto the end of this leaf.
+    pub(crate) fn append(&mut self, other: &mut Self) {
+        debug_assert!(self.length + other.length <= C::LeafSize::USIZE);
+        unsafe {
+            self.keys.append(self.length, &mut other.keys, other.length);
+            self.values
+                .append(self.length, &mut other.values, other.length);
+        }
+        self.length += other.length;
+        other.length = 0;
+    }
+
+    /// Move entries between two neighbours until the left one has
+    /// `left_len` of them, returning its new highest key.
+    pub(crate) fn even_out(left: &mut Self, right: &mut Self, left_len: usize) -> K
+    where
+        K: Clone,
+    {
+        if left.len() < left_len {
+            let moving = left_len - left.len();
+            let key = right.keys()[moving - 1].clone();
+            let rest = right.split_off(moving);
+            left.append(right);
+            *right = rest;
+            key
+        } else {
+            let key = left.keys()[left_len - 1].clone();
+            let mut moving = left.split_off(left_len);
+            moving.append(right);
+            *right = moving;
+            key
+        }
+    }
+
     pub(crate) unsafe fn push_unchecked(&mut self, key: K, value: V) {
         self.keys.push(self.length, key);
         self.values.push(self.length, value);
@@ -133,6 +208,80 @@ where
         result
     }
 
+    /// Remove every entry for which `f` returns `false`, keeping the rest in order.
+    ///
+    /// `len` is decremented by one for each entry removed.
+    pub(crate) fn retain<F>(&mut self, f: &mut F, len: &mut usize)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        // We compact the leaf in place, like `Vec::retain`: everything between
+        // `write` and `read` is a hole. The guard closes the holes up when it
+        // drops, even if `f` or a destructor panics, so the leaf stays valid.
+        struct Guard<'a, K, V, C>
+        where
+            C: TreeConfig<K, V>,
+        {
+            leaf: &'a mut Leaf<K, V, C>,
+            len: &'a mut usize,
+            length: usize,
+            read: usize,
+            write: usize,
+        }
+
+        impl<'a, K, V, C> Drop for Guard<'a, K, V, C>
+        where
+            C: TreeConfig<K, V>,
+        {
+            fn drop(&mut self) {
+                let remaining = self.length - self.read;
+                unsafe {
+                    let keys = self.leaf.keys.mut_ptr();
+                    let values = self.leaf.values.mut_ptr();
+                    keys.add(self.read).copy_to(keys.add(self.write), remaining);
+                    values
+                        .add(self.read)
+                        .copy_to(values.add(self.write), remaining);
+                }
+                self.leaf.length = self.write + remaining;
+                *self.len -= self.read - self.write;
+            }
+        }
+
+        let length = self.length;
+        let mut guard = Guard {
+            leaf: self,
+            len,
+            length,
+            read: 0,
+            write: 0,
+        };
+        while guard.read < length {
+            let index = guard.read;
+            unsafe {
+                let key = guard.leaf.keys.mut_ptr().add(index);
+                let value = guard.leaf.values.mut_ptr().add(index);
+                if f(&*key, &mut *value) {
+                    if guard.write != index {
+                        key.copy_to_nonoverlapping(guard.leaf.keys.mut_ptr().add(guard.write), 1);
+                        value.copy_to_nonoverlapping(
+                            guard.leaf.values.mut_ptr().add(guard.write),
+                            1,
+                        );
+                    }
+                    guard.write += 1;
+                    guard.read += 1;
+                } else {
+                    // Step past the entry before dropping it, so a panicking
+                    // destructor can't cause it to be dropped twice.
+                    guard.read += 1;
+                    core::ptr::drop_in_place(key);
+                    core::ptr::drop_in_place(value);
+                }
+            }
+        }
+    }
+
     pub(crate) fn pop_back(&mut self) -> Option<(K, V)> {
         if !self.is_empty() {
             let result =
@@ -167,21 +316,43 @@ where
     K: Clone + Ord,
     C: TreeConfig<K, V>,
 {
-    pub(crate) fn get(&self, key: &K) -> Option<&V> {
+    pub(crate) fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
         self.keys()
-            .binary_search(key)
+            .binary_search_by(|k| k.borrow().cmp(key))
             .ok()
             .map(|index| unsafe { self.values().get_unchecked(index) })
     }
 
-    pub(crate) fn get_mut(&mut self, key: &K) -> Option<&mut V> {
-        if let Ok(index) = self.keys().binary_search(key) {
+    pub(crate) fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        if let Ok(index) = self.keys().binary_search_by(|k| k.borrow().cmp(key)) {
             Some(unsafe { self.values_mut().get_unchecked_mut(index) })
         } else {
             None
         }
     }
 
+    /// Find the value for `key` through a raw pointer to the leaf, without
+    /// borrowing its values, so pointers to other values in the same leaf
+    /// stay good.
+    pub(crate) unsafe fn value_ptr<Q>(this: *mut Self, key: &Q) -> Option<*mut V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let length = core::ptr::addr_of!((*this).length).read();
+        let keys = (*core::ptr::addr_of!((*this).keys)).deref(length);
+        let index = keys.binary_search_by(|k| k.borrow().cmp(key)).ok()?;
+        Some(Array::raw_ptr(core::ptr::addr_of_mut!((*this).values)).add(index))
+    }
+
     pub(crate) fn get_linear(&self, key: &K) -> Option<&V> {
         for (index, stored_key) in self.keys().iter().enumerate() {
             if stored_key == key {
@@ -191,9 +362,28 @@ where
         None
     }
 
+    /// Check that this leaf isn't empty, and that its keys are in order,
+    /// above `lower` and no higher than `upper`.
+    pub(crate) fn check(&self, lower: Option<&K>, upper: &K) -> Result<(), InvariantError> {
+        if self.is_empty() {
+            return Err(InvariantError::new(Invariant::EmptyLeaf));
+        }
+        let mut previous = lower;
+        for (index, key) in self.keys().iter().enumerate() {
+            if previous.is_some_and(|previous| previous >= key) {
+                return Err(InvariantError::new(Invariant::KeyOrder(index)));
+            }
+            previous = Some(key);
+        }
+        if self.highest() > upper {
+            return Err(InvariantError::new(Invariant::KeyBound));
+        }
+        Ok(())
+    }
+
     pub(crate) fn insert(&mut self, key: K, value: V) -> InsertResult<K, V> {
         match self.keys().binary_search(&key) {
-            Ok(index) => InsertResult::Replaced(std::mem::replace(
+            Ok(index) => InsertResult::Replaced(core::mem::replace(
                 unsafe { self.values_mut().get_unchecked_mut(index) },
                 value,
             )),
@@ -207,6 +397,59 @@ where
             }
         }
     }
+
+    /// Apply the ops at the front of `batch` for as long as their keys fall
+    /// between `lower` and `upper`, returning `false` if we stopped at an
+    /// insert we don't have room for.
+    ///
+    /// `len` goes up or down by one for each entry added or removed, and
+    /// `removed` counts the removals.
+    pub(crate) fn apply_batch<I>(
+        &mut self,
+        batch: &mut Peekable<I>,
+        lower: Option<&K>,
+        upper: Option<&K>,
+        len: &mut usize,
+        removed: &mut usize,
+    ) -> bool
+    where
+        I: Iterator<Item = (K, Op<V>)>,
+    {
+        while let Some((key, op)) = batch.peek() {
+            if !in_bounds(key, lower, upper) {
+                return true;
+            }
+            let found = self.keys().binary_search(key);
+            if found.is_err() && matches!(op, Op::Insert(_)) && self.is_full() {
+                return false;
+            }
+            let (key, op) = batch.next().unwrap();
+            match (found, op) {
+                (Ok(index), Op::Insert(value)) | (Ok(index), Op::Update(value)) => {
+                    let _old = core::mem::replace(
+                        unsafe { self.values_mut().get_unchecked_mut(index) },
+                        value,
+                    );
+                }
+                (Ok(index), Op::Remove) => {
+                    let _entry = unsafe { self.remove_unchecked(index) };
+                    *len -= 1;
+                    *removed += 1;
+                    // Our branch has to drop us before anything else can
+                    // call into user code.
+                    if self.is_empty() {
+                        return true;
+                    }
+                }
+                (Err(index), Op::Insert(value)) => {
+                    unsafe { self.insert_unchecked(index, key, value) };
+                    *len += 1;
+                }
+                (Err(_), _) => {}
+            }
+        }
+        true
+    }
 }
 
 impl<K, V, C> Debug for Leaf<K, V, C>
@@ -220,3 +463,23 @@ where
         writeln!(f, "Leaf(len={}) {:?}", self.len(), pairs)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{config::Tree64, pointer::Unique};
+    use std::mem::size_of;
+
+    #[test]
+    fn zero_sized_values_take_no_space() {
+        type C = Tree64<Unique>;
+        assert_eq!(
+            0,
+            size_of::<Array<(), <C as TreeConfig<u64, ()>>::LeafSize>>()
+        );
+        assert_eq!(
+            size_of::<Leaf<u64, u64, C>>() - size_of::<[u64; 64]>(),
+            size_of::<Leaf<u64, (), C>>()
+        );
+    }
+}
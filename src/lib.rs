@@ -13,37 +13,95 @@
 #![allow(clippy::question_mark)] // this lint makes code less readable
 #![allow(clippy::large_enum_variant)] // this lint is buggy
 #![cfg_attr(core_intrinsics, feature(core_intrinsics))]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
-use std::fmt::{Debug, Error, Formatter};
-use std::{
+extern crate alloc;
+
+use alloc::{boxed::Box, collections::BTreeMap, vec::Vec};
+use core::fmt::{Debug, Error, Formatter};
+use core::{
+    borrow::Borrow,
     cmp::Ordering,
-    collections::BTreeMap,
     hash::{Hash, Hasher},
     iter::FromIterator,
-    ops::{Add, AddAssign, Index, IndexMut, RangeBounds},
+    ops::{
+        Add, AddAssign, BitAnd, BitAndAssign, BitOr, BitOrAssign, Bound, Index, IndexMut,
+        RangeBounds, Sub, SubAssign,
+    },
 };
 
+#[cfg(feature = "arbitrary")]
+mod arbitrary_impl;
 mod arch;
 mod array;
+mod batch;
+mod bimap;
+mod bounded;
 mod branch;
+mod builder;
 mod config;
+mod counter;
+mod cow;
+mod cursor;
+mod dot;
 mod entry;
+mod frozen;
+mod id_alloc;
+#[cfg(feature = "im-compat")]
+mod im_compat;
+mod invariant;
 mod iter;
+pub mod keycodec;
 mod leaf;
+mod memory;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod patch;
 mod pointer;
+mod range_map;
 mod search;
+#[cfg(feature = "serde")]
+mod serde_impl;
+mod set;
+
+// For `tree_config!`.
+#[doc(hidden)]
+pub use typenum as __typenum;
 
-use branch::Branch;
+use branch::{Branch, Cut};
+use config::split_len;
+use iter::Seek;
 use leaf::Leaf;
 use pointer::Pointer;
-use search::PathedPointer;
-
-pub use config::{Tree64, TreeConfig};
-pub use entry::Entry;
-pub use iter::{Iter, IterMut, MergeIter, OwnedIter};
-pub use pointer::{PointerKind, Shared, SyncShared, Unique};
+use search::{find_key, PathedPointer};
+
+pub use batch::Op;
+pub use bimap::BiPalmMap;
+pub use bounded::{BoundedPalmTree, Evict};
+pub use builder::PalmTreeBuilder;
+pub use config::{Tree128, Tree16, Tree256, Tree64, TreeConfig};
+pub use counter::PalmCounter;
+pub use cow::CowValue;
+pub use cursor::{Cursor, CursorMut};
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use frozen::{FrozenIter, FrozenPalmTree};
+pub use id_alloc::{IdAllocator, IdKey};
+pub use invariant::{Invariant, InvariantError};
+pub use iter::{
+    Around, Chunk, ChunkBy, CoWalk, ExtractIf, IntoKeys, IntoValues, Iter, IterMut, Keys,
+    MergeIter, Merged, OwnedIter, Pairs, ScanMut, Values, ValuesMut,
+};
+pub use memory::{LevelUsage, MemoryUsage};
+#[cfg(feature = "rayon")]
+pub use parallel::{ParIter, ParIterMut};
+pub use patch::{Patch, PatchError};
+pub use pointer::{PointerKind, Shared, SyncPointerKind, SyncShared, Unique};
+pub use range_map::{RangeMap, RangeSet};
+pub use set::{
+    Difference, Intersection, PalmSet, SetIntoIter, SetIter, SymmetricDifference, Union,
+};
 
-#[cfg(any(test, feature = "test"))]
+#[cfg(any(test, feature = "test", feature = "bench-util"))]
 pub mod tests;
 
 enum InsertResult<K, V> {
@@ -52,15 +110,43 @@ enum InsertResult<K, V> {
     Full(K, V),
 }
 
+/// A tree which owns its nodes outright, like a `BTreeMap`.
 pub type StdPalmTree<K, V> = PalmTree<K, V, Tree64<Unique>>;
+/// Another name for `RcPalmTree`.
 pub type ImPalmTree<K, V> = PalmTree<K, V, Tree64<Shared>>;
+/// A tree whose clones share nodes through an `Rc`, copying only the ones
+/// they change.
+///
+/// `Rc` can't cross threads, so neither can this:
+///
+/// ```compile_fail
+/// # use palmtree::RcPalmTree;
+/// let tree: RcPalmTree<u32, u32> = RcPalmTree::new();
+/// std::thread::spawn(move || tree.len());
+/// ```
+pub type RcPalmTree<K, V> = PalmTree<K, V, Tree64<Shared>>;
+/// A tree whose clones share nodes through an `Arc`, so they can be read
+/// and changed from different threads.
+///
+/// ```
+/// # use palmtree::SyncPalmTree;
+/// let tree: SyncPalmTree<u32, u32> = SyncPalmTree::load((0..1000).map(|i| (i, i)));
+/// let copy = tree.clone();
+/// let sum = std::thread::spawn(move || copy.values().sum::<u32>());
+/// assert_eq!(499_500, sum.join().unwrap());
+/// ```
 pub type SyncPalmTree<K, V> = PalmTree<K, V, Tree64<SyncShared>>;
+pub type StdPalmSet<T> = PalmSet<T, Tree64<Unique>>;
 
 pub struct PalmTree<K, V, C>
 where
     C: TreeConfig<K, V>,
 {
     size: usize,
+    /// Entries removed since the last compaction, if `C` asks for auto-compaction.
+    removed: usize,
+    /// The highest key an auto-compaction under way has got up to.
+    packed: Option<K>,
     root: Option<Pointer<Branch<K, V, C>, C::PointerKind>>,
 }
 
@@ -80,6 +166,8 @@ where
     pub fn new() -> Self {
         Self {
             size: 0,
+            removed: 0,
+            packed: None,
             root: None,
         }
     }
@@ -93,100 +181,64 @@ where
     /// Construct a B+-tree efficiently from an ordered iterator.
     ///
     /// This algorithm requires the results coming out of the iterator
-    /// to be in sorted order, with no duplicate keys, or the resulting
-    /// tree will be in a very bad state. In debug mode, this invariant
-    /// will be validated and panic ensues if it isn't held.
+    /// to be in sorted order, with no duplicate keys. We check this as we
+    /// go, at the cost of one comparison per entry, and panic if it
+    /// doesn't hold. Use `from_unsorted` for input in any order.
     pub fn load<I>(iter: I) -> Self
     where
         V: Clone,
         I: IntoIterator<Item = (K, V)>,
     {
-        fn push_stack<K, V, C>(
-            child: Pointer<Branch<K, V, C>, C::PointerKind>,
-            stack: &mut Vec<Pointer<Branch<K, V, C>, C::PointerKind>>,
-        ) where
-            K: Clone,
-            V: Clone,
-            C: TreeConfig<K, V>,
-        {
-            let mut parent = stack.pop().unwrap_or_else(|| Branch::new(true).into());
-            if parent.is_full() {
-                push_stack(parent, stack);
-                parent = Pointer::new(Branch::new(true));
-            }
-            Pointer::make_mut(&mut parent).push_branch(child.highest().clone(), child);
-            stack.push(parent);
-        }
-
-        #[cfg(debug_assertions)]
-        let mut last_record = (0, None);
-
-        let iter = iter.into_iter();
-        let mut size = 0;
-        let mut stack: Vec<Pointer<Branch<K, V, C>, C::PointerKind>> = Vec::new();
-        let mut parent: Branch<K, V, C> = Branch::new(false);
-        let mut leaf: Leaf<K, V, C> = Leaf::new();
+        Self::load_at(iter, 0)
+    }
 
-        // Loop over input, fill leaf, push into parent when full.
+    /// Load entries which start `offset` entries into a larger input, for
+    /// `par_load`, so an unordered key is reported at its index in the
+    /// whole input.
+    pub(crate) fn load_at<I>(iter: I, offset: usize) -> Self
+    where
+        V: Clone,
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut builder = PalmTreeBuilder::new();
         for (key, value) in iter {
-            #[cfg(debug_assertions)]
-            {
-                if let (last_index, Some(last_key)) = last_record {
-                    if last_key >= key {
-                        panic!("PalmTree::load: unordered key at index {}", last_index);
-                    }
-                    last_record = (last_index + 1, Some(key.clone()));
-                }
-            }
-
-            if leaf.is_full() {
-                // If parent is full, push it to the parent above it on the stack.
-                if parent.is_full() {
-                    push_stack(Pointer::new(parent), &mut stack);
-                    parent = Branch::new(false);
+            if let Some(last_key) = builder.last_key() {
+                if last_key >= &key {
+                    panic!(
+                        "PalmTree::load: unordered key at index {}",
+                        offset + builder.len()
+                    );
                 }
-
-                parent.push_leaf(leaf.highest().clone(), Pointer::new(leaf));
-
-                leaf = Leaf::new();
             }
-
-            // Push the input into the leaf.
-            unsafe { leaf.push_unchecked(key, value) };
-            size += 1;
-        }
-
-        // If the input was empty, return immediately with an empty tree.
-        if size == 0 {
-            return Self {
-                size: 0,
-                root: None,
-            };
-        }
-
-        // At end of input, push last leaf into parent, as above.
-        if parent.is_full() {
-            push_stack(Pointer::new(parent), &mut stack);
-            parent = Branch::new(false);
+            builder.push_sorted(key, value);
         }
-        parent.push_leaf(leaf.highest().clone(), Pointer::new(leaf));
-
-        // Push parent into the parent above it.
-        push_stack(Pointer::new(parent), &mut stack);
+        builder.finish()
+    }
 
-        // Fold parent stack into the top level parent.
-        while stack.len() > 1 {
-            let parent = stack.pop().unwrap();
-            push_stack(parent, &mut stack);
+    /// Construct a B+-tree from entries in any order, by sorting them and
+    /// then loading them as with `load`.
+    ///
+    /// Where a key turns up more than once, the last value for it wins, as
+    /// with inserting them one at a time.
+    ///
+    /// ```
+    /// # use palmtree::StdPalmTree;
+    /// let tree: StdPalmTree<u32, char> =
+    ///     StdPalmTree::from_unsorted(vec![(3, 'a'), (1, 'b'), (3, 'c')]);
+    /// assert_eq!(vec![(&1, &'b'), (&3, &'c')], tree.iter().collect::<Vec<_>>());
+    /// ```
+    pub fn from_unsorted<I>(iter: I) -> Self
+    where
+        V: Clone,
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut entries: Vec<(K, V)> = iter.into_iter().collect();
+        builder::sort_entries(&mut entries);
+        let mut builder = PalmTreeBuilder::new();
+        for (key, value) in entries {
+            builder.push_sorted(key, value);
         }
-
-        // The root is now the only item left on the stack.
-        let mut tree = Self {
-            size,
-            root: stack.pop(),
-        };
-        tree.trim_root();
-        tree
+        builder.finish()
     }
 
     // For benchmarking: lookup with a linear search instead of binary.
@@ -198,7 +250,11 @@ where
         }
     }
 
-    pub fn get(&self, key: &K) -> Option<&V> {
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
         if let Some(ref root) = self.root {
             root.get(key)
         } else {
@@ -206,9 +262,163 @@ where
         }
     }
 
-    pub fn get_mut(&mut self, key: &K) -> Option<&mut V>
+    /// Find the entry at the given fraction `q` of the way through the tree.
+    ///
+    /// `q` runs from `0.0` for the lowest key to `1.0` for the highest, and
+    /// the rank it gives is rounded to the nearest entry, so `quantile(0.99)`
+    /// is the p99 key. Returns `None` if the tree is empty or `q` is outside
+    /// that range.
+    ///
+    /// The tree doesn't keep counts of its subtrees, so this has to walk
+    /// the lengths of the leaves in front of the entry, which is O(n/B)
+    /// rather than O(log n).
+    pub fn quantile(&self, q: f64) -> Option<(&K, &V)> {
+        if self.is_empty() || !(0.0..=1.0).contains(&q) {
+            return None;
+        }
+        // `f64::round` needs std, but the rank is never negative, so adding a
+        // half and truncating rounds it the same way.
+        self.get_index((q * (self.len() - 1) as f64 + 0.5) as usize)
+    }
+
+    /// Find the median entry. For an even number of entries, this is
+    /// the lower of the two middle entries.
+    pub fn median(&self) -> Option<(&K, &V)> {
+        if self.is_empty() {
+            return None;
+        }
+        self.get_index((self.len() - 1) / 2)
+    }
+
+    /// Find the entry `index` places from the lowest, or `None` if there
+    /// are no more than `index` entries.
+    ///
+    /// Like `quantile`, this walks the lengths of the leaves in front of
+    /// the entry, which is O(n/B).
+    ///
+    /// ```
+    /// # use palmtree::StdPalmTree;
+    /// let tree: StdPalmTree<u32, u32> = StdPalmTree::load((0..10).map(|i| (i * 10, i)));
+    /// assert_eq!(Some((&30, &3)), tree.get_index(3));
+    /// assert_eq!(3, tree.rank(&30));
+    /// assert_eq!(4, tree.rank(&35));
+    /// assert_eq!(None, tree.get_index(10));
+    /// ```
+    pub fn get_index(&self, index: usize) -> Option<(&K, &V)> {
+        if index >= self.len() {
+            return None;
+        }
+        self.root.as_ref()?.get_by_rank(&mut { index })
+    }
+
+    /// Find the entry `index` places from the lowest, with the value
+    /// mutable. See `get_index`.
+    pub fn get_index_mut(&mut self, index: usize) -> Option<(&K, &mut V)>
+    where
+        V: Clone,
+    {
+        if index >= self.len() {
+            return None;
+        }
+        Pointer::make_mut(self.root.as_mut()?).get_by_rank_mut(index)
+    }
+
+    /// Count the entries with keys below `key`, which is the index `key`
+    /// has or would have if it were inserted. See `get_index`.
+    pub fn rank<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        match self.root {
+            Some(ref root) if !self.is_empty() => root.rank(key),
+            _ => 0,
+        }
+    }
+
+    /// Count the entries in each bucket between consecutive `bounds`.
+    ///
+    /// Bucket `i` holds the keys from `bounds[i]` up to, but not including,
+    /// `bounds[i + 1]`, so there's one bucket fewer than there are bounds,
+    /// and keys outside them aren't counted. The bounds must be in
+    /// ascending order.
+    pub fn histogram(&self, bounds: &[K]) -> Vec<usize> {
+        self.histogram_with(bounds, 0, |count, _, _| count + 1)
+    }
+
+    /// Fold the entries in each bucket between consecutive `bounds` into a
+    /// value, starting from `init`. See `histogram`.
+    pub fn histogram_with<A, F>(&self, bounds: &[K], init: A, mut f: F) -> Vec<A>
+    where
+        A: Clone,
+        F: FnMut(A, &K, &V) -> A,
+    {
+        assert!(
+            bounds.windows(2).all(|pair| pair[0] < pair[1]),
+            "PalmTree::histogram: bucket bounds aren't in ascending order"
+        );
+        if bounds.len() < 2 {
+            return Vec::new();
+        }
+        let count = bounds.len() - 1;
+        let mut buckets = Vec::with_capacity(count);
+        let mut acc = init.clone();
+        // One pass over everything between the outer bounds, moving along
+        // to the next bucket whenever a key reaches its upper bound.
+        for (key, value) in self.range(&bounds[0]..&bounds[count]) {
+            while key >= &bounds[buckets.len() + 1] {
+                buckets.push(core::mem::replace(&mut acc, init.clone()));
+            }
+            acc = f(acc, key, value);
+        }
+        buckets.push(acc);
+        buckets.resize(count, init);
+        buckets
+    }
+
+    /// Find the entry with the longest key which is a prefix of `probe`.
+    ///
+    /// This is for keys which are byte strings, such as routing tables or
+    /// hierarchical paths, and the keys must sort in the same order as their
+    /// bytes do, which holds for `Vec<u8>` and `String`.
+    pub fn get_longest_prefix(&self, probe: &[u8]) -> Option<(&K, &V)>
+    where
+        K: AsRef<[u8]>,
+    {
+        let root = self.root.as_ref()?;
+        let mut target = probe;
+        loop {
+            // Any key which is a prefix of `target` sorts between itself and
+            // `target`, so it shares at least as long a prefix with `target`
+            // as the closest key below does, which lets us cut `target` down
+            // to that until we find one.
+            let (key, value) = root.floor_by(&|key: &K| key.as_ref().cmp(target))?;
+            let key_bytes = key.as_ref();
+            if target.starts_with(key_bytes) {
+                return Some((key, value));
+            }
+            let common = key_bytes
+                .iter()
+                .zip(target)
+                .take_while(|(left, right)| left == right)
+                .count();
+            target = &target[..common];
+        }
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
     where
+        K: Borrow<Q>,
         V: Clone,
+        Q: Ord + ?Sized,
     {
         if let Some(ref mut root) = self.root {
             Pointer::make_mut(root).get_mut(key)
@@ -217,6 +427,115 @@ where
         }
     }
 
+    /// Look up the values for several keys at once for writing.
+    ///
+    /// Returns `None` if any of the keys aren't in the tree, or if any key
+    /// turns up more than once, since we can't hand out two mutable
+    /// references to the same value. Each key is a separate lookup, and
+    /// we compare every result with the ones before it, so this is meant
+    /// for a handful of keys, not thousands.
+    ///
+    /// ```
+    /// # use palmtree::StdPalmTree;
+    /// let mut tree: StdPalmTree<u32, u32> = (0..1000).map(|i| (i, i)).collect();
+    /// if let Some([from, to]) = tree.get_many_mut([&10, &900]) {
+    ///     std::mem::swap(from, to);
+    /// }
+    /// assert_eq!(Some(&900), tree.get(&10));
+    /// assert_eq!(None, tree.get_many_mut([&10, &10]));
+    /// ```
+    pub fn get_many_mut<Q, const N: usize>(&mut self, keys: [&Q; N]) -> Option<[&mut V; N]>
+    where
+        K: Borrow<Q>,
+        V: Clone,
+        Q: Ord + ?Sized,
+    {
+        // A `get_mut` for each key would reborrow the whole path down and
+        // invalidate the values we'd found before, so we go through raw
+        // pointers from a single borrow of the root instead.
+        let mut values = [core::ptr::null_mut::<V>(); N];
+        if N > 0 {
+            let root: *mut Branch<K, V, C> = Pointer::make_mut(self.root.as_mut()?);
+            let mut unique = Vec::new();
+            for (value, key) in values.iter_mut().zip(keys) {
+                *value = unsafe { Branch::value_ptr(root, key, &mut unique) }?;
+            }
+        }
+        for index in 1..N {
+            if values[..index].contains(&values[index]) {
+                return None;
+            }
+        }
+        Some(values.map(|value| unsafe { &mut *value }))
+    }
+
+    /// Look up the value for a key you know is in the tree, skipping the
+    /// checks for whether it's there on the way down.
+    ///
+    /// # Safety
+    ///
+    /// `key` must be in the tree. If it isn't, you get a reference to
+    /// whichever value we land on, or to memory past the end of a leaf.
+    /// Debug builds panic instead.
+    pub unsafe fn get_unchecked(&self, key: &K) -> &V {
+        debug_assert!(self.root.is_some(), "get_unchecked: empty tree");
+        match self.root {
+            Some(ref root) => root.get_unchecked(key),
+            None => core::hint::unreachable_unchecked(),
+        }
+    }
+
+    /// Look up the value for a key you know is in the tree for writing,
+    /// skipping the checks for whether it's there on the way down.
+    ///
+    /// # Safety
+    ///
+    /// `key` must be in the tree, as with `get_unchecked`.
+    pub unsafe fn get_mut_unchecked(&mut self, key: &K) -> &mut V
+    where
+        V: Clone,
+    {
+        debug_assert!(self.root.is_some(), "get_mut_unchecked: empty tree");
+        match self.root {
+            Some(ref mut root) => Pointer::make_mut(root).get_mut_unchecked(key),
+            None => core::hint::unreachable_unchecked(),
+        }
+    }
+
+    /// Look up a value for reading, while leaving the option to change it.
+    ///
+    /// Unlike `get_mut`, which copies any nodes on the way to the value
+    /// that are shared with another tree straight away, this only does so
+    /// when you call `CowValue::to_mut`.
+    pub fn get_cow<'a, Q>(&'a mut self, key: &'a Q) -> Option<CowValue<'a, K, V, C, Q>>
+    where
+        K: Borrow<Q>,
+        V: Clone,
+        Q: Ord + ?Sized,
+    {
+        CowValue::new(self, key)
+    }
+
+    /// Get the keys and values as slices, if they all fit in a single leaf.
+    ///
+    /// Small trees have a single leaf under the root, and this gives you
+    /// direct access to it without going through an iterator. An empty tree
+    /// gives you empty slices, and a tree with more than one leaf, even if
+    /// it's been emptied out so its entries would fit in one, gives `None`.
+    pub fn as_single_leaf_slices(&self) -> Option<(&[K], &[V])> {
+        match self.root {
+            None => Some((&[], &[])),
+            Some(ref root) if root.has_leaves() && root.len() <= 1 => match root.len() {
+                0 => Some((&[], &[])),
+                _ => {
+                    let leaf = root.get_leaf(0);
+                    Some((leaf.keys(), leaf.values()))
+                }
+            },
+            Some(_) => None,
+        }
+    }
+
     pub fn len(&self) -> usize {
         self.size
     }
@@ -225,181 +544,1449 @@ where
         self.len() == 0
     }
 
+    /// The number of levels of branches above the leaves, which is zero for
+    /// an empty tree.
+    ///
+    /// Every removal merges nodes left less than half full, and drops the
+    /// root when it's down to one child, so the tree gets shorter as it
+    /// empties as well as taller as it fills.
+    pub fn height(&self) -> usize {
+        match self.root {
+            Some(ref root) if !self.is_empty() => root.height(),
+            _ => 0,
+        }
+    }
+
     pub fn iter(&self) -> Iter<'_, K, V, C> {
         Iter::new(self, ..)
     }
 
-    pub fn iter_mut(&mut self) -> IterMut<'_, K, V, C> {
-        IterMut::new(self, ..)
+    pub fn keys(&self) -> Keys<'_, K, V, C> {
+        Keys::new(self.iter())
     }
 
-    pub fn range<R>(&self, range: R) -> Iter<'_, K, V, C>
-    where
-        R: RangeBounds<K>,
-    {
-        Iter::new(self, range)
+    pub fn values(&self) -> Values<'_, K, V, C> {
+        Values::new(self.iter())
     }
 
-    pub fn range_mut<R>(&mut self, range: R) -> IterMut<'_, K, V, C>
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V, C>
     where
-        R: RangeBounds<K>,
+        V: Clone,
     {
-        IterMut::new(self, range)
+        ValuesMut::new(self.iter_mut())
     }
 
-    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, C> {
-        Entry::new(self, key)
+    pub fn into_keys(self) -> IntoKeys<K, V, C>
+    where
+        V: Clone,
+    {
+        IntoKeys::new(self.into_iter())
     }
 
-    pub fn insert(&mut self, key: K, value: V) -> Option<V>
+    pub fn into_values(self) -> IntoValues<K, V, C>
     where
         V: Clone,
     {
-        match self.entry(key) {
-            Entry::Occupied(mut entry) => Some(entry.insert(value)),
-            Entry::Vacant(entry) => {
-                entry.insert(value);
-                None
-            }
-        }
+        IntoValues::new(self.into_iter())
     }
 
-    pub fn remove(&mut self, key: &K) -> Option<(K, V)> {
-        if let Ok(path) = PathedPointer::<&mut (K, V), _, _, _>::exact_key(self.root.as_mut()?, key)
-        {
-            self.size -= 1;
-            Some(unsafe { path.remove() })
-        } else {
-            None
-        }
+    /// Group runs of consecutive entries whose keys `project` to the same
+    /// value, such as timestamps falling on the same day. See `ChunkBy`.
+    pub fn chunk_by<F, G>(&self, project: F) -> ChunkBy<'_, K, V, C, F>
+    where
+        F: FnMut(&K) -> G,
+        G: PartialEq,
+    {
+        self.iter().chunk_by(project)
     }
 
-    pub fn remove_lowest(&mut self) -> Option<(K, V)> {
-        if self.is_empty() {
-            None
-        } else {
-            let path = PathedPointer::<&mut (K, V), _, _, _>::lowest(self.root.as_mut()?);
-            self.size -= 1;
-            Some(unsafe { path.remove() })
-        }
+    /// Iterate over each pair of adjacent entries, in order.
+    pub fn iter_pairs(&self) -> Pairs<'_, K, V, C> {
+        Pairs::new(self.iter())
     }
 
-    pub fn remove_highest(&mut self) -> Option<(K, V)> {
-        if self.is_empty() {
-            None
-        } else {
-            let path = PathedPointer::<&mut (K, V), _, _, _>::highest(self.root.as_mut()?);
-            self.size -= 1;
-            Some(unsafe { path.remove() })
-        }
+    /// Iterate over each pair of adjacent entries inside `range`.
+    pub fn range_pairs<Q, R>(&self, range: R) -> Pairs<'_, K, V, C>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        Pairs::new(self.range(range))
     }
 
-    fn merge_left_from(
-        left: impl Iterator<Item = (K, V)>,
-        right: impl Iterator<Item = (K, V)>,
-    ) -> impl Iterator<Item = (K, V)> {
-        MergeIter::merge(
-            left,
-            right,
-            |(left, _), (right, _)| left > right,
-            |(left, _), (right, _)| left == right,
+    /// Iterate over up to `limit` entries nearest to `key`, closest first,
+    /// as measured by `distance`. See `Around`.
+    pub fn around<F, D>(&self, key: &K, limit: usize, distance: F) -> Around<'_, K, V, C, F>
+    where
+        F: FnMut(&K, &K) -> D,
+        D: Ord,
+    {
+        Around::new(
+            key.clone(),
+            self.range(..key),
+            self.range(key..),
+            limit,
+            distance,
         )
     }
 
-    fn merge_right_from(
-        left: impl Iterator<Item = (K, V)>,
-        right: impl Iterator<Item = (K, V)>,
-    ) -> impl Iterator<Item = (K, V)> {
-        MergeIter::merge(
-            left,
-            right,
-            |(left, _), (right, _)| left >= right,
-            |(left, _), (right, _)| left == right,
-        )
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V, C>
+    where
+        V: Clone,
+    {
+        self.range_mut(..)
     }
 
-    pub fn merge_left_iter(left: Self, right: Self) -> impl Iterator<Item = (K, V)> {
-        Self::merge_left_from(left.into_iter(), right.into_iter())
+    /// Scan the tree's entries in order with a lending iterator, which can
+    /// also remove them as it goes. See `ScanMut`.
+    pub fn scan_mut(&mut self) -> ScanMut<'_, K, V, C>
+    where
+        V: Clone,
+    {
+        self.make_range_unique(&..);
+        ScanMut::new(self)
     }
 
-    pub fn merge_left(left: Self, right: Self) -> Self
+    /// Remove every entry `pred` returns `true` for, lazily, as the
+    /// iterator returned yields them. See `ExtractIf`.
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, K, V, C, F>
     where
         V: Clone,
+        F: FnMut(&K, &mut V) -> bool,
     {
-        Self::load(Self::merge_left_iter(left, right))
+        ExtractIf::new(self.scan_mut(), pred)
     }
 
-    pub fn merge_right_iter(left: Self, right: Self) -> impl Iterator<Item = (K, V)> {
-        Self::merge_right_from(left.into_iter(), right.into_iter())
+    /// Get a cursor at the lowest entry inside `bound`, or at nothing if
+    /// there's no such entry. See `Cursor`.
+    pub fn lower_bound<Q>(&self, bound: Bound<&Q>) -> Cursor<'_, K, V, C>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        Cursor::new(self, bound, false)
     }
 
-    pub fn merge_right(left: Self, right: Self) -> Self
+    /// Get a cursor at the highest entry inside `bound`, or at nothing if
+    /// there's no such entry.
+    pub fn upper_bound<Q>(&self, bound: Bound<&Q>) -> Cursor<'_, K, V, C>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        Cursor::new(self, bound, true)
+    }
+
+    /// Get a cursor which can change the tree, at the lowest entry inside
+    /// `bound`. See `CursorMut`.
+    ///
+    /// The cursor can go anywhere in the tree, so any nodes shared with
+    /// another tree are copied first, as with `scan_mut`.
+    pub fn lower_bound_mut<Q>(&mut self, bound: Bound<&Q>) -> CursorMut<'_, K, V, C>
     where
+        K: Borrow<Q>,
         V: Clone,
+        Q: Ord + ?Sized,
     {
-        Self::load(Self::merge_right_iter(left, right))
+        self.make_range_unique(&..);
+        CursorMut::new(self, bound, false)
     }
 
-    pub fn append_left(&mut self, other: Self)
+    /// Get a cursor which can change the tree, at the highest entry inside
+    /// `bound`.
+    pub fn upper_bound_mut<Q>(&mut self, bound: Bound<&Q>) -> CursorMut<'_, K, V, C>
     where
+        K: Borrow<Q>,
         V: Clone,
+        Q: Ord + ?Sized,
     {
-        let root = self.root.take();
-        if root.is_some() {
-            let left = OwnedIter::new(root, self.size);
-            let right = other.into_iter();
-            *self = Self::load(Self::merge_left_from(left, right));
-        } else {
-            *self = other;
-        }
+        self.make_range_unique(&..);
+        CursorMut::new(self, bound, true)
     }
 
-    pub fn append_right(&mut self, other: Self)
+    pub fn range<Q, R>(&self, range: R) -> Iter<'_, K, V, C>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        Iter::new(self, range)
+    }
+
+    pub fn range_keys<Q, R>(&self, range: R) -> Keys<'_, K, V, C>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        Keys::new(self.range(range))
+    }
+
+    pub fn range_values<Q, R>(&self, range: R) -> Values<'_, K, V, C>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        Values::new(self.range(range))
+    }
+
+    pub fn range_mut<Q, R>(&mut self, range: R) -> IterMut<'_, K, V, C>
     where
+        K: Borrow<Q>,
         V: Clone,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
     {
-        let root = self.root.take();
-        if root.is_some() {
-            let left = OwnedIter::new(root, self.size);
-            let right = other.into_iter();
-            *self = Self::load(Self::merge_right_from(left, right));
-        } else {
-            *self = other;
-        }
+        self.make_range_unique(&range);
+        IterMut::new(self, range)
     }
 
-    fn trim_root(&mut self)
+    /// Overwrite the values in `range`, in key order, with those from `values`.
+    ///
+    /// Keys and the shape of the tree are left alone. Writing stops when
+    /// either the range or `values` runs out, and the number of values
+    /// written is returned.
+    pub fn fill_range<Q, R, I>(&mut self, range: R, values: I) -> usize
     where
+        K: Borrow<Q>,
         V: Clone,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+        I: IntoIterator<Item = V>,
     {
-        if let Some(ref mut root) = self.root {
-            // If a branch bearing root only has one child, we can replace the root with that child.
-            while root.has_branches() && root.len() == 1 {
-                *root = Pointer::make_mut(root).remove_last_branch().1;
-            }
+        let mut written = 0;
+        for ((_, slot), value) in self.range_mut(range).zip(values) {
+            *slot = value;
+            written += 1;
         }
+        written
     }
 
-    fn split_root(root: &mut Pointer<Branch<K, V, C>, C::PointerKind>)
+    /// Overwrite the values in `range`, in key order, with copies of `values`.
+    ///
+    /// As with `fill_range`, this stops at the end of whichever is shorter
+    /// and returns the number of values written.
+    pub fn copy_values_from_slice<Q, R>(&mut self, range: R, values: &[V]) -> usize
     where
-        V: Clone,
+        K: Borrow<Q>,
+        V: Copy,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
     {
-        let old_root = std::mem::replace(root, Branch::new(true).into());
-        let (left, right) = Branch::split(old_root);
-        Pointer::make_mut(root).push_branch_pair(
-            left.highest().clone(),
-            left,
-            right.highest().clone(),
-            right,
-        );
+        self.fill_range(range, values.iter().copied())
     }
 
-    pub fn insert_recursive(&mut self, key: K, value: V) -> Option<V>
+    /// Replace every key in `range` with `shift` of it, in place.
+    ///
+    /// This is for maps of offsets, where inserting into the underlying
+    /// sequence moves everything after it along, as in
+    /// `tree.shift_keys(at.., |key| key + len)`. `shift` must keep the keys
+    /// in the same order, and mustn't move any of them past a key outside
+    /// `range`, or this will panic, leaving the tree unchanged.
+    ///
+    /// We call `shift` once for each key, and hold on to the shifted keys
+    /// until we've checked them all, so this allocates room for as many
+    /// keys as there are in `range`.
+    pub fn shift_keys<Q, R, F>(&mut self, range: R, mut shift: F)
     where
+        K: Borrow<Q>,
         V: Clone,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+        F: FnMut(&K) -> K,
     {
-        let len = self.size;
-        if let Some(ref mut root) = self.root {
+        let bounds = (range.start_bound(), range.end_bound());
+        let (first, last) = match (self.range(bounds).next(), self.range(bounds).next_back()) {
+            (Some((first, _)), Some((last, _))) => (first, last),
+            _ => return,
+        };
+        let below = self
+            .range::<K, _>((Bound::Unbounded, Bound::Excluded(first)))
+            .next_back();
+        let above = self
+            .range::<K, _>((Bound::Excluded(last), Bound::Unbounded))
+            .next();
+        let mut shifted: Vec<K> = Vec::new();
+        for (key, _) in self.range(bounds) {
+            let key = shift(key);
+            match shifted.last() {
+                Some(previous) if previous >= &key => {
+                    panic!("PalmTree::shift_keys: shifted keys would be out of order")
+                }
+                None if below.is_some_and(|(below, _)| below >= &key) => {
+                    panic!("PalmTree::shift_keys: shifted keys would pass a key outside the range")
+                }
+                _ => shifted.push(key),
+            }
+        }
+        if above.is_some_and(|(above, _)| shifted.last().is_some_and(|last| above <= last)) {
+            panic!("PalmTree::shift_keys: shifted keys would pass a key outside the range");
+        }
+        if let Some(ref mut root) = self.root {
+            Pointer::make_mut(root).shift_keys(&range, &mut shifted.into_iter());
+        }
+        self.validate();
+    }
+
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, C>
+    where
+        V: Clone,
+    {
+        // Inserting past the end of a branch goes into its last child.
+        self.make_path_unique(|keys| find_key(keys, &key).unwrap_or(keys.len() - 1));
+        Entry::new(self, key)
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V>
+    where
+        V: Clone,
+    {
+        match self.entry(key) {
+            Entry::Occupied(mut entry) => Some(entry.insert(value)),
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+                None
+            }
+        }
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        V: Clone,
+        Q: Ord + ?Sized,
+    {
+        self.make_path_unique(|keys| find_key(keys, key).unwrap_or(keys.len() - 1));
+        if let Ok(path) = PathedPointer::<&mut (K, V), _, _, _>::exact_key(self.root.as_mut()?, key)
+        {
+            self.size -= 1;
+            let result = unsafe { path.remove() };
+            self.rebalance_path(|keys| find_key(keys, key).unwrap_or(keys.len() - 1));
+            self.entries_removed(1);
+            Some(result)
+        } else {
+            None
+        }
+    }
+
+    /// Construct a copy of this tree with `key` mapped to `value`, leaving
+    /// this one as it was.
+    ///
+    /// With a `Shared` or `SyncShared` config, the copy shares every node
+    /// with this tree except the ones on the path down to `key`, so this
+    /// costs about as much as an `insert`. With `Unique`, every node has
+    /// to be copied.
+    ///
+    /// ```
+    /// # use palmtree::ImPalmTree;
+    /// let tree: ImPalmTree<u32, &str> = ImPalmTree::load(vec![(1, "a"), (2, "b")]);
+    /// let updated = tree.update(2, "B");
+    /// assert_eq!(Some(&"b"), tree.get(&2));
+    /// assert_eq!(Some(&"B"), updated.get(&2));
+    /// ```
+    #[must_use]
+    pub fn update(&self, key: K, value: V) -> Self
+    where
+        V: Clone,
+    {
+        let mut tree = self.clone();
+        tree.insert(key, value);
+        tree
+    }
+
+    /// Construct a copy of this tree without `key`, leaving this one as it
+    /// was. See `update`.
+    #[must_use]
+    pub fn without<Q>(&self, key: &Q) -> Self
+    where
+        K: Borrow<Q>,
+        V: Clone,
+        Q: Ord + ?Sized,
+    {
+        let mut tree = self.clone();
+        tree.remove(key);
+        tree
+    }
+
+    /// Remove the `n` entries with the lowest keys, and return them as a
+    /// tree of their own.
+    ///
+    /// Rather than removing them one by one, we cut the tree in two along
+    /// a single path, moving every node on either side of it whole. Finding
+    /// where to cut means counting the entries in front of it, a leaf at a
+    /// time.
+    pub fn pop_first_n(&mut self, n: usize) -> Self
+    where
+        V: Clone,
+    {
+        if n >= self.len() {
+            return core::mem::take(self);
+        }
+        let rest = self.cut(Cut::<K>::Rank(n));
+        core::mem::replace(self, rest)
+    }
+
+    /// Remove the `n` entries with the highest keys, and return them as a
+    /// tree of their own. See `pop_first_n`.
+    pub fn pop_last_n(&mut self, n: usize) -> Self
+    where
+        V: Clone,
+    {
+        if n >= self.len() {
+            return core::mem::take(self);
+        }
+        self.cut(Cut::<K>::Rank(self.len() - n))
+    }
+
+    /// Split the tree at `key`, keeping the entries with keys below it and
+    /// returning the rest as a tree of their own.
+    ///
+    /// Like `pop_first_n`, this cuts along a single path and moves every
+    /// node on either side of it whole, but it finds the path by key, so
+    /// the only other cost is counting the entries in the half that moves.
+    pub fn split_off<Q>(&mut self, key: &Q) -> Self
+    where
+        K: Borrow<Q>,
+        V: Clone,
+        Q: Ord + ?Sized,
+    {
+        self.cut(Cut::Key(key))
+    }
+
+    /// Remove every entry in `range`, and return an iterator which owns
+    /// them.
+    ///
+    /// We cut the range out along the paths to either end of it, and join
+    /// what's left either side back together with a graft, so nodes inside
+    /// the range are moved out whole rather than emptied one entry at a
+    /// time. Counting the entries which move costs a visit to each of
+    /// their leaves, but nothing in them is touched until the iterator
+    /// gets to it.
+    ///
+    /// A range whose start is above its end holds nothing, and drains
+    /// nothing.
+    ///
+    /// ```
+    /// # use palmtree::StdPalmTree;
+    /// let mut tree: StdPalmTree<u32, u32> = (0..10_000).map(|i| (i, i)).collect();
+    /// let expired: Vec<_> = tree.drain_range(..9000).collect();
+    /// assert_eq!(9000, expired.len());
+    /// assert_eq!(Some((&9000, &9000)), tree.first_key_value());
+    /// ```
+    pub fn drain_range<Q, R>(&mut self, range: R) -> OwnedIter<K, V, C>
+    where
+        K: Borrow<Q>,
+        V: Clone,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let (drained, rest) = self.cut_range(range);
+        *self = Self::concat(core::mem::take(self), rest);
+        drained.into_iter()
+    }
+
+    /// Turn the tree into an iterator over just the entries in `range`.
+    ///
+    /// As with `drain_range`, we cut the range out along the paths to
+    /// either end of it, so the nodes either side of it are dropped whole
+    /// and the iterator never sees them.
+    ///
+    /// ```
+    /// # use palmtree::StdPalmTree;
+    /// let tree: StdPalmTree<u32, u32> = (0..10_000).map(|i| (i, i)).collect();
+    /// let mut range = tree.into_range(100..200);
+    /// assert_eq!(100, range.len());
+    /// assert_eq!(Some((199, 199)), range.next_back());
+    /// assert_eq!(Some((100, 100)), range.next());
+    /// ```
+    pub fn into_range<Q, R>(mut self, range: R) -> OwnedIter<K, V, C>
+    where
+        K: Borrow<Q>,
+        V: Clone,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        self.cut_range(range).0.into_iter()
+    }
+
+    /// Cut out the entries in `range`, returning them along with the
+    /// entries above the range, and keeping the ones below it.
+    fn cut_range<Q, R>(&mut self, range: R) -> (Self, Self)
+    where
+        K: Borrow<Q>,
+        V: Clone,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let mut inside = match range.start_bound() {
+            Bound::Included(key) => self.cut(Cut::Key(key)),
+            Bound::Excluded(key) => self.cut(Cut::After(key)),
+            Bound::Unbounded => core::mem::take(self),
+        };
+        let above = match range.end_bound() {
+            Bound::Included(key) => inside.cut(Cut::After(key)),
+            Bound::Excluded(key) => inside.cut(Cut::Key(key)),
+            Bound::Unbounded => Self::new(),
+        };
+        (inside, above)
+    }
+
+    /// Cut the tree in two, keeping everything in front of the cut and
+    /// returning the rest.
+    fn cut<Q>(&mut self, mut cut: Cut<'_, Q>) -> Self
+    where
+        K: Borrow<Q>,
+        V: Clone,
+        Q: Ord + ?Sized,
+    {
+        let root = match self.root {
+            Some(ref mut root) => Pointer::make_mut(root),
+            None => return Self::new(),
+        };
+        let moved = match cut {
+            Cut::Rank(rank) => Some(self.size.saturating_sub(rank)),
+            Cut::Key(_) | Cut::After(_) => None,
+        };
+        let right_root = root.cut(&mut cut);
+        let moved = moved.unwrap_or_else(|| right_root.count());
+        let mut right = Self {
+            size: moved,
+            removed: 0,
+            packed: None,
+            root: Some(Pointer::new(right_root)),
+        };
+        self.size -= moved;
+        for tree in [&mut *self, &mut right] {
+            if tree.size == 0 {
+                tree.root = None;
+            } else {
+                tree.trim_root();
+            }
+            tree.validate();
+        }
+        right
+    }
+
+    /// Get the entry with the lowest key.
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        if self.is_empty() {
+            return None;
+        }
+        let path = PathedPointer::<&(K, V), _, _, _>::lowest(self.root.as_ref()?);
+        Some(unsafe { path.into_entry() })
+    }
+
+    /// Get the entry with the highest key.
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        if self.is_empty() {
+            return None;
+        }
+        let path = PathedPointer::<&(K, V), _, _, _>::highest(self.root.as_ref()?);
+        Some(unsafe { path.into_entry() })
+    }
+
+    /// Get the entry with the lowest key, with the value mutable.
+    pub fn first_key_value_mut(&mut self) -> Option<(&K, &mut V)>
+    where
+        V: Clone,
+    {
+        if self.is_empty() {
+            return None;
+        }
+        self.make_path_unique(|_| 0);
+        let path = PathedPointer::<&mut (K, V), _, _, _>::lowest(self.root.as_mut()?);
+        Some(unsafe { path.into_entry_mut() })
+    }
+
+    /// Get the entry with the highest key, with the value mutable.
+    pub fn last_key_value_mut(&mut self) -> Option<(&K, &mut V)>
+    where
+        V: Clone,
+    {
+        if self.is_empty() {
+            return None;
+        }
+        self.make_path_unique(|keys| keys.len() - 1);
+        let path = PathedPointer::<&mut (K, V), _, _, _>::highest(self.root.as_mut()?);
+        Some(unsafe { path.into_entry_mut() })
+    }
+
+    /// Get an entry handle for the lowest key, to look at or remove it
+    /// in place.
+    pub fn first_entry(&mut self) -> Option<OccupiedEntry<'_, K, V, C>>
+    where
+        V: Clone,
+    {
+        if self.is_empty() {
+            return None;
+        }
+        self.make_path_unique(|_| 0);
+        let path = PathedPointer::lowest(self.root.as_mut()?);
+        Some(OccupiedEntry::new(self, path))
+    }
+
+    /// Get an entry handle for the highest key, to look at or remove it
+    /// in place.
+    pub fn last_entry(&mut self) -> Option<OccupiedEntry<'_, K, V, C>>
+    where
+        V: Clone,
+    {
+        if self.is_empty() {
+            return None;
+        }
+        self.make_path_unique(|keys| keys.len() - 1);
+        let path = PathedPointer::highest(self.root.as_mut()?);
+        Some(OccupiedEntry::new(self, path))
+    }
+
+    /// Remove the entry with the lowest key. The same as `remove_lowest`,
+    /// named as `BTreeMap` names it.
+    pub fn pop_first(&mut self) -> Option<(K, V)>
+    where
+        V: Clone,
+    {
+        self.remove_lowest()
+    }
+
+    /// Remove the entry with the highest key. The same as `remove_highest`,
+    /// named as `BTreeMap` names it.
+    pub fn pop_last(&mut self) -> Option<(K, V)>
+    where
+        V: Clone,
+    {
+        self.remove_highest()
+    }
+
+    pub fn remove_lowest(&mut self) -> Option<(K, V)>
+    where
+        V: Clone,
+    {
+        if self.is_empty() {
+            None
+        } else {
+            self.make_path_unique(|_| 0);
+            let path = PathedPointer::<&mut (K, V), _, _, _>::lowest(self.root.as_mut()?);
+            self.size -= 1;
+            let result = unsafe { path.remove() };
+            self.rebalance_path(|_| 0);
+            self.entries_removed(1);
+            Some(result)
+        }
+    }
+
+    pub fn remove_highest(&mut self) -> Option<(K, V)>
+    where
+        V: Clone,
+    {
+        if self.is_empty() {
+            None
+        } else {
+            self.make_path_unique(|keys| keys.len() - 1);
+            let path = PathedPointer::<&mut (K, V), _, _, _>::highest(self.root.as_mut()?);
+            self.size -= 1;
+            let result = unsafe { path.remove() };
+            self.rebalance_path(|keys| keys.len() - 1);
+            self.entries_removed(1);
+            Some(result)
+        }
+    }
+
+    /// Copy any node on the path `choose` picks down the tree which is
+    /// shared with another tree, so the path can be changed in place.
+    ///
+    /// Cursors only hold plain pointers to the nodes, so anything changing
+    /// the tree through one has to call this, or `make_range_unique`, first.
+    fn make_path_unique<F>(&mut self, mut choose: F)
+    where
+        V: Clone,
+        F: FnMut(&[K]) -> usize,
+    {
+        if let Some(ref mut root) = self.root {
+            Pointer::make_mut(root).make_path_unique(&mut choose);
+        }
+    }
+
+    /// Even out any node left less than half full on the path `choose`
+    /// picks down the tree, after a removal along that path.
+    ///
+    /// This can move entries between nodes, so no cursor into the tree
+    /// survives it.
+    fn rebalance_path<F>(&mut self, mut choose: F)
+    where
+        V: Clone,
+        F: FnMut(&[K]) -> usize,
+    {
+        if let Some(ref mut root) = self.root {
+            Pointer::make_mut(root).rebalance_path(&mut choose);
+            self.trim_root();
+        }
+    }
+
+    /// Copy any node which could hold a key in `range` and is shared with
+    /// another tree.
+    fn make_range_unique<Q, R>(&mut self, range: &R)
+    where
+        K: Borrow<Q>,
+        V: Clone,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        if let Some(ref mut root) = self.root {
+            Pointer::make_mut(root).make_range_unique(range);
+        }
+    }
+
+    /// Rebuild the tree with every node packed full.
+    ///
+    /// Removing entries keeps nodes at least half full, but no fuller, and
+    /// cutting a tree with `split_off` or `pop_first_n` can leave the nodes
+    /// along the cut with only a few entries. This repacks them in a single
+    /// pass, the same way `load` builds a tree. See also
+    /// `TreeConfig::AUTO_COMPACT_PERCENT`.
+    pub fn compact(&mut self)
+    where
+        V: Clone,
+    {
+        // Take the tree out first, so that we're left empty rather than
+        // inconsistent if a `clone` panics on the way.
+        let tree = core::mem::take(self);
+        *self = Self::load(tree);
+    }
+
+    /// Leak the tree, so it lives for the rest of the program, and get a
+    /// mutable reference to it, like `Vec::leak`.
+    ///
+    /// This is for lookup tables you build once at startup and never drop.
+    /// Nothing under the tree is ever freed, including any nodes it shares
+    /// with other trees, which stay valid for as long as those trees do.
+    pub fn leak<'a>(self) -> &'a mut Self
+    where
+        C: 'a,
+    {
+        Box::leak(Box::new(self))
+    }
+
+    /// Keep count of removals, and once there's been enough of them, if the
+    /// config asks for it, compact the tree a bottom level branch at a time,
+    /// a branch per call until we've been through the whole tree.
+    fn entries_removed(&mut self, count: usize)
+    where
+        V: Clone,
+    {
+        if let Some(percent) = C::AUTO_COMPACT_PERCENT {
+            self.removed += count;
+            if self.packed.is_some() || self.removed * 100 > self.size * percent {
+                if self.packed.is_none() {
+                    self.removed = 0;
+                }
+                let after = self.packed.take();
+                if let Some(ref mut root) = self.root {
+                    self.packed = Pointer::make_mut(root).pack_next(after.as_ref());
+                }
+            }
+        }
+        self.validate();
+    }
+
+    /// Remove every entry for which `f` returns `false`.
+    ///
+    /// This visits the entries in order in a single pass, removing them
+    /// from each leaf in place, and evens out or merges leaves and branches
+    /// left less than half full as it goes, so the tree is as densely
+    /// packed afterwards as it would be after calling `remove` for each.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        V: Clone,
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        if let Some(ref mut root) = self.root {
+            let size = self.size;
+            Pointer::make_mut(root).retain(&mut f, &mut self.size);
+            self.trim_root();
+            let removed = size - self.size;
+            self.entries_removed(removed);
+        }
+    }
+
+    /// Remove every key which is present in `other` from this tree.
+    ///
+    /// Both trees are walked in order and entries are removed a leaf
+    /// at a time, which is much faster than calling `remove` for each
+    /// key in `other`.
+    pub fn difference_in_place<V2, C2>(&mut self, other: &PalmTree<K, V2, C2>)
+    where
+        V: Clone,
+        C2: TreeConfig<K, V2>,
+    {
+        if self.is_empty() || other.is_empty() {
+            return;
+        }
+        self.retain_by_keys(other, false);
+    }
+
+    /// Remove every key which isn't present in `other` from this tree.
+    ///
+    /// Like `difference_in_place`, this walks both trees in order and
+    /// removes entries a leaf at a time.
+    pub fn intersect_in_place<V2, C2>(&mut self, other: &PalmTree<K, V2, C2>)
+    where
+        V: Clone,
+        C2: TreeConfig<K, V2>,
+    {
+        if other.is_empty() {
+            *self = Self::new();
+            return;
+        }
+        self.retain_by_keys(other, true);
+    }
+
+    /// Keep the entries whose keys are in `other` if `found` is set, or
+    /// the ones whose keys aren't if it isn't, stepping along `other` as
+    /// we go.
+    fn retain_by_keys<V2, C2>(&mut self, other: &PalmTree<K, V2, C2>, found: bool)
+    where
+        V: Clone,
+        C2: TreeConfig<K, V2>,
+    {
+        let mut other_keys = Seek::new(other.keys());
+        let mut compare = |key: &K, other_key: &&K| key.cmp(other_key);
+        self.retain(|key, _| loop {
+            match other_keys.step(Some(key), &mut compare) {
+                Some(Merged::Right(_)) => continue,
+                Some(Merged::Both(..)) => return found,
+                _ => return !found,
+            }
+        });
+    }
+
+    fn merge_left_from(
+        left: impl Iterator<Item = (K, V)>,
+        right: impl Iterator<Item = (K, V)>,
+    ) -> impl Iterator<Item = (K, V)> {
+        MergeIter::merge(
+            left,
+            right,
+            |(left, _), (right, _)| left > right,
+            |(left, _), (right, _)| left == right,
+        )
+    }
+
+    fn merge_right_from(
+        left: impl Iterator<Item = (K, V)>,
+        right: impl Iterator<Item = (K, V)>,
+    ) -> impl Iterator<Item = (K, V)> {
+        MergeIter::merge(
+            left,
+            right,
+            |(left, _), (right, _)| left >= right,
+            |(left, _), (right, _)| left == right,
+        )
+    }
+
+    /// Merge in order, calling `f` with the key and both values wherever a
+    /// key turns up on both sides. The key from `left` is the one we keep.
+    fn merge_with_from<F>(
+        left: impl Iterator<Item = (K, V)>,
+        right: impl Iterator<Item = (K, V)>,
+        mut f: F,
+    ) -> impl Iterator<Item = (K, V)>
+    where
+        F: FnMut(&K, V, V) -> V,
+    {
+        CoWalk::new(left, right, |(left, _), (right, _)| left.cmp(right)).map(move |merged| {
+            match merged {
+                Merged::Left(entry) | Merged::Right(entry) => entry,
+                Merged::Both((key, left), (_, right)) => {
+                    let value = f(&key, left, right);
+                    (key, value)
+                }
+            }
+        })
+    }
+
+    /// Keep the entries from `left` whose keys don't appear in `right`.
+    fn difference_from<B>(
+        left: impl Iterator<Item = (K, V)>,
+        right: impl Iterator<Item = B>,
+    ) -> impl Iterator<Item = (K, V)>
+    where
+        B: Borrow<K>,
+    {
+        CoWalk::new(left, right, |(key, _), other: &B| key.cmp(other.borrow())).filter_map(
+            |merged| match merged {
+                Merged::Left(entry) => Some(entry),
+                _ => None,
+            },
+        )
+    }
+
+    /// Keep the entries from `left` whose keys also appear in `right`.
+    fn intersection_from<B>(
+        left: impl Iterator<Item = (K, V)>,
+        right: impl Iterator<Item = B>,
+    ) -> impl Iterator<Item = (K, V)>
+    where
+        B: Borrow<K>,
+    {
+        CoWalk::new(left, right, |(key, _), other: &B| key.cmp(other.borrow())).filter_map(
+            |merged| match merged {
+                Merged::Both(entry, _) => Some(entry),
+                _ => None,
+            },
+        )
+    }
+
+    /// Keep the entries whose keys appear in only one of `left` and
+    /// `right`.
+    fn symmetric_difference_from(
+        left: impl Iterator<Item = (K, V)>,
+        right: impl Iterator<Item = (K, V)>,
+    ) -> impl Iterator<Item = (K, V)> {
+        CoWalk::new(left, right, |(left, _), (right, _)| left.cmp(right)).filter_map(|merged| {
+            match merged {
+                Merged::Left(entry) | Merged::Right(entry) => Some(entry),
+                Merged::Both(..) => None,
+            }
+        })
+    }
+
+    /// Keep the keys found in both `left` and `right`, calling `f` with the
+    /// key and both values to pick the value.
+    fn intersection_with_from<F>(
+        left: impl Iterator<Item = (K, V)>,
+        right: impl Iterator<Item = (K, V)>,
+        mut f: F,
+    ) -> impl Iterator<Item = (K, V)>
+    where
+        F: FnMut(&K, V, V) -> V,
+    {
+        CoWalk::new(left, right, |(left, _), (right, _)| left.cmp(right)).filter_map(
+            move |merged| match merged {
+                Merged::Both((key, left), (_, right)) => {
+                    let value = f(&key, left, right);
+                    Some((key, value))
+                }
+                _ => None,
+            },
+        )
+    }
+
+    pub fn merge_left_iter(left: Self, right: Self) -> impl Iterator<Item = (K, V)>
+    where
+        V: Clone,
+    {
+        Self::merge_left_from(left.into_iter(), right.into_iter())
+    }
+
+    pub fn merge_left(left: Self, right: Self) -> Self
+    where
+        V: Clone,
+    {
+        Self::load(Self::merge_left_iter(left, right))
+    }
+
+    pub fn merge_right_iter(left: Self, right: Self) -> impl Iterator<Item = (K, V)>
+    where
+        V: Clone,
+    {
+        Self::merge_right_from(left.into_iter(), right.into_iter())
+    }
+
+    pub fn merge_right(left: Self, right: Self) -> Self
+    where
+        V: Clone,
+    {
+        Self::load(Self::merge_right_iter(left, right))
+    }
+
+    /// Merge two trees, calling `f` with the key, the value from `left` and
+    /// the value from `right` to pick the value for a key found in both.
+    ///
+    /// ```
+    /// # use palmtree::StdPalmTree;
+    /// let left: StdPalmTree<&str, u32> = vec![("a", 1), ("b", 2)].into_iter().collect();
+    /// let right: StdPalmTree<&str, u32> = vec![("b", 3), ("c", 4)].into_iter().collect();
+    /// let sums = StdPalmTree::merge_with(left, right, |_, left, right| left + right);
+    /// assert_eq!(vec![(&"a", &1), (&"b", &5), (&"c", &4)], sums.iter().collect::<Vec<_>>());
+    /// ```
+    pub fn merge_with<F>(left: Self, right: Self, f: F) -> Self
+    where
+        V: Clone,
+        F: FnMut(&K, V, V) -> V,
+    {
+        Self::load(Self::merge_with_from(
+            left.into_iter(),
+            right.into_iter(),
+            f,
+        ))
+    }
+
+    pub fn intersection_with_iter<F>(left: Self, right: Self, f: F) -> impl Iterator<Item = (K, V)>
+    where
+        V: Clone,
+        F: FnMut(&K, V, V) -> V,
+    {
+        Self::intersection_with_from(left.into_iter(), right.into_iter(), f)
+    }
+
+    /// The keys found in both `left` and `right`, with the value for each
+    /// picked by calling `f` with the key, the value from `left` and the
+    /// value from `right`.
+    ///
+    /// ```
+    /// # use palmtree::StdPalmTree;
+    /// let left: StdPalmTree<&str, u32> = vec![("a", 1), ("b", 2)].into_iter().collect();
+    /// let right: StdPalmTree<&str, u32> = vec![("b", 3), ("c", 4)].into_iter().collect();
+    /// let both = StdPalmTree::intersection_with(left, right, |_, left, right| left * right);
+    /// assert_eq!(vec![(&"b", &6)], both.iter().collect::<Vec<_>>());
+    /// ```
+    pub fn intersection_with<F>(left: Self, right: Self, f: F) -> Self
+    where
+        V: Clone,
+        F: FnMut(&K, V, V) -> V,
+    {
+        Self::load(Self::intersection_with_iter(left, right, f))
+    }
+
+    pub fn difference_iter(left: Self, right: Self) -> impl Iterator<Item = (K, V)>
+    where
+        V: Clone,
+    {
+        Self::difference_from(left.into_iter(), right.into_iter().map(|(key, _)| key))
+    }
+
+    /// The entries from `left` whose keys don't appear in `right`, the same
+    /// as `left - right`.
+    pub fn difference(left: Self, right: Self) -> Self
+    where
+        V: Clone,
+    {
+        Self::load(Self::difference_iter(left, right))
+    }
+
+    pub fn symmetric_difference_iter(left: Self, right: Self) -> impl Iterator<Item = (K, V)>
+    where
+        V: Clone,
+    {
+        Self::symmetric_difference_from(left.into_iter(), right.into_iter())
+    }
+
+    /// The entries whose keys appear in only one of `left` and `right`.
+    pub fn symmetric_difference(left: Self, right: Self) -> Self
+    where
+        V: Clone,
+    {
+        Self::load(Self::symmetric_difference_iter(left, right))
+    }
+
+    /// Join two trees where every key in `left` is below every key in
+    /// `right`.
+    ///
+    /// Rather than rebuilding either of them, we graft the root of the
+    /// shorter tree onto the edge of the taller one, so this only touches
+    /// the nodes down that edge.
+    fn concat(mut left: Self, mut right: Self) -> Self
+    where
+        V: Clone,
+    {
+        if left.is_empty() {
+            return right;
+        }
+        if right.is_empty() {
+            return left;
+        }
+        let size = left.size + right.size;
+        let removed = left.removed + right.removed;
+        let mut left_root = left.root.take().unwrap();
+        let mut right_root = right.root.take().unwrap();
+        // A stale separator down the left tree's last edge could be above
+        // keys in the right tree, which would send searches the wrong way.
+        Pointer::make_mut(&mut left_root).lower_highest();
+        let left_height = left_root.height();
+        let right_height = right_root.height();
+        let root = match left_height.cmp(&right_height) {
+            Ordering::Equal => Branch::join(left_root, right_root),
+            Ordering::Greater => {
+                match Pointer::make_mut(&mut left_root).graft(
+                    left_height,
+                    right_root,
+                    right_height,
+                    false,
+                ) {
+                    Some(sibling) => Branch::join(left_root, sibling),
+                    None => left_root,
+                }
+            }
+            Ordering::Less => {
+                match Pointer::make_mut(&mut right_root).graft(
+                    right_height,
+                    left_root,
+                    left_height,
+                    true,
+                ) {
+                    Some(sibling) => Branch::join(sibling, right_root),
+                    None => right_root,
+                }
+            }
+        };
+        let tree = Self {
+            size,
+            removed,
+            packed: None,
+            root: Some(root),
+        };
+        tree.validate();
+        tree
+    }
+
+    /// Whether every key in `left` is below every key in `right`, so
+    /// `concat` can join them.
+    fn is_below(left: &Self, right: &Self) -> bool {
+        match (left.last_key_value(), right.first_key_value()) {
+            (Some((highest, _)), Some((lowest, _))) => highest < lowest,
+            _ => true,
+        }
+    }
+
+    /// Move every entry in `other` into this tree. Where a key exists in
+    /// both trees, the value from this tree wins.
+    ///
+    /// If every key in one tree is below every key in the other, we join
+    /// them with a graft in `O(log n)`. Otherwise, both are merged in
+    /// order and the result is rebuilt with `load`.
+    pub fn append_left(&mut self, other: Self)
+    where
+        V: Clone,
+    {
+        if Self::is_below(self, &other) {
+            *self = Self::concat(core::mem::take(self), other);
+            return;
+        }
+        if Self::is_below(&other, self) {
+            *self = Self::concat(other, core::mem::take(self));
+            return;
+        }
+        let root = self.root.take();
+        if root.is_some() {
+            let left = OwnedIter::new(root, self.size);
+            let right = other.into_iter();
+            *self = Self::load(Self::merge_left_from(left, right));
+        } else {
+            *self = other;
+        }
+    }
+
+    /// Move every entry in `other` into this tree. Where a key exists in
+    /// both trees, the value from `other` wins. See `append_left`.
+    pub fn append_right(&mut self, other: Self)
+    where
+        V: Clone,
+    {
+        if Self::is_below(self, &other) {
+            *self = Self::concat(core::mem::take(self), other);
+            return;
+        }
+        if Self::is_below(&other, self) {
+            *self = Self::concat(other, core::mem::take(self));
+            return;
+        }
+        let root = self.root.take();
+        if root.is_some() {
+            let left = OwnedIter::new(root, self.size);
+            let right = other.into_iter();
+            *self = Self::load(Self::merge_right_from(left, right));
+        } else {
+            *self = other;
+        }
+    }
+
+    /// Move every entry in `other` into this tree. Where a key exists in
+    /// both trees, we call `f` with the key, the value from this tree and
+    /// the value from `other`, and keep what it returns. See `append_left`.
+    pub fn append_with<F>(&mut self, other: Self, f: F)
+    where
+        V: Clone,
+        F: FnMut(&K, V, V) -> V,
+    {
+        if Self::is_below(self, &other) {
+            *self = Self::concat(core::mem::take(self), other);
+            return;
+        }
+        if Self::is_below(&other, self) {
+            *self = Self::concat(other, core::mem::take(self));
+            return;
+        }
+        let root = self.root.take();
+        if root.is_some() {
+            let left = OwnedIter::new(root, self.size);
+            let right = other.into_iter();
+            *self = Self::load(Self::merge_with_from(left, right, f));
+        } else {
+            *self = other;
+        }
+    }
+
+    /// Move every entry in `other` into this tree, leaving `other` empty,
+    /// as with `BTreeMap::append`. Where a key exists in both trees, the
+    /// value from `other` wins. See `append_right`.
+    ///
+    /// ```
+    /// # use palmtree::StdPalmTree;
+    /// let mut tree: StdPalmTree<u32, char> = vec![(1, 'a'), (2, 'b')].into_iter().collect();
+    /// let mut other: StdPalmTree<u32, char> = vec![(2, 'c'), (3, 'd')].into_iter().collect();
+    /// tree.append(&mut other);
+    /// assert!(other.is_empty());
+    /// assert_eq!(vec![(&1, &'a'), (&2, &'c'), (&3, &'d')], tree.iter().collect::<Vec<_>>());
+    /// ```
+    pub fn append(&mut self, other: &mut Self)
+    where
+        V: Clone,
+    {
+        self.append_right(core::mem::take(other))
+    }
+
+    /// Merge clones of every entry in `other` into this tree.
+    ///
+    /// Where a key exists in both trees, the value from `other` wins,
+    /// as with `append_right`. Rather than inserting entries one at a
+    /// time, both trees are merged in order and the result is rebuilt
+    /// with `load`.
+    pub fn absorb<C2>(&mut self, other: &PalmTree<K, V, C2>)
+    where
+        V: Clone,
+        C2: TreeConfig<K, V>,
+    {
+        if other.is_empty() {
+            return;
+        }
+        let right = other.iter().map(|(k, v)| (k.clone(), v.clone()));
+        let root = self.root.take();
+        if root.is_none() {
+            *self = Self::load(right);
+        } else {
+            *self = Self::load(Self::merge_right_from(
+                OwnedIter::new(root, self.size),
+                right,
+            ));
+        }
+    }
+
+    /// Work out the changes which would turn this tree into `other`.
+    ///
+    /// Both trees are walked in order, and every entry which differs ends
+    /// up in the patch, so `apply_patch` can replay them onto a copy of
+    /// this tree elsewhere.
+    pub fn diff<C2>(&self, other: &PalmTree<K, V, C2>) -> Patch<K, V>
+    where
+        V: Clone + PartialEq,
+        C2: TreeConfig<K, V>,
+    {
+        let mut upserts = Vec::new();
+        let mut removals = Vec::new();
+        let mut left = self.iter().peekable();
+        let mut right = other.iter().peekable();
+        loop {
+            match (left.peek(), right.peek()) {
+                (None, None) => break,
+                (Some(_), None) => {
+                    removals.extend(left.map(|(key, _)| key.clone()));
+                    break;
+                }
+                (None, Some(_)) => {
+                    upserts.extend(right.map(|(key, value)| (key.clone(), value.clone())));
+                    break;
+                }
+                (Some((left_key, left_value)), Some((right_key, right_value))) => {
+                    match left_key.cmp(right_key) {
+                        Ordering::Less => {
+                            removals.push((*left_key).clone());
+                            left.next();
+                        }
+                        Ordering::Greater => {
+                            upserts.push(((*right_key).clone(), (*right_value).clone()));
+                            right.next();
+                        }
+                        Ordering::Equal => {
+                            if left_value != right_value {
+                                upserts.push(((*right_key).clone(), (*right_value).clone()));
+                            }
+                            left.next();
+                            right.next();
+                        }
+                    }
+                }
+            }
+        }
+        Patch::new(upserts, removals)
+    }
+
+    /// Apply a patch made by `diff`.
+    ///
+    /// The whole patch goes in with a single merge and `load`, the same way
+    /// as `append_right`, rather than an insert or remove per change.
+    pub fn apply_patch(&mut self, patch: Patch<K, V>)
+    where
+        V: Clone,
+    {
+        if patch.is_empty() {
+            return;
+        }
+        let (upserts, removals) = patch.into_parts();
+        let kept = Self::difference_from(core::mem::take(self).into_iter(), removals.iter());
+        *self = Self::load(Self::merge_right_from(kept, upserts.into_iter()));
+    }
+
+    /// Apply a batch of inserts, updates and removals, in order.
+    ///
+    /// Rather than walking down from the root for each op, we go down once
+    /// and apply every op which lands in the same leaf while we're there,
+    /// moving on to the next leaf along when we run out, and only copying
+    /// or rebuilding the nodes the ops land in. A batch in key order walks
+    /// the tree just once; any other order works, but costs more walking.
+    ///
+    /// ```
+    /// # use palmtree::{Op, StdPalmTree};
+    /// let mut tree: StdPalmTree<u32, &str> = StdPalmTree::load(vec![(1, "a"), (2, "b"), (3, "c")]);
+    /// tree.apply_batch(vec![(1, Op::Remove), (2, Op::Update("B")), (4, Op::Update("D")), (5, Op::Insert("E"))]);
+    /// assert_eq!(vec![(&2, &"B"), (&3, &"c"), (&5, &"E")], tree.iter().collect::<Vec<_>>());
+    /// ```
+    pub fn apply_batch<I>(&mut self, batch: I)
+    where
+        V: Clone,
+        I: IntoIterator<Item = (K, Op<V>)>,
+    {
+        let mut batch = batch.into_iter().peekable();
+        let mut removed = 0;
+        loop {
+            if self.is_empty() {
+                // There's nothing for anything but an insert to apply to.
+                match batch.next() {
+                    Some((key, Op::Insert(value))) => {
+                        self.insert(key, value);
+                    }
+                    Some(_) => {}
+                    None => break,
+                }
+                continue;
+            }
+            let root = self.root.as_mut().unwrap();
+            if Pointer::make_mut(root).apply_batch(
+                &mut batch,
+                None,
+                None,
+                &mut self.size,
+                &mut removed,
+            ) {
+                if batch.peek().is_none() {
+                    break;
+                }
+                // The root only stops early once the batch has emptied it,
+                // and the rest of the batch goes in as it would have to an
+                // empty tree.
+                self.root = None;
+                continue;
+            }
+            let key = &batch.peek().unwrap().0;
+            Self::split_root(root, key);
+        }
+        self.trim_root();
+        self.entries_removed(removed);
+    }
+
+    /// Check the tree's structural invariants, describing the first broken
+    /// one.
+    ///
+    /// Keys must be in order within every node, and under the separator their
+    /// parent holds for them. A branch's last separator must be the one its
+    /// parent holds for it. Every node below the root must be non-empty, the
+    /// leaves must all be at the same depth, and the tree's length must be
+    /// the number of entries in them. This visits every node, so it's for
+    /// tests and debugging rather than for keeping in production code.
+    ///
+    /// ```
+    /// # use palmtree::StdPalmTree;
+    /// let mut tree: StdPalmTree<u32, u32> = (0..10_000).map(|i| (i, i)).collect();
+    /// tree.retain(|key, _| key % 3 == 0);
+    /// assert_eq!(Ok(()), tree.check_invariants());
+    /// ```
+    pub fn check_invariants(&self) -> Result<(), InvariantError> {
+        let entries = match self.root {
+            Some(ref root) => root.check(None, None)?.0,
+            None => 0,
+        };
+        if entries != self.size {
+            return Err(InvariantError::new(Invariant::Length {
+                length: self.size,
+                entries,
+            }));
+        }
+        Ok(())
+    }
+
+    /// With the `validate` feature enabled, check the tree's invariants and
+    /// panic with a dump of its shape if any are broken. Otherwise, do nothing.
+    ///
+    /// This walks the whole tree, so under `validate` every change is O(n).
+    #[inline(always)]
+    fn validate(&self) {
+        #[cfg(feature = "validate")]
+        {
+            if let Err(error) = self.check_invariants() {
+                let mut shape = alloc::string::String::new();
+                if let Some(ref root) = self.root {
+                    root.shape(&mut shape, 0);
+                }
+                panic!("PalmTree: broken invariant: {}\n{}", error, shape);
+            }
+        }
+    }
+
+    fn trim_root(&mut self)
+    where
+        V: Clone,
+    {
+        if let Some(ref mut root) = self.root {
+            // If a branch bearing root only has one child, we can replace the root with that child.
+            while root.has_branches() && root.len() == 1 {
+                *root = Pointer::make_mut(root).remove_last_branch().1;
+            }
+        }
+    }
+
+    /// Split a full root to make room for `key`.
+    fn split_root(root: &mut Pointer<Branch<K, V, C>, C::PointerKind>, key: &K)
+    where
+        V: Clone,
+    {
+        // Clone the new separator keys before taking the old root apart,
+        // so that a panicking `clone` leaves the tree intact.
+        let old_root_ref = Pointer::make_mut(root);
+        let append = key > old_root_ref.highest_present();
+        let right_len = split_len::<K, V, C>(old_root_ref.len(), append);
+        let left_key = old_root_ref.split_key(right_len).clone();
+        let right_key = old_root_ref.highest().clone();
+        let old_root = core::mem::replace(root, Branch::new(true).into());
+        let (left, right) = Branch::split(old_root, right_len);
+        Pointer::make_mut(root).push_branch_pair(left_key, left, right_key, right);
+    }
+
+    pub fn insert_recursive(&mut self, key: K, value: V) -> Option<V>
+    where
+        V: Clone,
+    {
+        let result = self.insert_recursive_unvalidated(key, value);
+        self.validate();
+        result
+    }
+
+    fn insert_recursive_unvalidated(&mut self, key: K, value: V) -> Option<V>
+    where
+        V: Clone,
+    {
+        let len = self.size;
+        if let Some(ref mut root) = self.root {
             let root_ref = Pointer::make_mut(root);
             // Special case: if a tree has size 0 but there is a root, it's because
             // we removed the last entry and the root has been left allocated.
@@ -424,7 +2011,7 @@ where
                         // If the root is full, we need to increase the height of the tree and retry insertion,
                         // so we can split the old root.
                         let key2 = root_ref.highest().clone();
-                        let child = std::mem::replace(root_ref, Branch::new(true));
+                        let child = core::mem::replace(root_ref, Branch::new(true));
                         root_ref.push_branch(key2, Pointer::new(child));
                         self.insert(key, value)
                     }
@@ -477,6 +2064,8 @@ where
         Self {
             root: self.root.clone(),
             size: self.size,
+            removed: self.removed,
+            packed: self.packed.clone(),
         }
     }
 }
@@ -499,286 +2088,1848 @@ where
     }
 }
 
-impl<'a, K, V, C> Index<&'a K> for PalmTree<K, V, C>
+impl<K, V, C, Q> Index<&Q> for PalmTree<K, V, C>
 where
-    K: Ord + Clone,
+    K: Ord + Clone + Borrow<Q>,
+    Q: Ord + ?Sized,
     C: TreeConfig<K, V>,
 {
     type Output = V;
 
-    fn index(&self, index: &K) -> &Self::Output {
+    fn index(&self, index: &Q) -> &Self::Output {
         self.get(index).expect("no entry found for key")
     }
 }
 
-impl<'a, K, V, C> IndexMut<&'a K> for PalmTree<K, V, C>
+impl<K, V, C, Q> IndexMut<&Q> for PalmTree<K, V, C>
 where
-    K: Ord + Clone,
+    K: Ord + Clone + Borrow<Q>,
     V: Clone,
+    Q: Ord + ?Sized,
     C: TreeConfig<K, V>,
 {
-    fn index_mut(&mut self, index: &K) -> &mut Self::Output {
+    fn index_mut(&mut self, index: &Q) -> &mut Self::Output {
         self.get_mut(index).expect("no entry found for key")
     }
 }
 
-impl<K, V, C> PartialEq for PalmTree<K, V, C>
+impl<K, V, C> PalmTree<K, V, C>
+where
+    K: Ord + Clone,
+    V: Eq,
+    C: TreeConfig<K, V>,
+{
+    /// Compare with `other` as `==` does, except that trees which share
+    /// nodes, such as a tree and a clone of it with a few changes, only
+    /// need the nodes they don't share compared, as long as they're the
+    /// same shape.
+    ///
+    /// `==` can't do this, because a shared node is only equal to itself
+    /// if its values are: a tree of `f64::NAN`s isn't equal to a clone of
+    /// itself. Hence the `V: Eq` bound.
+    pub fn shared_eq(&self, other: &Self) -> bool {
+        self.eq_with(other, true)
+    }
+}
+
+impl<K, V, C> PalmTree<K, V, C>
+where
+    K: Ord + Clone,
+    V: PartialEq,
+    C: TreeConfig<K, V>,
+{
+    /// Trees of the same shape are compared a leaf at a time, and anything
+    /// else entry by entry.
+    fn eq_with(&self, other: &Self, skip_shared: bool) -> bool {
+        if self.len() != other.len() {
+            return false;
+        }
+        if let (Some(left), Some(right)) = (&self.root, &other.root) {
+            if let Some(equal) = left.shared_eq(right, skip_shared) {
+                return equal;
+            }
+        }
+        self.iter().eq(other.iter())
+    }
+}
+
+impl<K, V, C> PartialEq for PalmTree<K, V, C>
+where
+    K: Ord + Clone,
+    V: PartialEq,
+    C: TreeConfig<K, V>,
+{
+    /// See `shared_eq` for a faster comparison of trees which share nodes.
+    fn eq(&self, other: &Self) -> bool {
+        self.eq_with(other, false)
+    }
+}
+
+impl<K, V, C> Eq for PalmTree<K, V, C>
+where
+    K: Ord + Clone,
+    V: Eq,
+    C: TreeConfig<K, V>,
+{
+}
+
+impl<K, V, C> PartialOrd for PalmTree<K, V, C>
+where
+    K: Ord + Clone,
+    V: PartialOrd,
+    C: TreeConfig<K, V>,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<K, V, C> Ord for PalmTree<K, V, C>
+where
+    K: Ord + Clone,
+    V: Ord,
+    C: TreeConfig<K, V>,
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+impl<K, V, C> Extend<(K, V)> for PalmTree<K, V, C>
+where
+    K: Ord + Clone,
+    V: Clone,
+    C: TreeConfig<K, V>,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+impl<'a, K, V, C> Extend<(&'a K, &'a V)> for PalmTree<K, V, C>
+where
+    K: 'a + Ord + Copy,
+    V: 'a + Copy,
+    C: TreeConfig<K, V>,
+{
+    fn extend<I: IntoIterator<Item = (&'a K, &'a V)>>(&mut self, iter: I) {
+        for (k, v) in iter {
+            self.insert(*k, *v);
+        }
+    }
+}
+
+impl<'a, K, V, C, C2> Extend<&'a PalmTree<K, V, C2>> for PalmTree<K, V, C>
+where
+    K: 'a + Ord + Clone,
+    V: 'a + Clone,
+    C: TreeConfig<K, V>,
+    C2: 'a + TreeConfig<K, V>,
+{
+    fn extend<I: IntoIterator<Item = &'a PalmTree<K, V, C2>>>(&mut self, iter: I) {
+        for tree in iter {
+            self.absorb(tree);
+        }
+    }
+}
+
+impl<K, V, C> Add for PalmTree<K, V, C>
+where
+    K: Ord + Clone,
+    V: Clone,
+    C: TreeConfig<K, V>,
+{
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self::Output {
+        Self::merge_right(self, other)
+    }
+}
+
+impl<K, V, C> AddAssign for PalmTree<K, V, C>
+where
+    K: Ord + Clone,
+    V: Clone,
+    C: TreeConfig<K, V>,
+{
+    fn add_assign(&mut self, other: Self) {
+        self.append_right(other)
+    }
+}
+
+impl<K, V, C, C2> Add<&PalmTree<K, V, C2>> for PalmTree<K, V, C>
+where
+    K: Ord + Clone,
+    V: Clone,
+    C: TreeConfig<K, V>,
+    C2: TreeConfig<K, V>,
+{
+    type Output = Self;
+
+    fn add(mut self, other: &PalmTree<K, V, C2>) -> Self::Output {
+        self.absorb(other);
+        self
+    }
+}
+
+impl<'a, K, V, C, C2> AddAssign<&'a PalmTree<K, V, C2>> for PalmTree<K, V, C>
+where
+    K: Ord + Clone,
+    V: Clone,
+    C: TreeConfig<K, V>,
+    C2: TreeConfig<K, V>,
+{
+    fn add_assign(&mut self, other: &'a PalmTree<K, V, C2>) {
+        self.absorb(other)
+    }
+}
+
+impl<K, V, C> BitOr for PalmTree<K, V, C>
+where
+    K: Ord + Clone,
+    V: Clone,
+    C: TreeConfig<K, V>,
+{
+    type Output = Self;
+
+    /// The union of both trees, keeping the values from `self` where keys collide.
+    fn bitor(self, other: Self) -> Self::Output {
+        Self::merge_left(self, other)
+    }
+}
+
+impl<K, V, C> BitOrAssign for PalmTree<K, V, C>
+where
+    K: Ord + Clone,
+    V: Clone,
+    C: TreeConfig<K, V>,
+{
+    fn bitor_assign(&mut self, other: Self) {
+        self.append_left(other)
+    }
+}
+
+impl<K, V, C> BitAnd for PalmTree<K, V, C>
+where
+    K: Ord + Clone,
+    V: Clone,
+    C: TreeConfig<K, V>,
+{
+    type Output = Self;
+
+    /// The entries from `self` whose keys also appear in `other`.
+    fn bitand(self, other: Self) -> Self::Output {
+        let keys = other.iter().map(|(key, _)| key);
+        Self::load(Self::intersection_from(self.into_iter(), keys))
+    }
+}
+
+impl<K, V, C> BitAndAssign for PalmTree<K, V, C>
+where
+    K: Ord + Clone,
+    V: Clone,
+    C: TreeConfig<K, V>,
+{
+    fn bitand_assign(&mut self, other: Self) {
+        self.intersect_in_place(&other)
+    }
+}
+
+impl<K, V, C> Sub for PalmTree<K, V, C>
+where
+    K: Ord + Clone,
+    V: Clone,
+    C: TreeConfig<K, V>,
+{
+    type Output = Self;
+
+    /// The entries from `self` whose keys don't appear in `other`.
+    fn sub(self, other: Self) -> Self::Output {
+        let keys = other.iter().map(|(key, _)| key);
+        Self::load(Self::difference_from(self.into_iter(), keys))
+    }
+}
+
+impl<K, V, C> SubAssign for PalmTree<K, V, C>
+where
+    K: Ord + Clone,
+    V: Clone,
+    C: TreeConfig<K, V>,
+{
+    fn sub_assign(&mut self, other: Self) {
+        self.difference_in_place(&other)
+    }
+}
+
+impl<K, V, C> Hash for PalmTree<K, V, C>
 where
-    K: Ord + Clone,
-    V: PartialEq,
+    K: Ord + Clone + Hash,
+    V: Hash,
     C: TreeConfig<K, V>,
 {
-    fn eq(&self, other: &Self) -> bool {
-        self.len() == other.len() && self.iter().eq(other.iter())
+    fn hash<H>(&self, state: &mut H)
+    where
+        H: Hasher,
+    {
+        for entry in self {
+            entry.hash(state);
+        }
     }
 }
 
-impl<K, V, C> Eq for PalmTree<K, V, C>
+impl<'a, K, V, C> IntoIterator for &'a PalmTree<K, V, C>
 where
     K: Ord + Clone,
-    V: Eq,
     C: TreeConfig<K, V>,
 {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V, C>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
 }
 
-impl<K, V, C> PartialOrd for PalmTree<K, V, C>
+impl<'a, K, V, C> IntoIterator for &'a mut PalmTree<K, V, C>
 where
     K: Ord + Clone,
-    V: PartialOrd,
+    V: Clone,
     C: TreeConfig<K, V>,
 {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.iter().partial_cmp(other.iter())
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V, C>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
     }
 }
 
-impl<K, V, C> Ord for PalmTree<K, V, C>
+impl<K, V, C> IntoIterator for PalmTree<K, V, C>
 where
     K: Ord + Clone,
-    V: Ord,
+    V: Clone,
     C: TreeConfig<K, V>,
 {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.iter().cmp(other.iter())
+    type Item = (K, V);
+    type IntoIter = OwnedIter<K, V, C>;
+    fn into_iter(self) -> Self::IntoIter {
+        OwnedIter::new(self.root, self.size)
     }
 }
 
-impl<K, V, C> Extend<(K, V)> for PalmTree<K, V, C>
+impl<K, V, C> From<BTreeMap<K, V>> for PalmTree<K, V, C>
 where
     K: Ord + Clone,
     V: Clone,
     C: TreeConfig<K, V>,
 {
-    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
-        for (k, v) in iter {
-            self.insert(k, v);
+    fn from(map: BTreeMap<K, V>) -> Self {
+        Self::load(map)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tests::scaled;
+
+    #[test]
+    fn lookup_empty() {
+        let tree: StdPalmTree<usize, usize> = PalmTree::new();
+        assert_eq!(None, tree.get(&1337));
+    }
+
+    #[test]
+    fn lookup_single() {
+        let mut tree: StdPalmTree<usize, usize> = PalmTree::new();
+        tree.insert(1337, 31337);
+        assert_eq!(None, tree.get(&1336));
+        assert_eq!(Some(&31337), tree.get(&1337));
+        assert_eq!(None, tree.get(&1338));
+    }
+
+    #[test]
+    fn insert_in_sequence() {
+        let mut tree: StdPalmTree<usize, usize> = PalmTree::new();
+        let iters = scaled(131_072);
+        for i in 0..iters {
+            tree.insert(i, i);
+        }
+        for i in 0..iters {
+            assert_eq!(Some(&i), tree.get(&i));
+        }
+    }
+
+    #[test]
+    fn load_from_ordered_stream() {
+        let size = 131_072;
+        let tree: StdPalmTree<usize, usize> = PalmTree::load((0..size).map(|i| (i, i)));
+        for i in 0..size {
+            assert_eq!(Some(&i), tree.get(&i));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "PalmTree::load: unordered key at index 3")]
+    fn load_out_of_order() {
+        let _: StdPalmTree<usize, ()> = PalmTree::load(vec![(1, ()), (2, ()), (3, ()), (3, ())]);
+    }
+
+    #[test]
+    fn load_from_unsorted() {
+        let keys: Vec<usize> = (0..10_000).map(|i| (i * 7919) % 5000).collect();
+        let tree: StdPalmTree<usize, usize> =
+            PalmTree::from_unsorted(keys.iter().enumerate().map(|(i, key)| (*key, i)));
+        tree.check_invariants().unwrap();
+        let mut model = std::collections::BTreeMap::new();
+        model.extend(keys.iter().enumerate().map(|(i, key)| (*key, i)));
+        assert!(model.iter().eq(tree.iter()));
+    }
+
+    #[test]
+    fn delete_delete_delete() {
+        let mut tree: StdPalmTree<usize, usize> =
+            PalmTree::load((0..scaled(131_072)).map(|i| (i, i)));
+        for i in scaled(31337)..scaled(41337) {
+            assert_eq!(Some((i, i)), tree.remove(&i));
+            assert_eq!(None, tree.remove(&i));
+        }
+    }
+
+    #[test]
+    fn small_delete() {
+        let mut tree: StdPalmTree<usize, usize> = PalmTree::load((0..64).map(|i| (i, i)));
+        assert_eq!(Some((0, 0)), tree.remove(&0));
+        assert_eq!(None, tree.remove(&0));
+    }
+
+    #[test]
+    fn insert_into_emptied_tree() {
+        let mut tree: StdPalmTree<u8, u8> = PalmTree::new();
+        tree.insert(0, 0);
+        tree.remove(&0);
+        tree.insert(0, 0);
+        tree.insert(10, 10);
+
+        let result: Vec<(u8, u8)> = tree.iter().map(|(k, v)| (*k, *v)).collect();
+        let expected: Vec<(u8, u8)> = vec![(0, 0), (10, 10)];
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn difference_in_place() {
+        let mut tree: StdPalmTree<usize, usize> = PalmTree::load((0..10_000).map(|i| (i, i)));
+        let mut nat: BTreeMap<usize, usize> = (0..10_000).map(|i| (i, i)).collect();
+        let other: StdPalmTree<usize, ()> = PalmTree::load(
+            (0..2000)
+                .map(|i| i * 3)
+                .chain(20_000..20_100)
+                .map(|i| (i, ())),
+        );
+        tree.difference_in_place(&other);
+        for (key, _) in &other {
+            nat.remove(key);
+        }
+        assert_eq!(nat.len(), tree.len());
+        assert!(nat.iter().eq(tree.iter()));
+    }
+
+    #[test]
+    fn difference_in_place_removing_everything() {
+        let mut tree: StdPalmTree<usize, usize> = PalmTree::load((0..1000).map(|i| (i, i)));
+        let other = tree.clone();
+        tree.difference_in_place(&other);
+        assert_eq!(0, tree.len());
+        assert_eq!(None, tree.iter().next());
+        tree.insert(5, 5);
+        tree.insert(1, 1);
+        assert_eq!(vec![(&1, &1), (&5, &5)], tree.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn difference_in_place_leaves_clones_alone() {
+        let mut tree: ImPalmTree<usize, usize> = PalmTree::load((0..1000).map(|i| (i, i)));
+        let snapshot = tree.clone();
+        let other: ImPalmTree<usize, usize> = PalmTree::load((100..900).map(|i| (i, i)));
+        tree.difference_in_place(&other);
+        assert_eq!(200, tree.len());
+        assert_eq!(1000, snapshot.len());
+        assert!(snapshot.iter().map(|(k, _)| *k).eq(0..1000));
+    }
+
+    #[test]
+    fn persistent_updates_copy_one_path() {
+        thread_local! {
+            static CLONES: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+        }
+
+        #[derive(Debug, PartialEq)]
+        struct Counted(usize);
+
+        impl Clone for Counted {
+            fn clone(&self) -> Self {
+                CLONES.with(|clones| clones.set(clones.get() + 1));
+                Counted(self.0)
+            }
+        }
+
+        let tree: ImPalmTree<usize, Counted> =
+            PalmTree::load((0..100_000).map(|i| (i, Counted(i))));
+        CLONES.with(|clones| clones.set(0));
+        // A clone only takes another reference to the root.
+        let snapshot = tree.clone();
+        assert_eq!(0, CLONES.with(|clones| clones.get()));
+        assert_eq!(tree, snapshot);
+        let updated = tree.update(50_000, Counted(0)).without(&70_000);
+        // Only the two leaves on the paths get copied.
+        assert!(CLONES.with(|clones| clones.get()) <= 128);
+        updated.check_invariants().unwrap();
+        assert_eq!(Some(&Counted(50_000)), tree.get(&50_000));
+        assert_eq!(Some(&Counted(0)), updated.get(&50_000));
+        assert!(tree.contains_key(&70_000));
+        assert!(!updated.contains_key(&70_000));
+        assert_eq!(99_999, updated.len());
+    }
+
+    #[test]
+    fn shared_trees_compare_unshared_nodes() {
+        thread_local! {
+            static COMPARISONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+        }
+
+        #[derive(Clone, Debug)]
+        struct Counted(usize);
+
+        impl PartialEq for Counted {
+            fn eq(&self, other: &Self) -> bool {
+                COMPARISONS.with(|count| count.set(count.get() + 1));
+                self.0 == other.0
+            }
+        }
+
+        impl Eq for Counted {}
+
+        let comparisons = || COMPARISONS.with(|count| count.replace(0));
+        let size = scaled(100_000);
+        let tree: ImPalmTree<usize, Counted> = PalmTree::load((0..size).map(|i| (i, Counted(i))));
+        assert!(tree.shared_eq(&tree.clone()));
+        assert_eq!(0, comparisons());
+        assert_eq!(tree, tree.clone());
+        assert_eq!(size, comparisons());
+
+        let mut changed = tree.clone();
+        changed.insert(size / 2, Counted(0));
+        assert!(!tree.shared_eq(&changed));
+        changed.insert(size / 2, Counted(size / 2));
+        comparisons();
+        assert!(tree.shared_eq(&changed));
+        // Only the leaf we changed gets compared.
+        assert!(comparisons() <= 128);
+
+        // A clone of a tree whose values aren't equal to themselves isn't
+        // equal to it.
+        let nans: ImPalmTree<usize, f64> = PalmTree::load((0..1000).map(|i| (i, f64::NAN)));
+        assert_ne!(nans, nans.clone());
+
+        // Trees of different shapes still compare entry by entry.
+        let mut reshaped = tree.clone();
+        reshaped.remove(&(size / 2));
+        reshaped.insert(size / 2, Counted(size / 2));
+        let rebuilt: ImPalmTree<usize, Counted> =
+            tree.iter().map(|(k, v)| (*k, v.clone())).collect();
+        assert_eq!(tree, reshaped);
+        assert_eq!(tree, rebuilt);
+        assert_eq!(reshaped, rebuilt);
+        assert!(tree.shared_eq(&reshaped));
+        // Each entry is compared once, not once for the leaves and again
+        // entry by entry when the shapes turn out not to match.
+        comparisons();
+        assert_eq!(tree, rebuilt);
+        assert_eq!(size, comparisons());
+        let unique: StdPalmTree<usize, usize> = PalmTree::load((0..1000).map(|i| (i, i)));
+        assert_eq!(unique, unique.clone());
+        assert_ne!(unique, unique.clone().update(500, 0));
+    }
+
+    #[test]
+    fn intersect_in_place() {
+        let mut tree: StdPalmTree<usize, usize> = PalmTree::load((0..10_000).map(|i| (i, i)));
+        let other: StdPalmTree<usize, ()> = PalmTree::load(
+            (0..2000)
+                .map(|i| i * 3)
+                .chain(20_000..20_100)
+                .map(|i| (i, ())),
+        );
+        tree.intersect_in_place(&other);
+        assert_eq!(2000, tree.len());
+        assert!(tree
+            .iter()
+            .map(|(k, v)| (*k, *v))
+            .eq((0..2000).map(|i| (i * 3, i * 3))));
+
+        let mut shared: ImPalmTree<usize, usize> = PalmTree::load((0..1000).map(|i| (i, i)));
+        let snapshot = shared.clone();
+        shared.intersect_in_place(&StdPalmTree::<usize, ()>::new());
+        assert!(shared.is_empty());
+        assert_eq!(1000, snapshot.len());
+    }
+
+    #[test]
+    fn absorb_clones_and_merges() {
+        let mut tree: StdPalmTree<String, usize> =
+            PalmTree::load((0..1000).map(|i| (format!("{:04}", i * 2), i)));
+        let other: ImPalmTree<String, usize> =
+            PalmTree::load((0..1000).map(|i| (format!("{:04}", i * 3), i + 10_000)));
+        let mut nat: BTreeMap<String, usize> = tree.clone().into_iter().collect();
+        nat.extend(other.clone());
+        tree.absorb(&other);
+        assert_eq!(nat.len(), tree.len());
+        assert!(nat.iter().eq(tree.iter()));
+
+        let mut extended: StdPalmTree<String, usize> = PalmTree::new();
+        extended.extend(vec![&other, &other]);
+        assert!(other.iter().eq(extended.iter()));
+    }
+
+    #[test]
+    fn fill_range() {
+        let mut tree: StdPalmTree<usize, usize> = PalmTree::load((0..10_000).map(|i| (i, i)));
+        assert_eq!(1000, tree.fill_range(1000..2000, (0..).map(|i| i * 10)));
+        assert_eq!(3, tree.fill_range(9997.., 0..100));
+        assert_eq!(2, tree.copy_values_from_slice(..=4, &[42, 43]));
+        for (key, value) in &tree {
+            let expected = match *key {
+                0 => 42,
+                1 => 43,
+                1000..=1999 => (key - 1000) * 10,
+                9997..=9999 => key - 9997,
+                _ => *key,
+            };
+            assert_eq!(expected, *value);
+        }
+    }
+
+    fn count_leaves<K, V, C>(branch: &Branch<K, V, C>) -> usize
+    where
+        C: TreeConfig<K, V>,
+    {
+        if branch.has_leaves() {
+            branch.len()
+        } else {
+            (0..branch.len())
+                .map(|index| count_leaves(branch.get_branch(index)))
+                .sum()
+        }
+    }
+
+    #[test]
+    fn compact() {
+        let mut tree: StdPalmTree<usize, usize> = PalmTree::load((0..64_000).map(|i| (i, i)));
+        tree.retain(|key, _| key % 4 == 0);
+        assert!(count_leaves(tree.root.as_ref().unwrap()) > 300);
+        tree.compact();
+        assert_eq!(250, count_leaves(tree.root.as_ref().unwrap()));
+        assert!(tree.iter().map(|(k, _)| *k).eq((0..16_000).map(|i| i * 4)));
+    }
+
+    #[test]
+    fn retain_packs_as_it_goes() {
+        let mut tree: ImPalmTree<usize, usize> = PalmTree::load((0..256_000).map(|i| (i, i)));
+        let copy = tree.clone();
+        let mut seen = 0;
+        tree.retain(|key, value| {
+            assert_eq!(seen, *key);
+            seen += 1;
+            *value += 1;
+            key % 16 == 0 || (100_000..101_000).contains(key)
+        });
+        assert_eq!(256_000, seen);
+        tree.check_invariants().unwrap();
+        assert_eq!(16_937, tree.len());
+        assert!(tree
+            .iter()
+            .all(|(k, v)| *v == k + 1 && (k % 16 == 0 || (100_000..101_000).contains(k))));
+        // Every leaf but the last under each branch is at least half full.
+        assert!(count_leaves(tree.root.as_ref().unwrap()) <= tree.len() / 32 + 64);
+        assert!(copy.iter().all(|(k, v)| k == v));
+
+        tree.retain(|key, _| key % 2 == 1);
+        tree.check_invariants().unwrap();
+        assert_eq!(500, tree.len());
+        tree.retain(|_, _| false);
+        tree.check_invariants().unwrap();
+        assert!(tree.is_empty());
+        for i in 0..1000 {
+            tree.insert(i, i);
+        }
+        tree.check_invariants().unwrap();
+    }
+
+    #[test]
+    fn remove_rebalances() {
+        fn sparse_nodes<K, V, C>(branch: &Branch<K, V, C>) -> usize
+        where
+            C: TreeConfig<K, V>,
+        {
+            (0..branch.len())
+                .map(|index| {
+                    if branch.has_branches() {
+                        let child = branch.get_branch(index);
+                        sparse_nodes(child) + (child.len() < 32) as usize
+                    } else {
+                        (branch.get_leaf(index).len() < 32) as usize
+                    }
+                })
+                .sum()
+        }
+
+        let size = scaled(256_000);
+        let kept = size / 8;
+        let mut tree: StdPalmTree<usize, usize> = PalmTree::load((0..size).map(|i| (i, i)));
+        for i in 0..size {
+            if i % 8 != 0 {
+                assert_eq!(Some((i, i)), tree.remove(&i));
+            }
+        }
+        tree.check_invariants().unwrap();
+        assert_eq!(kept, tree.len());
+        assert!(count_leaves(tree.root.as_ref().unwrap()) <= size / 256);
+        assert!(sparse_nodes(tree.root.as_ref().unwrap()) <= 1);
+        assert!(tree.iter().map(|(k, _)| *k).eq((0..kept).map(|i| i * 8)));
+
+        // Removing from either end and through entries rebalances too, and
+        // the tree gets shorter as it empties.
+        let rounds = scaled(10_000);
+        let middle = rounds + (kept - 3 * rounds) / 2;
+        for i in 0..rounds {
+            assert_eq!(Some((i * 8, i * 8)), tree.remove_lowest());
+            let key = (kept - 1 - i) * 8;
+            assert_eq!(Some((key, key)), tree.remove_highest());
+            let key = (middle + i) * 8;
+            match tree.entry(key) {
+                Entry::Occupied(entry) => assert_eq!(key, entry.remove()),
+                Entry::Vacant(_) => panic!("{} is missing", key),
+            }
+            if i % 1000 == 0 {
+                tree.check_invariants().unwrap();
+            }
+        }
+        tree.check_invariants().unwrap();
+        assert_eq!(kept - 3 * rounds, tree.len());
+        assert!(sparse_nodes(tree.root.as_ref().unwrap()) <= 1);
+        assert!(tree.root.as_ref().unwrap().has_leaves());
+    }
+
+    #[test]
+    fn auto_compact() {
+        struct Compacting;
+        impl<K, V> TreeConfig<K, V> for Compacting {
+            type BranchSize = typenum::U64;
+            type LeafSize = typenum::U64;
+            type PointerKind = Unique;
+            const AUTO_COMPACT_PERCENT: Option<usize> = Some(50);
+        }
+
+        // Compaction goes a bottom level branch at a time, so no removal
+        // drops more than a branch's worth of leaves.
+        let size = scaled(64_000);
+        let mut tree: PalmTree<usize, usize, Compacting> =
+            PalmTree::load((0..size).map(|i| (i, i)));
+        let mut plain: StdPalmTree<usize, usize> = PalmTree::load((0..size).map(|i| (i, i)));
+        let mut leaves = count_leaves(tree.root.as_ref().unwrap());
+        let mut most = 0;
+        for i in 0..size {
+            let key = i * 7919 % size;
+            if !key.is_multiple_of(4) {
+                tree.remove(&key);
+                plain.remove(&key);
+                let now = count_leaves(tree.root.as_ref().unwrap());
+                most = most.max(leaves - now);
+                leaves = now;
+            }
+        }
+        assert!(most <= 64, "{} leaves dropped at once", most);
+        assert!(leaves < count_leaves(plain.root.as_ref().unwrap()) * 9 / 10);
+        assert_eq!(size / 4, tree.len());
+        assert!(tree
+            .iter()
+            .map(|(k, _)| *k)
+            .eq((0..size / 4).map(|i| i * 4)));
+    }
+
+    #[test]
+    fn pop_first_and_last_n() {
+        let mut tree: ImPalmTree<usize, usize> = ImPalmTree::load((0..10_000).map(|i| (i, i)));
+        for i in (0..10_000).step_by(7) {
+            tree.remove(&i);
+        }
+        let copy = tree.clone();
+        let mut model: Vec<usize> = copy.iter().map(|(k, _)| *k).collect();
+
+        for n in &[0, 1, 63, 64, 65, 1000, 2047] {
+            let first = tree.pop_first_n(*n);
+            let expected: Vec<usize> = model.drain(..*n).collect();
+            first.check_invariants().unwrap();
+            tree.check_invariants().unwrap();
+            assert!(first.iter().map(|(k, _)| *k).eq(expected));
+
+            let last = tree.pop_last_n(*n);
+            let expected: Vec<usize> = model.drain(model.len() - n..).collect();
+            last.check_invariants().unwrap();
+            tree.check_invariants().unwrap();
+            assert!(last.iter().map(|(k, _)| *k).eq(expected));
+            assert!(tree.iter().map(|(k, _)| *k).eq(model.iter().copied()));
+        }
+
+        // What's left still takes inserts on both sides of the cuts.
+        for i in 0..10_000 {
+            tree.insert(i, i);
+        }
+        tree.check_invariants().unwrap();
+        assert_eq!(10_000, tree.len());
+        assert_eq!(model.len() + 5, tree.pop_first_n(model.len() + 5).len());
+        assert_eq!(10_000 - model.len() - 5, tree.pop_last_n(20_000).len());
+        assert!(tree.is_empty());
+        assert!(copy.iter().all(|(k, v)| k == v && k % 7 != 0));
+    }
+
+    #[test]
+    fn append_disjoint() {
+        let sizes = [0, 1, 100, scaled(5000), scaled(300_000)];
+        for &left_len in &sizes {
+            for &right_len in &sizes {
+                let mut left: ImPalmTree<usize, usize> =
+                    ImPalmTree::load((0..left_len + 1).map(|i| (i, i)));
+                // Leave a stale separator down the left tree's last edge.
+                left.remove(&left_len);
+                let right: ImPalmTree<usize, usize> =
+                    ImPalmTree::load((0..right_len).map(|i| (left_len + i, left_len + i)));
+                let copies = (left.clone(), right.clone());
+
+                let mut joined = left.clone();
+                joined.append_right(right.clone());
+                joined.check_invariants().unwrap();
+                assert_eq!(left_len + right_len, joined.len());
+                assert!(joined.iter().map(|(k, _)| *k).eq(0..left_len + right_len));
+
+                // The other way round, lower keys come in from `other`.
+                let mut joined = right;
+                joined.append_left(left);
+                joined.check_invariants().unwrap();
+                assert!(joined.iter().map(|(k, _)| *k).eq(0..left_len + right_len));
+                for i in 0..1000 {
+                    joined.insert(i * 7, 0);
+                    joined.remove(&(i * 13));
+                }
+                joined.check_invariants().unwrap();
+                assert!(copies.0.iter().map(|(k, _)| *k).eq(0..left_len));
+                assert_eq!(right_len, copies.1.len());
+            }
+        }
+
+        // Overlapping trees still merge, with the right value winning.
+        let mut left: StdPalmTree<usize, usize> = (0..100).map(|i| (i * 2, 0)).collect();
+        let right: StdPalmTree<usize, usize> = (0..100).map(|i| (i * 3, 1)).collect();
+        left.append_right(right);
+        left.check_invariants().unwrap();
+        assert_eq!(Some(&1), left.get(&6));
+        assert_eq!(Some(&0), left.get(&4));
+
+        // `append` drains its argument like `BTreeMap::append` does.
+        let mut model: BTreeMap<usize, usize> = left.iter().map(|(k, v)| (*k, *v)).collect();
+        let mut other: StdPalmTree<usize, usize> = (150..250).map(|i| (i, 2)).collect();
+        let mut model_other: BTreeMap<usize, usize> = other.iter().map(|(k, v)| (*k, *v)).collect();
+        left.append(&mut other);
+        model.append(&mut model_other);
+        left.check_invariants().unwrap();
+        assert!(other.is_empty());
+        assert!(model.iter().eq(left.iter()));
+        let mut above: StdPalmTree<usize, usize> = (1000..2000).map(|i| (i, 3)).collect();
+        left.append(&mut above);
+        assert!(above.is_empty());
+        assert_eq!(model.len() + 1000, left.len());
+    }
+
+    #[test]
+    fn merge_and_append_with() {
+        let left: StdPalmTree<usize, usize> = (0..1000).map(|i| (i * 2, 1)).collect();
+        let right: StdPalmTree<usize, usize> = (0..1000).map(|i| (i * 3, 10)).collect();
+        let mut model: BTreeMap<usize, usize> = left.iter().map(|(k, v)| (*k, *v)).collect();
+        for (key, value) in &right {
+            *model.entry(*key).or_default() += value;
+        }
+
+        let merged = StdPalmTree::merge_with(left.clone(), right.clone(), |_, l, r| l + r);
+        merged.check_invariants().unwrap();
+        assert!(model.iter().eq(merged.iter()));
+
+        let mut seen = Vec::new();
+        let mut appended = left.clone();
+        appended.append_with(right, |key, l, r| {
+            seen.push(*key);
+            l + r
+        });
+        appended.check_invariants().unwrap();
+        assert!(model.iter().eq(appended.iter()));
+        assert!(seen.iter().copied().eq((0..2000).step_by(6)));
+
+        // Disjoint trees are joined without calling the closure.
+        let mut low: StdPalmTree<usize, usize> = (0..100).map(|i| (i, i)).collect();
+        low.append_with(
+            (100..200).map(|i| (i, i)).collect(),
+            |_, _, _| unreachable!(),
+        );
+        low.check_invariants().unwrap();
+        assert!(low
+            .iter()
+            .map(|(k, v)| (*k, *v))
+            .eq((0..200).map(|i| (i, i))));
+    }
+
+    #[test]
+    fn split_off() {
+        let mut tree: ImPalmTree<usize, usize> = ImPalmTree::load((0..10_000).map(|i| (i, i)));
+        for i in (0..10_000).step_by(7) {
+            tree.remove(&i);
+        }
+        let copy = tree.clone();
+        let mut model: BTreeMap<usize, usize> = copy.iter().map(|(k, v)| (*k, *v)).collect();
+
+        // Split at keys which are there, keys which aren't, and either end.
+        for key in &[9999, 9998, 9100, 7000, 4096, 4095, 63, 1, 0] {
+            let right = tree.split_off(key);
+            let expected = model.split_off(key);
+            right.check_invariants().unwrap();
+            tree.check_invariants().unwrap();
+            assert_eq!(expected.len(), right.len());
+            assert_eq!(model.len(), tree.len());
+            assert!(right.iter().eq(expected.iter()));
+            assert!(tree.iter().eq(model.iter()));
+        }
+        assert!(tree.is_empty());
+
+        let mut tree = copy.clone();
+        assert!(tree.split_off(&20_000).is_empty());
+        assert_eq!(copy.len(), tree.len());
+        let mut right = tree.split_off(&5000);
+        for i in 0..10_000 {
+            if i < 5000 {
+                tree.insert(i, i);
+            } else {
+                right.insert(i, i);
+            }
+        }
+        tree.check_invariants().unwrap();
+        right.check_invariants().unwrap();
+        assert_eq!(5000, tree.len());
+        assert_eq!(5000, right.len());
+        assert!(copy.iter().all(|(k, v)| k == v && k % 7 != 0));
+    }
+
+    #[test]
+    fn drain_range() {
+        let tree: ImPalmTree<usize, usize> = ImPalmTree::load((0..20_000).map(|i| (i, i)));
+        let mut tree = tree.without(&5000);
+        let copy = tree.clone();
+        let mut model: BTreeMap<usize, usize> = tree.iter().map(|(k, v)| (*k, *v)).collect();
+
+        let ranges = [
+            (Bound::Included(1000), Bound::Excluded(1001)),
+            (Bound::Excluded(4000), Bound::Included(6000)),
+            (Bound::Included(15_000), Bound::Unbounded),
+            (Bound::Excluded(7000), Bound::Excluded(7000)),
+            (Bound::Included(9000), Bound::Included(8000)),
+            (Bound::Unbounded, Bound::Excluded(64)),
+            (Bound::Included(12_000), Bound::Included(30_000)),
+        ];
+        for range in ranges {
+            // BTreeMap::range panics on the empty ranges, so filter instead.
+            let expected: Vec<(usize, usize)> = model
+                .iter()
+                .filter(|(k, _)| range.contains(*k))
+                .map(|(k, v)| (*k, *v))
+                .collect();
+            let drained: Vec<(usize, usize)> = tree.drain_range(range).collect();
+            for (key, _) in &expected {
+                model.remove(key);
+            }
+            tree.check_invariants().unwrap();
+            assert_eq!(expected, drained);
+            assert_eq!(model.len(), tree.len());
+            assert!(tree.iter().eq(model.iter()));
+        }
+        assert_eq!(model.len(), tree.drain_range::<usize, _>(..).count());
+        assert!(tree.is_empty());
+        assert_eq!(19_999, copy.len());
+        assert!(copy.iter().all(|(k, v)| k == v && *k != 5000));
+    }
+
+    #[test]
+    fn into_range() {
+        let tree: ImPalmTree<usize, usize> = ImPalmTree::load((0..20_000).map(|i| (i, i)));
+        let model: BTreeMap<usize, usize> = tree.iter().map(|(k, v)| (*k, *v)).collect();
+        let ranges = [
+            (Bound::Included(1000), Bound::Excluded(9000)),
+            (Bound::Excluded(63), Bound::Included(64)),
+            (Bound::Unbounded, Bound::Included(4095)),
+            (Bound::Included(19_990), Bound::Unbounded),
+            (Bound::Included(5000), Bound::Excluded(5000)),
+            (Bound::Unbounded, Bound::Unbounded),
+        ];
+        for range in ranges {
+            let expected: Vec<(usize, usize)> = model
+                .iter()
+                .filter(|(k, _)| range.contains(*k))
+                .map(|(k, v)| (*k, *v))
+                .collect();
+            let mut iter = tree.clone().into_range(range);
+            assert_eq!(expected.len(), iter.len());
+            // Take from both ends, meeting somewhere past the middle.
+            let mut back = Vec::new();
+            for _ in 0..expected.len() / 3 {
+                back.push(iter.next_back().unwrap());
+            }
+            let mut actual: Vec<(usize, usize)> = iter.by_ref().collect();
+            assert_eq!(None, iter.next_back());
+            actual.extend(back.into_iter().rev());
+            assert_eq!(expected, actual);
+        }
+        assert_eq!(20_000, tree.len());
+        tree.check_invariants().unwrap();
+    }
+
+    #[test]
+    fn ranges_by_borrow() {
+        use Bound::{Excluded, Included, Unbounded};
+        let mut tree: StdPalmTree<String, usize> =
+            (0..1000).map(|i| (format!("{:04}", i), i)).collect();
+        assert!(tree
+            .range::<str, _>((Included("0100"), Excluded("0110")))
+            .map(|(_, v)| *v)
+            .eq(100..110));
+        assert!(tree
+            .range_keys::<str, _>((Unbounded, Excluded("0002")))
+            .eq(vec!["0000", "0001"]));
+        assert_eq!(
+            9,
+            tree.range_pairs::<str, _>((Included("0990"), Unbounded))
+                .count()
+        );
+        assert_eq!(
+            3,
+            tree.fill_range::<str, _, _>((Included("0500"), Included("0502")), vec![0; 10])
+        );
+        assert_eq!(Some(&0), tree.get("0501"));
+        assert_eq!(
+            2,
+            tree.copy_values_from_slice::<str, _>((Included("0998"), Unbounded), &[7, 7, 7])
+        );
+        assert_eq!(
+            vec![&7, &7],
+            tree.range_values::<str, _>((Included("0998"), Unbounded))
+                .collect::<Vec<_>>()
+        );
+        tree.shift_keys::<str, _, _>((Included("0990"), Unbounded), |key| format!("{}x", key));
+        tree.check_invariants().unwrap();
+        assert_eq!(Some(&990), tree.get("0990x"));
+        assert_eq!(None, tree.get("0999"));
+
+        let frozen = tree.freeze();
+        assert!(frozen
+            .range::<str, _>((Included("0100"), Excluded("0103")))
+            .map(|(_, v)| *v)
+            .eq(100..103));
+    }
+
+    #[test]
+    fn index_by_borrow_and_many_mut() {
+        let mut tree: ImPalmTree<String, usize> =
+            (0..10_000).map(|i| (format!("{:05}", i), i)).collect();
+        let snapshot = tree.clone();
+        assert_eq!(1234, tree["01234"]);
+        tree["01234"] = 0;
+
+        let [first, last, middle] = tree.get_many_mut(["00000", "09999", "05000"]).unwrap();
+        *first = 1;
+        *last = 2;
+        *middle = 3;
+        assert_eq!(1, tree["00000"]);
+        assert_eq!(2, tree["09999"]);
+        assert_eq!(3, tree["05000"]);
+        assert_eq!(None, tree.get_many_mut(["00001", "10000"]));
+        assert_eq!(None, tree.get_many_mut(["00001", "00002", "00001"]));
+        assert_eq!(Some([]), tree.get_many_mut::<str, 0>([]));
+        assert_eq!(9999, snapshot["09999"]);
+        assert_eq!(1234, snapshot["01234"]);
+    }
+
+    #[test]
+    fn get_many_mut_in_one_leaf() {
+        let mut tree: PalmTree<usize, usize, Tree64<Shared>> =
+            PalmTree::load((0..1000).map(|i| (i, i)));
+        let snapshot = tree.clone();
+        let [a, b, c] = tree.get_many_mut([&501, &500, &502]).unwrap();
+        core::mem::swap(a, c);
+        *b += 1000;
+        assert_eq!(Some(&502), tree.get(&501));
+        assert_eq!(Some(&1500), tree.get(&500));
+        assert_eq!(Some(&501), tree.get(&502));
+        assert_eq!(Some(&501), snapshot.get(&501));
+        tree.check_invariants().unwrap();
+
+        // Unique nodes are boxes, which mustn't be reborrowed under the
+        // values we've handed out either.
+        let mut tree: StdPalmTree<usize, usize> = PalmTree::load((0..1000).map(|i| (i, i)));
+        let [a, b, c] = tree.get_many_mut([&10, &900, &11]).unwrap();
+        core::mem::swap(a, b);
+        *c = 0;
+        assert_eq!(Some(&900), tree.get(&10));
+        assert_eq!(Some(&10), tree.get(&900));
+        assert_eq!(Some(&0), tree.get(&11));
+        assert_eq!(None, tree.get_many_mut([&10, &1000]));
+    }
+
+    #[test]
+    fn removals_shrink_the_root() {
+        let size = scaled(300_000);
+        let mut tree: StdPalmTree<usize, usize> = PalmTree::new();
+        assert_eq!(0, tree.height());
+        for i in 0..size {
+            tree.insert(i, i);
         }
+        assert_eq!(if size < 262_144 { 2 } else { 3 }, tree.height());
+        for i in 0..size / 3 {
+            tree.remove(&(i * 3));
+        }
+        for _ in 0..size / 6 {
+            tree.remove_lowest();
+            tree.remove_highest();
+        }
+        assert_eq!(size / 3, tree.len());
+        let keys: Vec<usize> = tree.keys().copied().collect();
+        for key in &keys[1000..] {
+            if let Entry::Occupied(entry) = tree.entry(*key) {
+                entry.remove();
+            }
+        }
+        tree.check_invariants().unwrap();
+        assert_eq!(1000, tree.len());
+        assert_eq!(1, tree.height());
+        while tree.pop_first().is_some() {}
+        assert_eq!(0, tree.height());
     }
-}
 
-impl<'a, K, V, C> Extend<(&'a K, &'a V)> for PalmTree<K, V, C>
-where
-    K: 'a + Ord + Copy,
-    V: 'a + Copy,
-    C: TreeConfig<K, V>,
-{
-    fn extend<I: IntoIterator<Item = (&'a K, &'a V)>>(&mut self, iter: I) {
-        for (k, v) in iter {
-            self.insert(*k, *v);
+    #[test]
+    fn share_between_threads() {
+        fn send_sync<T: Send + Sync>() {}
+        send_sync::<StdPalmTree<usize, String>>();
+        send_sync::<SyncPalmTree<usize, String>>();
+        send_sync::<PalmSet<usize, Tree64<SyncShared>>>();
+        // A unique tree only needs its values to be `Send` to be sent.
+        fn send<T: Send>() {}
+        send::<StdPalmTree<usize, core::cell::Cell<usize>>>();
+
+        let size = scaled(100_000);
+        let tree: SyncPalmTree<usize, usize> = PalmTree::load((0..size).map(|i| (i, i)));
+        let workers: Vec<std::thread::JoinHandle<SyncPalmTree<usize, usize>>> = (0..4)
+            .map(|worker| {
+                let mut tree = tree.clone();
+                std::thread::spawn(move || {
+                    assert_eq!(Some(&500), tree.get(&500));
+                    for key in (worker..size).step_by(4) {
+                        tree.remove(&key);
+                    }
+                    tree
+                })
+            })
+            .collect();
+        for (worker, handle) in workers.into_iter().enumerate() {
+            let changed = handle.join().unwrap();
+            changed.check_invariants().unwrap();
+            assert_eq!(size - (worker..size).step_by(4).count(), changed.len());
+            assert!(changed.keys().all(|key| key % 4 != worker));
         }
+        assert_eq!(size, tree.len());
+        assert!(tree.iter().all(|(k, v)| k == v));
     }
-}
-
-impl<K, V, C> Add for PalmTree<K, V, C>
-where
-    K: Ord + Clone,
-    V: Clone,
-    C: TreeConfig<K, V>,
-{
-    type Output = Self;
 
-    fn add(self, other: Self) -> Self::Output {
-        Self::merge_right(self, other)
+    #[test]
+    fn leaked_table() {
+        let table: &'static StdPalmTree<usize, usize> =
+            PalmTree::load((0..1000).map(|i| (i, i * i))).leak();
+        let lookups: Vec<std::thread::JoinHandle<usize>> = (0..4)
+            .map(|i| std::thread::spawn(move || *table.get(&(i * 100)).unwrap()))
+            .collect();
+        let squares: Vec<usize> = lookups.into_iter().map(|t| t.join().unwrap()).collect();
+        assert_eq!(vec![0, 10_000, 40_000, 90_000], squares);
     }
-}
 
-impl<K, V, C> AddAssign for PalmTree<K, V, C>
-where
-    K: Ord + Clone,
-    V: Clone,
-    C: TreeConfig<K, V>,
-{
-    fn add_assign(&mut self, other: Self) {
-        self.append_right(other)
+    #[test]
+    fn single_leaf_slices() {
+        let mut tree: StdPalmTree<usize, usize> = PalmTree::new();
+        assert_eq!(Some((&[][..], &[][..])), tree.as_single_leaf_slices());
+        for i in 0..64 {
+            tree.insert(i, i * 2);
+        }
+        let (keys, values) = tree.as_single_leaf_slices().unwrap();
+        assert_eq!(Ok(10), keys.binary_search(&10));
+        assert!(values.iter().copied().eq((0..64).map(|i| i * 2)));
+        tree.insert(64, 128);
+        assert_eq!(None, tree.as_single_leaf_slices());
+        tree.remove(&64);
+        assert_eq!(None, tree.as_single_leaf_slices());
     }
-}
 
-impl<'a, K, V, C, C2> Add<&'a PalmTree<K, V, C2>> for PalmTree<K, V, C>
-where
-    K: Ord + Copy,
-    V: Copy,
-    C: TreeConfig<K, V>,
-    C2: TreeConfig<K, V>,
-{
-    type Output = Self;
+    #[test]
+    fn first_and_last_mut() {
+        let mut tree: ImPalmTree<usize, usize> = ImPalmTree::new();
+        assert_eq!(None, tree.first_key_value_mut());
+        assert_eq!(None, tree.last_key_value_mut());
+        tree = ImPalmTree::load((0..10_000).map(|i| (i, i)));
+        let copy = tree.clone();
+        *tree.first_key_value_mut().unwrap().1 = 100;
+        let (key, value) = tree.last_key_value_mut().unwrap();
+        assert_eq!(&9999, key);
+        *value = 200;
+        assert_eq!(Some(&100), tree.get(&0));
+        assert_eq!(Some(&200), tree.get(&9999));
+        assert!(copy.iter().all(|(k, v)| k == v));
+
+        // A stale separator above the last leaf doesn't matter.
+        tree.remove(&9999);
+        assert_eq!(Some((&9998, &mut 9998)), tree.last_key_value_mut());
+    }
 
-    fn add(self, other: &PalmTree<K, V, C2>) -> Self::Output {
-        Self::load(Self::merge_right_from(
-            self.into_iter(),
-            other.iter().map(|(k, v)| (*k, *v)),
-        ))
+    #[test]
+    fn first_and_last_entries() {
+        let mut tree: ImPalmTree<usize, usize> = ImPalmTree::new();
+        assert_eq!(None, tree.first_key_value());
+        assert_eq!(None, tree.last_key_value());
+        assert!(tree.first_entry().is_none());
+        assert!(tree.last_entry().is_none());
+        assert_eq!(None, tree.pop_first());
+        assert_eq!(None, tree.pop_last());
+
+        tree = ImPalmTree::load((0..10_000).map(|i| (i, i)));
+        let copy = tree.clone();
+        assert_eq!(Some((&0, &0)), tree.first_key_value());
+        assert_eq!(Some((&9999, &9999)), tree.last_key_value());
+        *tree.first_entry().unwrap().get_mut() = 100;
+        assert_eq!(Some((&0, &100)), tree.first_key_value());
+        assert_eq!(9999, tree.last_entry().unwrap().remove());
+        assert_eq!(Some((&9998, &9998)), tree.last_key_value());
+        assert_eq!(Some((0, 100)), tree.pop_first());
+        assert_eq!(Some((9998, 9998)), tree.pop_last());
+        assert_eq!(9997, tree.len());
+        assert_eq!(10_000, copy.len());
+        assert!(copy.iter().all(|(k, v)| k == v));
+        tree.check_invariants().unwrap();
+
+        // Take the lowest entries only while they pass a test.
+        while let Some(entry) = tree.first_entry() {
+            if *entry.key() >= 5000 {
+                break;
+            }
+            entry.remove();
+        }
+        assert_eq!(Some((&5000, &5000)), tree.first_key_value());
+        tree.check_invariants().unwrap();
     }
-}
 
-impl<'a, K, V, C, C2> AddAssign<&'a PalmTree<K, V, C2>> for PalmTree<K, V, C>
-where
-    K: Ord + Copy,
-    V: Copy,
-    C: TreeConfig<K, V>,
-    C2: TreeConfig<K, V>,
-{
-    fn add_assign(&mut self, other: &'a PalmTree<K, V, C2>) {
-        let root = self.root.take();
-        if root.is_none() {
-            *self = Self::load(other.iter().map(|(k, v)| (*k, *v)));
-        } else {
-            *self = Self::load(Self::merge_right_from(
-                OwnedIter::new(root, self.size),
-                other.iter().map(|(k, v)| (*k, *v)),
-            ))
+    #[test]
+    fn unchecked_lookups() {
+        let mut tree: StdPalmTree<usize, usize> = (0..10_000).map(|i| (i * 2, i)).collect();
+        for i in (0..10_000).step_by(3) {
+            tree.remove(&(i * 2));
+        }
+        let keys: Vec<usize> = tree.iter().map(|(k, _)| *k).collect();
+        for key in &keys {
+            assert_eq!(key / 2, unsafe { *tree.get_unchecked(key) });
+            unsafe { *tree.get_mut_unchecked(key) += 1 };
         }
+        assert!(tree.iter().all(|(k, v)| *v == k / 2 + 1));
     }
-}
 
-impl<K, V, C> Hash for PalmTree<K, V, C>
-where
-    K: Ord + Clone + Hash,
-    V: Hash,
-    C: TreeConfig<K, V>,
-{
-    fn hash<H>(&self, state: &mut H)
-    where
-        H: Hasher,
-    {
-        for entry in self {
-            entry.hash(state);
+    #[test]
+    fn append_biased_splits() {
+        struct Appending;
+        impl<K, V> TreeConfig<K, V> for Appending {
+            type BranchSize = typenum::U64;
+            type LeafSize = typenum::U64;
+            type PointerKind = Unique;
+            const APPEND_SPLIT_PERCENT: usize = 90;
         }
+
+        // Half full leaves make for about `size / 32` of them, and 90% full
+        // ones about `size / 58`.
+        let size = scaled(64_000);
+        let mut even: StdPalmTree<usize, usize> = PalmTree::new();
+        let mut biased: PalmTree<usize, usize, Appending> = PalmTree::new();
+        for i in 0..size {
+            even.insert(i, i);
+            biased.insert(i, i);
+        }
+        let even_leaves = count_leaves(even.root.as_ref().unwrap());
+        let biased_leaves = count_leaves(biased.root.as_ref().unwrap());
+        assert!(even_leaves >= size / 34, "{} leaves", even_leaves);
+        assert!(biased_leaves <= size / 53, "{} leaves", biased_leaves);
+        biased.check_invariants().unwrap();
+        assert!(biased.iter().map(|(k, _)| *k).eq(0..size));
+
+        // Anything which isn't an append still splits down the middle.
+        let mut biased: PalmTree<usize, usize, Appending> = PalmTree::new();
+        for i in (0..size).rev() {
+            biased.insert(i, i);
+        }
+        assert!(count_leaves(biased.root.as_ref().unwrap()) >= size / 34);
+        let more = scaled(20_000);
+        for i in 0..more {
+            biased.insert((i * 7919) % more + 100_000, i);
+        }
+        biased.check_invariants().unwrap();
+        assert_eq!(size + more, biased.len());
     }
-}
 
-impl<'a, K, V, C> IntoIterator for &'a PalmTree<K, V, C>
-where
-    K: Ord + Clone,
-    C: TreeConfig<K, V>,
-{
-    type Item = (&'a K, &'a V);
-    type IntoIter = Iter<'a, K, V, C>;
-    fn into_iter(self) -> Self::IntoIter {
-        self.iter()
+    #[test]
+    fn invariants_hold_through_mutation() {
+        let mut tree: StdPalmTree<usize, usize> = PalmTree::new();
+        for i in 0..20_000 {
+            tree.insert((i * 7919) % 20_000, i);
+            if i % 997 == 0 {
+                tree.check_invariants().unwrap();
+            }
+        }
+        tree.check_invariants().unwrap();
+        for i in 0..15_000 {
+            tree.remove(&((i * 4447) % 20_000));
+            if i % 997 == 0 {
+                tree.check_invariants().unwrap();
+            }
+        }
+        tree.check_invariants().unwrap();
+        tree.retain(|key, _| key % 3 == 0);
+        tree.check_invariants().unwrap();
+        tree.compact();
+        tree.check_invariants().unwrap();
+
+        tree.size += 1;
+        assert!(tree.check_invariants().is_err());
     }
-}
 
-impl<'a, K, V, C> IntoIterator for &'a mut PalmTree<K, V, C>
-where
-    K: Ord + Clone,
-    C: TreeConfig<K, V>,
-{
-    type Item = (&'a K, &'a mut V);
-    type IntoIter = IterMut<'a, K, V, C>;
-    fn into_iter(self) -> Self::IntoIter {
-        self.iter_mut()
+    #[test]
+    fn insert_past_stale_branch_key() {
+        // Emptying the end of the first 4096 entry branch leaves its key in
+        // the root at 4095, above anything left in it.
+        let mut tree: StdPalmTree<usize, usize> = PalmTree::load((0..8192).map(|i| (i, i)));
+        for i in 2000..4096 {
+            tree.remove(&i);
+        }
+        tree.insert(4095, 0);
+        tree.insert(3000, 0);
+        tree.check_invariants().unwrap();
+        let expected: Vec<usize> = (0..2000)
+            .chain(vec![3000, 4095])
+            .chain(4096..8192)
+            .collect();
+        assert!(tree.iter().map(|(k, _)| *k).eq(expected));
+        assert_eq!(Some((&3000, &0)), tree.range(2000..).next());
     }
-}
 
-impl<K, V, C> IntoIterator for PalmTree<K, V, C>
-where
-    K: Ord + Clone,
-    C: TreeConfig<K, V>,
-{
-    type Item = (K, V);
-    type IntoIter = OwnedIter<K, V, C>;
-    fn into_iter(self) -> Self::IntoIter {
-        OwnedIter::new(self.root, self.size)
+    #[test]
+    fn mutation_leaves_clones_alone() {
+        type Tree = PalmTree<usize, usize, Tree64<Shared>>;
+        let original: Tree = PalmTree::load((0..10_000).map(|i| (i * 2, i * 2)));
+        let unchanged = |tree: &Tree| {
+            assert_eq!(10_000, tree.len());
+            assert!(tree
+                .iter()
+                .map(|(k, v)| (*k, *v))
+                .eq((0..10_000).map(|i| (i * 2, i * 2))));
+        };
+
+        let mut tree = original.clone();
+        for i in 0..10_000 {
+            tree.insert(i * 2 + 1, 0);
+            tree.insert(i * 2, 1);
+        }
+        if let Entry::Occupied(mut entry) = tree.entry(7000) {
+            *entry.get_mut() += 1;
+        }
+        tree.insert(1_000_000, 0);
+        unchanged(&original);
+
+        let mut tree = original.clone();
+        for (_, value) in tree.range_mut(5000..6000) {
+            *value = 0;
+        }
+        assert_eq!(Some(&0), tree.get(&5000));
+        assert_eq!(Some(&6000), tree.get(&6000));
+        tree.iter_mut().for_each(|(_, value)| *value += 1);
+        unchanged(&original);
+
+        let mut tree = original.clone();
+        let mut scan = tree.scan_mut();
+        while let Some((key, value)) = scan.next_mut() {
+            if key % 4 == 0 {
+                scan.remove_current();
+            } else {
+                *value = 0;
+            }
+        }
+        unchanged(&original);
+
+        let mut tree = original.clone();
+        for i in 0..1000 {
+            tree.remove(&(i * 6));
+            tree.remove_lowest();
+            tree.remove_highest();
+        }
+        unchanged(&original);
     }
-}
 
-impl<K, V, C> From<BTreeMap<K, V>> for PalmTree<K, V, C>
-where
-    K: Ord + Clone,
-    V: Clone,
-    C: TreeConfig<K, V>,
-{
-    fn from(map: BTreeMap<K, V>) -> Self {
-        Self::load(map.into_iter())
+    #[test]
+    fn shift_keys() {
+        let mut tree: StdPalmTree<usize, usize> = PalmTree::load((0..10_000).map(|i| (i * 10, i)));
+        let mut nat: BTreeMap<usize, usize> = tree.iter().map(|(k, v)| (*k, *v)).collect();
+        // Leave the key for the second leaf stale at 1270, then shift the
+        // keys after it down below that.
+        for key in (1100..=1270).step_by(10) {
+            tree.remove(&key);
+            nat.remove(&key);
+        }
+        tree.shift_keys(1280.., |key| key - 150);
+        tree.shift_keys(50_000..60_000, |key| key + 5);
+        let shift = |nat: BTreeMap<usize, usize>, range: std::ops::Range<usize>, shift: isize| {
+            nat.into_iter()
+                .map(|(k, v)| {
+                    if range.contains(&k) {
+                        ((k as isize + shift) as usize, v)
+                    } else {
+                        (k, v)
+                    }
+                })
+                .collect()
+        };
+        nat = shift(nat, 1280..usize::MAX, -150);
+        nat = shift(nat, 50_000..60_000, 5);
+        tree.check_invariants().unwrap();
+        assert!(nat.iter().eq(tree.iter()));
+        for (key, value) in &nat {
+            assert_eq!(Some(value), tree.get(key));
+        }
+        tree.shift_keys(1_000_000.., |key| key + 1);
+        assert!(nat.iter().eq(tree.iter()));
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    #[test]
+    #[should_panic(expected = "would pass a key outside the range")]
+    fn shift_keys_past_neighbour() {
+        let mut tree: StdPalmTree<usize, usize> = PalmTree::load((0..100).map(|i| (i * 10, i)));
+        tree.shift_keys(500..600, |key| key + 10);
+    }
 
     #[test]
-    fn lookup_empty() {
-        let tree: StdPalmTree<usize, usize> = PalmTree::new();
-        assert_eq!(None, tree.get(&1337));
+    fn shift_keys_out_of_order() {
+        let mut tree: StdPalmTree<usize, usize> = PalmTree::load((0..1000).map(|i| (i, i)));
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            tree.shift_keys(100..200, |key| if *key == 150 { 300 } else { *key })
+        }));
+        assert!(result.is_err());
+        tree.check_invariants().unwrap();
+        assert!((0..1000)
+            .map(|i| (i, i))
+            .eq(tree.iter().map(|(k, v)| (*k, *v))));
+
+        let mut calls = 0;
+        tree.shift_keys(100..200, |key| {
+            calls += 1;
+            *key
+        });
+        assert_eq!(100, calls);
     }
 
     #[test]
-    fn lookup_single() {
-        let mut tree: StdPalmTree<usize, usize> = PalmTree::new();
-        tree.insert(1337, 31337);
-        assert_eq!(None, tree.get(&1336));
-        assert_eq!(Some(&31337), tree.get(&1337));
-        assert_eq!(None, tree.get(&1338));
+    fn histogram() {
+        let tree: StdPalmTree<usize, usize> = PalmTree::load((0..10_000).map(|i| (i * 2, i)));
+        assert_eq!(
+            vec![1, 4949, 4999, 1],
+            tree.histogram(&[100, 101, 10_000, 19_998, 19_999])
+        );
+        assert_eq!(vec![2, 5000, 4997], tree.histogram(&[1, 5, 10_005, 20_000]));
+        assert_eq!(vec![1, 0, 1], tree.histogram(&[0, 1, 2, 3]));
+        assert_eq!(vec![0, 0], tree.histogram(&[30_000, 40_000, 50_000]));
+        assert!(tree.histogram(&[5]).is_empty());
+        let sums = tree.histogram_with(&[0, 1000, 2000], 0, |sum, _, value| sum + value);
+        assert_eq!(vec![(0..500).sum::<usize>(), (500..1000).sum()], sums);
     }
 
     #[test]
-    fn insert_in_sequence() {
-        let mut tree: StdPalmTree<usize, usize> = PalmTree::new();
-        let iters = 131_072;
-        for i in 0..iters {
-            tree.insert(i, i);
-        }
-        for i in 0..iters {
-            assert_eq!(Some(&i), tree.get(&i));
+    fn borrowed_lookups() {
+        use std::ops::Bound::{Excluded, Included};
+        let mut tree: StdPalmTree<String, usize> =
+            (0..1000).map(|i| (format!("{:04}", i), i)).collect();
+        assert_eq!(Some(&42), tree.get("0042"));
+        assert!(tree.contains_key("0999"));
+        assert!(!tree.contains_key("1000"));
+        *tree.get_mut("0007").unwrap() = 0;
+        assert_eq!(Some(&0), tree.get("0007"));
+        assert_eq!(
+            vec![498, 499, 500],
+            tree.range::<str, _>((Included("0498"), Included("0500")))
+                .map(|(_, v)| *v)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            10,
+            tree.range::<str, _>((Included("089"), Excluded("09")))
+                .count()
+        );
+        for (_, value) in tree.range_mut::<str, _>((Included("05"), Excluded("06"))) {
+            *value += 1;
         }
+        assert_eq!(Some(&551), tree.get("0550"));
+        assert_eq!(Some(("0123".to_string(), 123)), tree.remove("0123"));
+        assert_eq!(None, tree.remove("0123"));
+        assert_eq!(999, tree.len());
     }
 
     #[test]
-    fn load_from_ordered_stream() {
-        let size = 131_072;
-        let tree: StdPalmTree<usize, usize> = PalmTree::load((0..size).map(|i| (i, i)));
-        for i in 0..size {
-            assert_eq!(Some(&i), tree.get(&i));
-        }
+    fn longest_prefix() {
+        let routes = ["", "a/b", "a/b/c", "a/bc", "b", "b/c/d/e"];
+        let tree: StdPalmTree<String, usize> = routes
+            .iter()
+            .enumerate()
+            .map(|(index, route)| (route.to_string(), index))
+            .collect();
+        let find = |probe: &str| {
+            tree.get_longest_prefix(probe.as_bytes())
+                .map(|(k, _)| k.as_str())
+        };
+        assert_eq!(Some(""), find(""));
+        assert_eq!(Some(""), find("a"));
+        assert_eq!(Some("a/b"), find("a/b"));
+        assert_eq!(Some("a/b"), find("a/b/"));
+        assert_eq!(Some("a/b/c"), find("a/b/c/d"));
+        assert_eq!(Some("a/bc"), find("a/bcd"));
+        assert_eq!(Some("a/b"), find("a/ba"));
+        assert_eq!(Some("b"), find("b/c/d"));
+        assert_eq!(Some("b/c/d/e"), find("b/c/d/e/f"));
+        assert_eq!(Some(""), find("c"));
+
+        // Lots of keys, so the search has to cross leaves to back off.
+        let tree: StdPalmTree<Vec<u8>, ()> = (0..10_000u32)
+            .map(|i| (format!("{:04}", i).into_bytes(), ()))
+            .chain(vec![(b"5".to_vec(), ()), (b"77".to_vec(), ())])
+            .collect();
+        let find = |probe: &[u8]| tree.get_longest_prefix(probe).map(|(k, _)| k.clone());
+        assert_eq!(Some(b"1234".to_vec()), find(b"12345"));
+        assert_eq!(Some(b"5".to_vec()), find(b"5x"));
+        assert_eq!(Some(b"77".to_vec()), find(b"77x"));
+        assert_eq!(Some(b"7777".to_vec()), find(b"7777"));
+        assert_eq!(None, find(b"x"));
+        assert_eq!(None, find(b"123"));
+    }
+
+    fn single_entry_tree() -> StdPalmTree<usize, usize> {
+        PalmTree::load(vec![(7, 7)])
     }
 
     #[test]
-    fn delete_delete_delete() {
-        let mut tree: StdPalmTree<usize, usize> = PalmTree::load((0..131_072).map(|i| (i, i)));
-        for i in 31337..41337 {
-            assert_eq!(Some((i, i)), tree.remove(&i));
-            assert_eq!(None, tree.remove(&i));
+    fn quantiles() {
+        let tree: StdPalmTree<usize, usize> = PalmTree::load((0..10_000).map(|i| (i * 2, i)));
+        assert_eq!(Some((&0, &0)), tree.quantile(0.0));
+        assert_eq!(Some((&19_998, &9999)), tree.quantile(1.0));
+        assert_eq!(Some((&9_900, &4950)), tree.quantile(0.495));
+        assert_eq!(Some((&19_798, &9899)), tree.quantile(0.99));
+        assert_eq!(Some((&9_998, &4999)), tree.median());
+        assert_eq!(None, tree.quantile(1.5));
+        assert_eq!(None, tree.quantile(f64::NAN));
+
+        let empty: StdPalmTree<usize, usize> = PalmTree::new();
+        assert_eq!(None, empty.quantile(0.5));
+        assert_eq!(None, empty.median());
+        let mut emptied = single_entry_tree();
+        emptied.remove(&7);
+        assert_eq!(None, emptied.median());
+
+        let single = single_entry_tree();
+        assert_eq!(Some((&7, &7)), single.median());
+        assert_eq!(Some((&7, &7)), single.quantile(0.5));
+    }
+
+    #[test]
+    fn positions() {
+        // Removals leave leaves of every length, with stale separators.
+        let mut tree: ImPalmTree<usize, usize> = PalmTree::load((0..20_000).map(|i| (i, i)));
+        for i in (0..20_000).filter(|i| i % 3 == 0 || (5000..9000).contains(i)) {
+            tree.remove(&i);
         }
+        let keys: Vec<usize> = tree.keys().copied().collect();
+        for (index, key) in keys.iter().enumerate().step_by(37) {
+            assert_eq!(Some((key, key)), tree.get_index(index));
+            assert_eq!(index, tree.rank(key));
+            assert_eq!(index + 1, tree.rank(&(key + 1)));
+        }
+        assert_eq!(None, tree.get_index(keys.len()));
+        assert_eq!(0, tree.rank(&0));
+        assert_eq!(keys.len(), tree.rank(&usize::MAX));
+
+        let snapshot = tree.clone();
+        *tree.get_index_mut(1000).unwrap().1 = 0;
+        assert_eq!(Some((&keys[1000], &0)), tree.get_index(1000));
+        assert_eq!(Some((&keys[1000], &keys[1000])), snapshot.get_index(1000));
+        assert_eq!(None, tree.get_index_mut(keys.len()));
+
+        let empty: StdPalmTree<usize, usize> = PalmTree::new();
+        assert_eq!(None, empty.get_index(0));
+        assert_eq!(0, empty.rank(&5));
     }
 
     #[test]
-    fn small_delete() {
-        let mut tree: StdPalmTree<usize, usize> = PalmTree::load((0..64).map(|i| (i, i)));
-        assert_eq!(Some((0, 0)), tree.remove(&0));
-        assert_eq!(None, tree.remove(&0));
+    fn key_set_operators() {
+        let left: StdPalmTree<usize, usize> = PalmTree::load((0..5000).map(|i| (i * 2, i)));
+        let right: StdPalmTree<usize, usize> =
+            PalmTree::load((0..5000).map(|i| (i * 3, i + 100_000)));
+        let nat_left: BTreeMap<usize, usize> = left.iter().map(|(k, v)| (*k, *v)).collect();
+        let nat_right: BTreeMap<usize, usize> = right.iter().map(|(k, v)| (*k, *v)).collect();
+
+        let difference = left.clone() - right.clone();
+        let expected: Vec<_> = nat_left
+            .iter()
+            .filter(|(k, _)| !nat_right.contains_key(k))
+            .collect();
+        assert_eq!(expected, difference.iter().collect::<Vec<_>>());
+        assert_eq!(expected.len(), difference.len());
+
+        let intersection = left.clone() & right.clone();
+        let expected: Vec<_> = nat_left
+            .iter()
+            .filter(|(k, _)| nat_right.contains_key(k))
+            .collect();
+        assert_eq!(expected, intersection.iter().collect::<Vec<_>>());
+        assert_eq!(expected.len(), intersection.len());
+
+        let union = left.clone() | right.clone();
+        let mut expected = nat_right.clone();
+        expected.extend(nat_left.clone());
+        assert_eq!(expected.len(), union.len());
+        assert!(expected.iter().eq(union.iter()));
+
+        let mut assigned = left.clone();
+        assigned -= right.clone();
+        assert_eq!(difference, assigned);
+        let mut assigned = left.clone();
+        assigned &= right.clone();
+        assert_eq!(intersection, assigned);
+        let mut assigned = left;
+        assigned |= right;
+        assert_eq!(union, assigned);
     }
 
     #[test]
-    fn insert_into_emptied_tree() {
-        let mut tree: StdPalmTree<u8, u8> = PalmTree::new();
-        tree.insert(0, 0);
-        tree.remove(&0);
-        tree.insert(0, 0);
-        tree.insert(10, 10);
+    fn key_set_methods() {
+        let left: StdPalmTree<usize, usize> = PalmTree::load((0..5000).map(|i| (i * 2, 1)));
+        let right: StdPalmTree<usize, usize> = PalmTree::load((0..5000).map(|i| (i * 3, 10)));
+        let nat_left: BTreeMap<usize, usize> = left.iter().map(|(k, v)| (*k, *v)).collect();
+        let nat_right: BTreeMap<usize, usize> = right.iter().map(|(k, v)| (*k, *v)).collect();
+
+        let intersection =
+            PalmTree::intersection_with(left.clone(), right.clone(), |_, l, r| l + r);
+        intersection.check_invariants().unwrap();
+        assert!(intersection
+            .iter()
+            .all(|(key, value)| key % 6 == 0 && *value == 11));
+        assert_eq!(
+            nat_left
+                .keys()
+                .filter(|k| nat_right.contains_key(k))
+                .count(),
+            intersection.len()
+        );
 
-        let result: Vec<(u8, u8)> = tree.iter().map(|(k, v)| (*k, *v)).collect();
-        let expected: Vec<(u8, u8)> = vec![(0, 0), (10, 10)];
-        assert_eq!(expected, result);
+        let difference = PalmTree::difference(left.clone(), right.clone());
+        difference.check_invariants().unwrap();
+        assert_eq!(left.clone() - right.clone(), difference);
+
+        let symmetric = PalmTree::symmetric_difference(left.clone(), right.clone());
+        symmetric.check_invariants().unwrap();
+        let expected: BTreeMap<usize, usize> = nat_left
+            .iter()
+            .filter(|(k, _)| !nat_right.contains_key(k))
+            .chain(nat_right.iter().filter(|(k, _)| !nat_left.contains_key(k)))
+            .map(|(k, v)| (*k, *v))
+            .collect();
+        assert!(expected.iter().eq(symmetric.iter()));
+        assert!(
+            PalmTree::symmetric_difference_iter(left.clone(), left.clone())
+                .next()
+                .is_none()
+        );
+        assert!(PalmTree::symmetric_difference_iter(left.clone(), StdPalmTree::new()).eq(left));
+    }
+
+    mod panic_safety {
+        use super::*;
+        use std::cell::Cell;
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        thread_local! {
+            // Number of clones and comparisons left before a `FuseKey` panics.
+            static FUSE: Cell<Option<usize>> = const { Cell::new(None) };
+            // Number of `FuseKey`s currently alive.
+            static LIVE: Cell<isize> = const { Cell::new(0) };
+        }
+
+        fn burn_fuse() {
+            FUSE.with(|fuse| match fuse.get() {
+                Some(0) => panic!("fuse blown"),
+                Some(n) => fuse.set(Some(n - 1)),
+                None => {}
+            })
+        }
+
+        fn live() -> isize {
+            LIVE.with(Cell::get)
+        }
+
+        #[derive(Debug)]
+        struct FuseKey(usize);
+
+        impl FuseKey {
+            fn new(key: usize) -> Self {
+                LIVE.with(|live| live.set(live.get() + 1));
+                FuseKey(key)
+            }
+        }
+
+        impl Clone for FuseKey {
+            fn clone(&self) -> Self {
+                burn_fuse();
+                Self::new(self.0)
+            }
+        }
+
+        impl Drop for FuseKey {
+            fn drop(&mut self) {
+                LIVE.with(|live| live.set(live.get() - 1));
+            }
+        }
+
+        impl PartialEq for FuseKey {
+            fn eq(&self, other: &Self) -> bool {
+                self.cmp(other) == Ordering::Equal
+            }
+        }
+
+        impl Eq for FuseKey {}
+
+        impl PartialOrd for FuseKey {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for FuseKey {
+            fn cmp(&self, other: &Self) -> Ordering {
+                burn_fuse();
+                self.0.cmp(&other.0)
+            }
+        }
+
+        fn check_tree<C>(tree: &PalmTree<FuseKey, usize, C>, must_contain: &[usize])
+        where
+            C: TreeConfig<FuseKey, usize>,
+        {
+            let keys: Vec<usize> = tree.iter().map(|(k, _)| k.0).collect();
+            assert_eq!(tree.len(), keys.len());
+            assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+            for key in must_contain {
+                assert_eq!(Some(key), tree.get(&FuseKey::new(*key)));
+            }
+        }
+
+        fn insert_until_panic<C>(fuse: usize) -> bool
+        where
+            C: TreeConfig<FuseKey, usize>,
+        {
+            let size = 4096;
+            let mut tree: PalmTree<FuseKey, usize, C> =
+                PalmTree::load((0..size).map(|i| (FuseKey::new(i * 2), i * 2)));
+            let snapshot = tree.clone();
+            FUSE.with(|f| f.set(Some(fuse)));
+            let result = catch_unwind(AssertUnwindSafe(|| {
+                for i in 0..size {
+                    tree.insert(FuseKey::new(i * 2 + 1), i * 2 + 1);
+                    tree.insert(FuseKey::new(size * 2 + i), size * 2 + i);
+                }
+            }));
+            FUSE.with(|f| f.set(None));
+            let originals: Vec<usize> = (0..size).map(|i| i * 2).collect();
+            check_tree(&tree, &originals);
+            check_tree(&snapshot, &originals);
+            assert_eq!(size, snapshot.len());
+            result.is_err()
+        }
+
+        fn insert_with_fuses<C>()
+        where
+            C: TreeConfig<FuseKey, usize>,
+        {
+            for fuse in (0..20_000).step_by(313) {
+                let panicked = insert_until_panic::<C>(fuse);
+                assert_eq!(0, live(), "leaked or double dropped a key at fuse {}", fuse);
+                if !panicked {
+                    break;
+                }
+            }
+        }
+
+        #[test]
+        fn insert_with_panicking_keys() {
+            insert_with_fuses::<Tree64<Unique>>();
+        }
+
+        #[test]
+        fn insert_with_panicking_keys_shared() {
+            insert_with_fuses::<Tree64<Shared>>();
+        }
+
+        #[test]
+        fn difference_with_panicking_keys() {
+            for fuse in (0..8192).step_by(331) {
+                let mut tree: StdPalmTree<FuseKey, usize> =
+                    PalmTree::load((0..4096).map(|i| (FuseKey::new(i), i)));
+                let other: StdPalmTree<FuseKey, ()> =
+                    PalmTree::load((0..2048).map(|i| (FuseKey::new(i * 2), ())));
+                FUSE.with(|f| f.set(Some(fuse)));
+                let result = catch_unwind(AssertUnwindSafe(|| tree.difference_in_place(&other)));
+                FUSE.with(|f| f.set(None));
+                check_tree(&tree, &[]);
+                if result.is_ok() {
+                    assert!(tree
+                        .iter()
+                        .map(|(k, _)| k.0)
+                        .eq((0..2048).map(|i| i * 2 + 1)));
+                }
+                drop(tree);
+                drop(other);
+                assert_eq!(0, live(), "leaked or double dropped a key at fuse {}", fuse);
+            }
+        }
+
+        #[test]
+        fn clone_with_panicking_keys() {
+            let tree: StdPalmTree<FuseKey, usize> =
+                PalmTree::load((0..4096).map(|i| (FuseKey::new(i), i)));
+            let live_keys = live();
+            for fuse in (0..4096).step_by(97) {
+                FUSE.with(|f| f.set(Some(fuse)));
+                assert!(catch_unwind(AssertUnwindSafe(|| tree.clone())).is_err());
+                FUSE.with(|f| f.set(None));
+                assert_eq!(live_keys, live());
+            }
+            drop(tree);
+            assert_eq!(0, live());
+        }
     }
 }
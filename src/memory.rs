@@ -0,0 +1,181 @@
+use crate::{branch::Branch, config::TreeConfig, leaf::Leaf, PalmTree};
+use alloc::vec::Vec;
+use core::mem::size_of;
+use typenum::Unsigned;
+
+/// How much memory a tree's nodes take up, a level at a time. Get one from
+/// `PalmTree::memory_usage`.
+///
+/// The byte counts are the sizes of the nodes themselves. They don't count
+/// anything the keys and values own on the heap, nor the allocator's own
+/// overhead, nor the reference counts of a `Shared` or `SyncShared` node.
+/// A node shared with another tree is counted in full for both.
+///
+/// ```
+/// # use palmtree::StdPalmTree;
+/// let tree: StdPalmTree<u64, u64> = StdPalmTree::load((0..10_000).map(|i| (i, i)));
+/// let usage = tree.memory_usage();
+/// assert_eq!(2, usage.height());
+/// assert_eq!(157, usage.leaves().nodes);
+/// assert!(usage.leaves().fill_factor() > 0.99);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MemoryUsage {
+    levels: Vec<LevelUsage>,
+}
+
+/// How much memory the nodes on one level of a tree take up. See
+/// `MemoryUsage`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct LevelUsage {
+    /// The number of nodes on this level.
+    pub nodes: usize,
+    /// The number of children, or for leaves entries, the nodes hold.
+    pub used: usize,
+    /// The number of children or entries the nodes have room for.
+    pub capacity: usize,
+    /// The size of the nodes in bytes.
+    pub bytes: usize,
+}
+
+impl LevelUsage {
+    /// The fraction of the room in these nodes which is in use.
+    pub fn fill_factor(&self) -> f64 {
+        if self.capacity == 0 {
+            0.0
+        } else {
+            self.used as f64 / self.capacity as f64
+        }
+    }
+}
+
+impl MemoryUsage {
+    /// The levels of the tree, from the root down to the leaves.
+    pub fn levels(&self) -> &[LevelUsage] {
+        &self.levels
+    }
+
+    /// The levels of branches, from the root down.
+    pub fn branches(&self) -> &[LevelUsage] {
+        self.levels
+            .split_last()
+            .map_or(&[], |(_, branches)| branches)
+    }
+
+    /// The level of leaves, which is empty if the tree has no nodes.
+    pub fn leaves(&self) -> LevelUsage {
+        self.levels.last().copied().unwrap_or_default()
+    }
+
+    /// The number of levels of branches above the leaves.
+    pub fn height(&self) -> usize {
+        self.branches().len()
+    }
+
+    /// The number of nodes on every level.
+    pub fn nodes(&self) -> usize {
+        self.levels.iter().map(|level| level.nodes).sum()
+    }
+
+    /// The size of every node in bytes.
+    pub fn bytes(&self) -> usize {
+        self.levels.iter().map(|level| level.bytes).sum()
+    }
+
+    /// The number of bytes in the nodes for each entry in the tree.
+    pub fn bytes_per_entry(&self) -> f64 {
+        let entries = self.leaves().used;
+        if entries == 0 {
+            0.0
+        } else {
+            self.bytes() as f64 / entries as f64
+        }
+    }
+}
+
+fn add_branch<K, V, C>(branch: &Branch<K, V, C>, depth: usize, levels: &mut Vec<LevelUsage>)
+where
+    C: TreeConfig<K, V>,
+{
+    if levels.len() <= depth {
+        levels.push(LevelUsage::default());
+    }
+    let level = &mut levels[depth];
+    level.nodes += 1;
+    level.used += branch.len();
+    level.capacity += C::BranchSize::USIZE;
+    level.bytes += size_of::<Branch<K, V, C>>();
+    if branch.has_branches() {
+        for index in 0..branch.len() {
+            add_branch(branch.get_branch(index), depth + 1, levels);
+        }
+    } else if !branch.is_empty() {
+        if levels.len() <= depth + 1 {
+            levels.push(LevelUsage::default());
+        }
+        let level = &mut levels[depth + 1];
+        for index in 0..branch.len() {
+            level.nodes += 1;
+            level.used += branch.get_leaf(index).len();
+            level.capacity += C::LeafSize::USIZE;
+            level.bytes += size_of::<Leaf<K, V, C>>();
+        }
+    }
+}
+
+impl<K, V, C> PalmTree<K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    /// Walk the tree and count up the memory its nodes take up. See
+    /// `MemoryUsage`.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let mut levels = Vec::new();
+        if let Some(ref root) = self.root {
+            add_branch(root, 0, &mut levels);
+        }
+        MemoryUsage { levels }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{tests::scaled, StdPalmTree};
+
+    #[test]
+    fn count_every_level() {
+        assert_eq!(
+            MemoryUsage::default(),
+            StdPalmTree::<u8, u8>::new().memory_usage()
+        );
+
+        let size = scaled(100_000) as u32;
+        let mut tree: StdPalmTree<u32, u32> = (0..size).map(|i| (i * 7919 % size, i)).collect();
+        let usage = tree.memory_usage();
+        assert_eq!(3, usage.levels().len());
+        assert_eq!(1, usage.levels()[0].nodes);
+        assert_eq!(tree.len(), usage.leaves().used);
+        // Each level holds as many children as there are nodes below it.
+        for pair in usage.levels().windows(2) {
+            assert_eq!(pair[0].used, pair[1].nodes);
+        }
+        assert_eq!(
+            usage.bytes(),
+            usage
+                .branches()
+                .iter()
+                .map(|level| level.nodes)
+                .sum::<usize>()
+                * size_of::<Branch<u32, u32, crate::Tree64<crate::Unique>>>()
+                + usage.leaves().nodes * size_of::<Leaf<u32, u32, crate::Tree64<crate::Unique>>>()
+        );
+
+        let random_fill = usage.leaves().fill_factor();
+        assert!(random_fill < 0.9, "{}", random_fill);
+        tree.compact();
+        let compacted = tree.memory_usage();
+        assert!(compacted.leaves().fill_factor() > 0.99);
+        assert!(compacted.bytes_per_entry() < usage.bytes_per_entry());
+    }
+}
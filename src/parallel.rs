@@ -0,0 +1,439 @@
+use crate::{
+    branch::{Branch, ChildMut, ChildrenMut},
+    builder::dedup_sorted_entries,
+    config::TreeConfig,
+    pointer::Pointer,
+    PalmTree,
+};
+use alloc::vec::Vec;
+use core::fmt::{Debug, Error, Formatter};
+use rayon::{
+    iter::{
+        plumbing::{bridge_unindexed, Folder, UnindexedConsumer, UnindexedProducer},
+        FromParallelIterator, IndexedParallelIterator, IntoParallelIterator, ParallelExtend,
+        ParallelIterator,
+    },
+    slice::ParallelSliceMut,
+};
+
+/// How many entries `par_load` gives each thread to load.
+const LOAD_CHUNK: usize = 1 << 16;
+
+/// Collect a parallel iterator into a sorted vector with no duplicate keys.
+///
+/// The sort is stable, so where a key turns up more than once, we keep the
+/// last one, as inserting the entries in order would have done.
+fn sorted_entries<K, V, I>(par_iter: I) -> Vec<(K, V)>
+where
+    K: Ord + Send,
+    V: Send,
+    I: IntoParallelIterator<Item = (K, V)>,
+{
+    let mut entries: Vec<(K, V)> = par_iter.into_par_iter().collect();
+    entries.par_sort_by(|(left, _), (right, _)| left.cmp(right));
+    dedup_sorted_entries(&mut entries);
+    entries
+}
+
+impl<K, V, C> FromParallelIterator<(K, V)> for PalmTree<K, V, C>
+where
+    K: Ord + Clone + Send,
+    V: Clone + Send,
+    C: TreeConfig<K, V>,
+{
+    fn from_par_iter<I>(par_iter: I) -> Self
+    where
+        I: IntoParallelIterator<Item = (K, V)>,
+    {
+        // Sorting is where the time goes, so that's done in parallel.
+        // Loading the sorted entries is a single linear pass.
+        Self::load(sorted_entries(par_iter))
+    }
+}
+
+impl<K, V, C> ParallelExtend<(K, V)> for PalmTree<K, V, C>
+where
+    K: Ord + Clone + Send,
+    V: Clone + Send,
+    C: TreeConfig<K, V>,
+{
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = (K, V)>,
+    {
+        self.append_right(Self::load(sorted_entries(par_iter)))
+    }
+}
+
+impl<K, V, C> PalmTree<K, V, C>
+where
+    K: Ord + Clone + Send,
+    V: Clone + Send,
+    C: TreeConfig<K, V>,
+{
+    /// Construct a tree from a vector of entries in ascending key order
+    /// with no duplicates, like `load`, but in parallel.
+    ///
+    /// Each thread loads a run of the entries into a tree of its own, and
+    /// the trees are joined up in order with the same graft
+    /// `append_right` uses when keys don't overlap. Like `load`, this
+    /// panics if the entries aren't in order.
+    pub fn par_load(entries: Vec<(K, V)>) -> Self
+    where
+        Self: Send,
+    {
+        if entries.len() <= LOAD_CHUNK {
+            return Self::load(entries);
+        }
+        // Each run's order is checked as it's loaded, but the joins between
+        // them have to be checked here.
+        for start in (LOAD_CHUNK..entries.len()).step_by(LOAD_CHUNK) {
+            if entries[start - 1].0 >= entries[start].0 {
+                panic!("PalmTree::load: unordered key at index {}", start);
+            }
+        }
+        entries
+            .into_par_iter()
+            .chunks(LOAD_CHUNK)
+            .enumerate()
+            .map(|(index, chunk)| Self::load_at(chunk, index * LOAD_CHUNK))
+            .reduce(Self::new, Self::concat)
+    }
+}
+
+/// A parallel iterator over the entries of a tree, in no particular order.
+///
+/// The work is split up along branch boundaries, so each thread gets whole
+/// subtrees, and within each, whole leaves.
+///
+/// ```
+/// # use palmtree::StdPalmTree;
+/// # use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+/// let tree: StdPalmTree<u64, u64> = (0..100_000).map(|i| (i, i)).collect();
+/// assert_eq!(tree.iter().map(|(_, v)| v).sum::<u64>(), tree.par_iter().map(|(_, v)| v).sum());
+/// ```
+pub struct ParIter<'a, K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    tree: &'a PalmTree<K, V, C>,
+}
+
+/// A parallel iterator over the entries of a tree with the values mutable.
+/// See `ParIter`.
+pub struct ParIterMut<'a, K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    tree: &'a mut PalmTree<K, V, C>,
+}
+
+/// The children from `start` up to `end` of a branch.
+struct Children<'a, K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    branch: &'a Branch<K, V, C>,
+    start: usize,
+    end: usize,
+}
+
+impl<'a, K, V, C> Children<'a, K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    fn all(branch: &'a Branch<K, V, C>) -> Self {
+        Self {
+            branch,
+            start: 0,
+            end: branch.len(),
+        }
+    }
+
+    fn fold<F>(self, mut folder: F) -> F
+    where
+        F: Folder<(&'a K, &'a V)>,
+    {
+        for index in self.start..self.end {
+            if folder.full() {
+                break;
+            }
+            folder = if self.branch.has_branches() {
+                Self::all(self.branch.get_branch(index)).fold(folder)
+            } else {
+                let leaf = self.branch.get_leaf(index);
+                folder.consume_iter(leaf.keys().iter().zip(leaf.values()))
+            };
+        }
+        folder
+    }
+}
+
+// Whether a pointer can cross threads depends on its kind, not just on what
+// it points to, so we ask it of the node pointers themselves.
+impl<'a, K, V, C> UnindexedProducer for Children<'a, K, V, C>
+where
+    K: Send + Sync,
+    V: Send + Sync,
+    C: Send + Sync + TreeConfig<K, V>,
+    Pointer<(), C::PointerKind>: Send + Sync,
+{
+    type Item = (&'a K, &'a V);
+
+    fn split(self) -> (Self, Option<Self>) {
+        let mut this = self;
+        // A lone branch can't be split, but its children can.
+        while this.end - this.start == 1 && this.branch.has_branches() {
+            this = Self::all(this.branch.get_branch(this.start));
+        }
+        if this.end - this.start < 2 {
+            return (this, None);
+        }
+        let middle = this.start + (this.end - this.start) / 2;
+        let right = Self {
+            branch: this.branch,
+            start: middle,
+            end: this.end,
+        };
+        this.end = middle;
+        (this, Some(right))
+    }
+
+    fn fold_with<F>(self, folder: F) -> F
+    where
+        F: Folder<Self::Item>,
+    {
+        self.fold(folder)
+    }
+}
+
+fn fold_mut<'a, K, V, C, F>(children: ChildrenMut<'a, K, V, C>, mut folder: F) -> F
+where
+    K: Clone,
+    V: Clone,
+    C: TreeConfig<K, V>,
+    F: Folder<(&'a K, &'a mut V)>,
+{
+    for child in children.into_children() {
+        if folder.full() {
+            break;
+        }
+        folder = match child {
+            ChildMut::Branch(children) => fold_mut(children, folder),
+            ChildMut::Leaf(leaf) => {
+                let (keys, values) = leaf.entries_mut();
+                folder.consume_iter(keys.iter().zip(values.iter_mut()))
+            }
+        };
+    }
+    folder
+}
+
+impl<'a, K, V, C> UnindexedProducer for ChildrenMut<'a, K, V, C>
+where
+    K: Clone + Send + Sync,
+    V: Clone + Send + Sync,
+    C: Send + Sync + TreeConfig<K, V>,
+    Pointer<(), C::PointerKind>: Send + Sync,
+{
+    type Item = (&'a K, &'a mut V);
+
+    fn split(self) -> (Self, Option<Self>) {
+        let this = self.descend();
+        if this.len() < 2 {
+            return (this, None);
+        }
+        let middle = this.len() / 2;
+        let (left, right) = this.split_at(middle);
+        (left, Some(right))
+    }
+
+    fn fold_with<F>(self, folder: F) -> F
+    where
+        F: Folder<Self::Item>,
+    {
+        fold_mut(self, folder)
+    }
+}
+
+impl<'a, K, V, C> ParallelIterator for ParIter<'a, K, V, C>
+where
+    K: Send + Sync,
+    V: Send + Sync,
+    C: Send + Sync + TreeConfig<K, V>,
+    Pointer<(), C::PointerKind>: Send + Sync,
+    PalmTree<K, V, C>: Send + Sync,
+{
+    type Item = (&'a K, &'a V);
+
+    fn drive_unindexed<Consumer>(self, consumer: Consumer) -> Consumer::Result
+    where
+        Consumer: UnindexedConsumer<Self::Item>,
+    {
+        match self.tree.root {
+            Some(ref root) if self.tree.size > 0 => bridge_unindexed(Children::all(root), consumer),
+            _ => rayon::iter::empty().drive_unindexed(consumer),
+        }
+    }
+}
+
+impl<'a, K, V, C> ParallelIterator for ParIterMut<'a, K, V, C>
+where
+    K: Ord + Clone + Send + Sync,
+    V: Clone + Send + Sync,
+    C: Send + Sync + TreeConfig<K, V>,
+    Pointer<(), C::PointerKind>: Send + Sync,
+    PalmTree<K, V, C>: Send + Sync,
+{
+    type Item = (&'a K, &'a mut V);
+
+    fn drive_unindexed<Consumer>(self, consumer: Consumer) -> Consumer::Result
+    where
+        Consumer: UnindexedConsumer<Self::Item>,
+    {
+        if self.tree.is_empty() {
+            return rayon::iter::empty().drive_unindexed(consumer);
+        }
+        // Every node is going to be written through, so none of them can
+        // be shared with another tree.
+        self.tree.make_range_unique(&..);
+        match self.tree.root {
+            Some(ref mut root) => {
+                let root = Pointer::make_mut(root);
+                bridge_unindexed(root.children_run_mut(), consumer)
+            }
+            None => rayon::iter::empty().drive_unindexed(consumer),
+        }
+    }
+}
+
+impl<'a, K, V, C> IntoParallelIterator for &'a PalmTree<K, V, C>
+where
+    K: Send + Sync,
+    V: Send + Sync,
+    C: Send + Sync + TreeConfig<K, V>,
+    Pointer<(), C::PointerKind>: Send + Sync,
+    PalmTree<K, V, C>: Send + Sync,
+{
+    type Item = (&'a K, &'a V);
+    type Iter = ParIter<'a, K, V, C>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        ParIter { tree: self }
+    }
+}
+
+impl<'a, K, V, C> IntoParallelIterator for &'a mut PalmTree<K, V, C>
+where
+    K: Ord + Clone + Send + Sync,
+    V: Clone + Send + Sync,
+    C: Send + Sync + TreeConfig<K, V>,
+    Pointer<(), C::PointerKind>: Send + Sync,
+    PalmTree<K, V, C>: Send + Sync,
+{
+    type Item = (&'a K, &'a mut V);
+    type Iter = ParIterMut<'a, K, V, C>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        ParIterMut { tree: self }
+    }
+}
+
+impl<'a, K, V, C> Debug for ParIter<'a, K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "ParIter")
+    }
+}
+
+impl<'a, K, V, C> Debug for ParIterMut<'a, K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "ParIterMut")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{PalmTree, StdPalmTree, SyncPalmTree};
+    use rayon::iter::{
+        IntoParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelExtend,
+        ParallelIterator,
+    };
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn collect_in_parallel() {
+        let tree: StdPalmTree<usize, usize> = (0..100_000usize)
+            .into_par_iter()
+            .map(|i| ((i * 7919) % 50_000, i))
+            .collect();
+        let nat: BTreeMap<usize, usize> = (0..100_000usize)
+            .map(|i| ((i * 7919) % 50_000, i))
+            .collect();
+        assert_eq!(nat.len(), tree.len());
+        assert!(nat.iter().eq(tree.iter()));
+    }
+
+    #[test]
+    fn extend_in_parallel() {
+        let mut tree: StdPalmTree<usize, usize> = (0..10_000).map(|i| (i * 2, i)).collect();
+        let mut nat: BTreeMap<usize, usize> = tree.iter().map(|(k, v)| (*k, *v)).collect();
+        tree.par_extend((0..10_000usize).into_par_iter().map(|i| (i * 3, i + 1)));
+        nat.extend((0..10_000usize).map(|i| (i * 3, i + 1)));
+        assert_eq!(nat.len(), tree.len());
+        assert!(nat.iter().eq(tree.iter()));
+    }
+
+    #[test]
+    #[should_panic(expected = "PalmTree::load: unordered key at index 65536")]
+    fn par_load_checks_between_runs() {
+        let entries: Vec<(usize, ())> = (65_536..131_072)
+            .chain(0..65_536)
+            .map(|i| (i, ()))
+            .collect();
+        let _: StdPalmTree<usize, ()> = PalmTree::par_load(entries);
+    }
+
+    #[test]
+    #[should_panic(expected = "PalmTree::load: unordered key at index 70000")]
+    fn par_load_reports_index_in_whole_input() {
+        let mut entries: Vec<(usize, ())> = (0..200_000).map(|i| (i, ())).collect();
+        entries.swap(69_999, 70_000);
+        let _: StdPalmTree<usize, ()> = PalmTree::par_load(entries);
+    }
+
+    #[test]
+    fn iterate_in_parallel() {
+        let mut tree: SyncPalmTree<u64, u64> = (0..100_000).map(|i| (i, i)).collect();
+        let copy = tree.clone();
+        assert_eq!(100_000, tree.par_iter().count());
+        assert_eq!(
+            copy.iter().map(|(_, v)| v).sum::<u64>(),
+            tree.par_iter().map(|(_, v)| v).sum::<u64>()
+        );
+
+        tree.par_iter_mut().for_each(|(k, v)| *v = k * 2);
+        tree.check_invariants().unwrap();
+        assert!(tree.iter().all(|(k, v)| *v == k * 2));
+        // The copy shared every node, and none of them were written to.
+        assert!(copy.iter().all(|(k, v)| k == v));
+
+        let mut empty: SyncPalmTree<u64, u64> = PalmTree::new();
+        assert_eq!(0, empty.par_iter().count());
+        assert_eq!(0, empty.par_iter_mut().count());
+    }
+
+    #[test]
+    fn load_in_parallel() {
+        let entries: Vec<(usize, usize)> = (0..300_000).map(|i| (i, i + 1)).collect();
+        let tree: StdPalmTree<usize, usize> = PalmTree::par_load(entries.clone());
+        tree.check_invariants().unwrap();
+        assert_eq!(entries.len(), tree.len());
+        assert!(entries.iter().map(|(k, v)| (k, v)).eq(tree.iter()));
+    }
+}
@@ -0,0 +1,174 @@
+use crate::{CoWalk, Merged};
+use alloc::vec::Vec;
+use core::fmt::{Display, Error, Formatter};
+
+/// The changes which turn one tree into another.
+///
+/// Get one from `PalmTree::diff`, and apply it to a copy of the tree it
+/// was taken from with `PalmTree::apply_patch`:
+///
+/// ```
+/// # use palmtree::StdPalmTree;
+/// let old = StdPalmTree::load((0..100).map(|i| (i, i)));
+/// let mut new = old.clone();
+/// new.remove(&10);
+/// new.insert(20, 0);
+/// new.insert(200, 200);
+///
+/// let patch = old.diff(&new);
+/// assert_eq!(&[(20, 0), (200, 200)], patch.upserts());
+/// assert_eq!(&[10], patch.removals());
+///
+/// let mut replica = old.clone();
+/// replica.apply_patch(patch);
+/// assert_eq!(new, replica);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Patch<K, V> {
+    upserts: Vec<(K, V)>,
+    removals: Vec<K>,
+}
+
+/// Why `Patch::from_parts` turned down the changes it was given.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PatchError {
+    /// The upsert at this index isn't above the one before it.
+    UpsertOrder(usize),
+    /// The removal at this index isn't above the one before it.
+    RemovalOrder(usize),
+    /// The removal at this index is also a key being upserted.
+    Overlap(usize),
+}
+
+impl Display for PatchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            PatchError::UpsertOrder(index) => write!(f, "upsert {} is out of order", index),
+            PatchError::RemovalOrder(index) => write!(f, "removal {} is out of order", index),
+            PatchError::Overlap(index) => write!(f, "removal {} is also upserted", index),
+        }
+    }
+}
+
+impl core::error::Error for PatchError {}
+
+impl<K, V> Patch<K, V> {
+    pub(crate) fn new(upserts: Vec<(K, V)>, removals: Vec<K>) -> Self {
+        Self { upserts, removals }
+    }
+
+    /// Put together a patch from entries to upsert and keys to remove, as
+    /// `into_parts` takes one apart.
+    ///
+    /// Both have to be in ascending key order, without duplicates, and no
+    /// key can be both upserted and removed, as in a patch from
+    /// `PalmTree::diff`.
+    ///
+    /// ```
+    /// # use palmtree::{Patch, PatchError};
+    /// let patch = Patch::from_parts(vec![(1, 'a'), (3, 'c')], vec![2]).unwrap();
+    /// assert_eq!(3, patch.len());
+    /// assert_eq!(Err(PatchError::Overlap(0)), Patch::from_parts(vec![(1, 'a')], vec![1]));
+    /// ```
+    pub fn from_parts(upserts: Vec<(K, V)>, removals: Vec<K>) -> Result<Self, PatchError>
+    where
+        K: Ord,
+    {
+        if let Some(index) = upserts.windows(2).position(|pair| pair[0].0 >= pair[1].0) {
+            return Err(PatchError::UpsertOrder(index + 1));
+        }
+        if let Some(index) = removals.windows(2).position(|pair| pair[0] >= pair[1]) {
+            return Err(PatchError::RemovalOrder(index + 1));
+        }
+        let overlap = CoWalk::new(
+            upserts.iter(),
+            removals.iter().enumerate(),
+            |(key, _), (_, removal)| key.cmp(removal),
+        )
+        .find_map(|merged| match merged {
+            Merged::Both(_, (index, _)) => Some(index),
+            _ => None,
+        });
+        match overlap {
+            Some(index) => Err(PatchError::Overlap(index)),
+            None => Ok(Self::new(upserts, removals)),
+        }
+    }
+
+    /// The entries to insert or overwrite, in key order.
+    pub fn upserts(&self) -> &[(K, V)] {
+        &self.upserts
+    }
+
+    /// The keys to remove, in order.
+    pub fn removals(&self) -> &[K] {
+        &self.removals
+    }
+
+    /// The number of changes in the patch.
+    pub fn len(&self) -> usize {
+        self.upserts.len() + self.removals.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn into_parts(self) -> (Vec<(K, V)>, Vec<K>) {
+        (self.upserts, self.removals)
+    }
+}
+
+impl<K, V> Default for Patch<K, V> {
+    fn default() -> Self {
+        Self::new(Vec::new(), Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::StdPalmTree;
+
+    #[test]
+    fn replicate_by_diff() {
+        let mut source: StdPalmTree<usize, usize> = StdPalmTree::load((0..10_000).map(|i| (i, i)));
+        let mut replica = source.clone();
+        for round in 1..10 {
+            for i in (0..10_000).step_by(round * 7) {
+                source.remove(&i);
+            }
+            for i in (0..20_000).step_by(round * 13) {
+                source.insert(i, round);
+            }
+            let patch = replica.diff(&source);
+            assert!(!patch.is_empty());
+            replica.apply_patch(patch);
+            assert_eq!(source, replica);
+            assert!(replica.diff(&source).is_empty());
+        }
+        replica.apply_patch(replica.diff(&StdPalmTree::new()));
+        assert!(replica.is_empty());
+    }
+
+    #[test]
+    fn patch_from_parts() {
+        let old: StdPalmTree<usize, usize> = StdPalmTree::load((0..100).map(|i| (i, i)));
+        let patch = Patch::from_parts(vec![(5, 0), (200, 1)], vec![3, 4, 6]).unwrap();
+        let mut new = old.clone();
+        new.apply_patch(patch.clone());
+        assert_eq!(patch, old.diff(&new));
+        assert_eq!(
+            Err(PatchError::UpsertOrder(2)),
+            Patch::from_parts(vec![(1, ()), (2, ()), (2, ())], vec![])
+        );
+        assert_eq!(
+            Err(PatchError::RemovalOrder(1)),
+            Patch::<usize, ()>::from_parts(vec![], vec![5, 4])
+        );
+        assert_eq!(
+            Err(PatchError::Overlap(2)),
+            Patch::from_parts(vec![(1, ()), (4, ())], vec![2, 3, 4])
+        );
+    }
+}
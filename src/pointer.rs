@@ -1,12 +1,12 @@
 #![allow(missing_debug_implementations)]
+#![allow(clippy::missing_safety_doc)] // this trait is only implemented in this module
 
-use std::{
+use alloc::{boxed::Box, rc::Rc, sync::Arc};
+use core::{
     marker::PhantomData,
     mem::{ManuallyDrop, MaybeUninit},
     ops::{Deref, DerefMut},
     ptr::NonNull,
-    rc::Rc,
-    sync::Arc,
 };
 
 pub trait PointerKind {
@@ -19,6 +19,43 @@ pub trait PointerKind {
     unsafe fn clone<A: Clone>(&self) -> Self;
 }
 
+/// A `PointerKind` whose pointers can be sent and shared between threads
+/// whenever what they point to is both `Send` and `Sync`, as an `Arc` can.
+///
+/// `SyncShared` is one. Implement it for your own kind to make trees using
+/// it `Send` and `Sync` under the same bounds as a `SyncPalmTree`; trees
+/// using any other kind stay on one thread, except for `Unique` ones.
+///
+/// # Safety
+///
+/// Clones of a pointer may be dereferenced and dropped on different
+/// threads at once, so the kind has to count them, and free what they
+/// point to, atomically.
+///
+/// ```
+/// # use palmtree::{PalmTree, PointerKind, SyncPointerKind, SyncShared, Tree64};
+/// # use core::ptr::NonNull;
+/// struct Counted(SyncShared);
+///
+/// impl PointerKind for Counted {
+///     // ...
+/// #   unsafe fn new<A>(value: A) -> Self { Self(SyncShared::new(value)) }
+/// #   unsafe fn into_raw<A>(self) -> NonNull<A> { self.0.into_raw() }
+/// #   unsafe fn from_raw<A>(ptr: NonNull<A>) -> Self { Self(SyncShared::from_raw(ptr)) }
+/// #   unsafe fn deref<A>(&self) -> &A { self.0.deref() }
+/// #   unsafe fn make_mut<A: Clone>(&mut self) -> &mut A { self.0.make_mut() }
+/// #   unsafe fn drop_ptr<A>(&mut self) { self.0.drop_ptr::<A>() }
+/// #   unsafe fn clone<A: Clone>(&self) -> Self { Self(self.0.clone::<A>()) }
+/// }
+///
+/// unsafe impl SyncPointerKind for Counted {}
+///
+/// fn send_sync<T: Send + Sync>(_: &T) {}
+/// let tree: PalmTree<u32, u32, Tree64<Counted>> = PalmTree::load((0..1000).map(|i| (i, i)));
+/// send_sync(&tree);
+/// ```
+pub unsafe trait SyncPointerKind: PointerKind {}
+
 pub struct Unique {
     data: MaybeUninit<Box<()>>,
 }
@@ -33,7 +70,7 @@ impl Unique {
     }
 
     unsafe fn cast_into<A>(self) -> Box<A> {
-        std::mem::transmute(self)
+        core::mem::transmute(self)
     }
 }
 
@@ -59,7 +96,7 @@ impl PointerKind for Unique {
     }
 
     unsafe fn drop_ptr<A>(&mut self) {
-        std::ptr::drop_in_place(self.data.as_mut_ptr().cast::<Box<A>>())
+        core::ptr::drop_in_place(self.data.as_mut_ptr().cast::<Box<A>>())
     }
 
     unsafe fn clone<A: Clone>(&self) -> Self {
@@ -81,7 +118,7 @@ impl Shared {
     }
 
     unsafe fn cast_into<A>(self) -> Rc<A> {
-        std::mem::transmute(self)
+        core::mem::transmute(self)
     }
 }
 
@@ -107,7 +144,7 @@ impl PointerKind for Shared {
     }
 
     unsafe fn drop_ptr<A>(&mut self) {
-        std::ptr::drop_in_place(self.data.as_mut_ptr().cast::<Rc<A>>())
+        core::ptr::drop_in_place(self.data.as_mut_ptr().cast::<Rc<A>>())
     }
 
     unsafe fn clone<A: Clone>(&self) -> Self {
@@ -129,7 +166,7 @@ impl SyncShared {
     }
 
     unsafe fn cast_into<A>(self) -> Arc<A> {
-        std::mem::transmute(self)
+        core::mem::transmute(self)
     }
 }
 
@@ -147,7 +184,7 @@ impl PointerKind for SyncShared {
     }
 
     unsafe fn deref<A>(&self) -> &A {
-        (*self.data.as_ptr().cast::<Box<A>>()).deref()
+        (*self.data.as_ptr().cast::<Arc<A>>()).deref()
     }
 
     unsafe fn make_mut<A: Clone>(&mut self) -> &mut A {
@@ -155,7 +192,7 @@ impl PointerKind for SyncShared {
     }
 
     unsafe fn drop_ptr<A>(&mut self) {
-        std::ptr::drop_in_place(self.data.as_mut_ptr().cast::<Arc<A>>())
+        core::ptr::drop_in_place(self.data.as_mut_ptr().cast::<Arc<A>>())
     }
 
     unsafe fn clone<A: Clone>(&self) -> Self {
@@ -168,8 +205,19 @@ pub(crate) struct Pointer<A, Kind: PointerKind> {
     kind: PhantomData<A>,
 }
 
-unsafe impl<A, Kind> Send for Pointer<A, Kind> where Kind: PointerKind + Send {}
-unsafe impl<A, Kind> Sync for Pointer<A, Kind> where Kind: PointerKind + Sync {}
+// A `Unique` pointer owns its `A` outright, like a `Box`, so it's as `Send`
+// and `Sync` as `A` is.
+unsafe impl<A: Send> Send for Pointer<A, Unique> {}
+unsafe impl<A: Sync> Sync for Pointer<A, Unique> {}
+
+// A `SyncShared` pointer hands out `&A` to whichever thread holds a clone
+// of it, and drops `A` on whichever thread lets go of it last, so it's only
+// safe to send if `A` is both `Send` and `Sync`, like an `Arc`. The same
+// goes for any other kind which says it counts its clones atomically.
+// `Shared` is neither, because `Rc` isn't.
+unsafe impl SyncPointerKind for SyncShared {}
+unsafe impl<A: Send + Sync, Kind: SyncPointerKind> Send for Pointer<A, Kind> {}
+unsafe impl<A: Send + Sync, Kind: SyncPointerKind> Sync for Pointer<A, Kind> {}
 
 impl<A, Kind: PointerKind> Pointer<A, Kind> {
     fn from_data(data: Kind) -> Self {
@@ -185,7 +233,7 @@ impl<A, Kind: PointerKind> Pointer<A, Kind> {
 
     pub(crate) fn into_raw(mut this: Self) -> NonNull<A> {
         let ptr = unsafe { ManuallyDrop::take(&mut this.data).into_raw::<A>() };
-        std::mem::forget(this);
+        core::mem::forget(this);
         ptr
     }
 
@@ -208,6 +256,11 @@ impl<A, Kind: PointerKind> Pointer<A, Kind> {
         this.data.deref().deref::<B>()
     }
 
+    /// Clone the pointer as if it pointed to a `B`.
+    pub(crate) unsafe fn clone_cast<B: Clone>(this: &Self) -> Self {
+        Self::from_data(this.data.clone::<B>())
+    }
+
     pub(crate) unsafe fn make_mut_cast<B>(this: &mut Self) -> &mut B
     where
         B: Clone,
@@ -0,0 +1,414 @@
+use crate::{config::TreeConfig, PalmTree};
+use alloc::vec::Vec;
+use core::{
+    fmt::{Debug, Error, Formatter},
+    ops::Range,
+};
+
+/// A map from non-overlapping ranges of keys to values.
+///
+/// Each range is stored as a single entry keyed by its start. Inserting a
+/// range overwrites whatever was mapped inside it before, splitting any
+/// range it only partly covers, and merges it with neighbours it touches
+/// which map to an equal value.
+///
+/// ```
+/// # use palmtree::{RangeMap, Tree64, Unique};
+/// let mut map: RangeMap<u32, &str, Tree64<Unique>> = RangeMap::new();
+/// map.insert(0..100, "free");
+/// map.insert(10..20, "used");
+/// map.insert(20..30, "used");
+/// assert_eq!(Some("used"), map.get(&25).copied());
+/// assert_eq!(Some("free"), map.get(&50).copied());
+/// let ranges: Vec<_> = map.iter().map(|(range, value)| (*range.start..*range.end, *value)).collect();
+/// assert_eq!(vec![(0..10, "free"), (10..30, "used"), (30..100, "free")], ranges);
+/// ```
+pub struct RangeMap<K, V, C>
+where
+    C: TreeConfig<K, (K, V)>,
+{
+    tree: PalmTree<K, (K, V), C>,
+}
+
+impl<K, V, C> RangeMap<K, V, C>
+where
+    K: Clone + Ord,
+    C: TreeConfig<K, (K, V)>,
+{
+    pub fn new() -> Self {
+        Self {
+            tree: PalmTree::new(),
+        }
+    }
+
+    /// The number of separate ranges in the map.
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Find the range containing `key`, and the value it maps to.
+    pub fn get_range(&self, key: &K) -> Option<(Range<&K>, &V)> {
+        let (start, (end, value)) = self.tree.range(..=key).next_back()?;
+        if key < end {
+            Some((start..end, value))
+        } else {
+            None
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.get_range(key).map(|(_, value)| value)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get_range(key).is_some()
+    }
+
+    /// Iterate over the ranges in the map, in order.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (Range<&K>, &V)> + '_ {
+        self.tree
+            .iter()
+            .map(|(start, (end, value))| (start..end, value))
+    }
+
+    /// Iterate over the ranges in the map which overlap `range`, in order.
+    pub fn overlapping<'a>(
+        &'a self,
+        range: &'a Range<K>,
+    ) -> impl DoubleEndedIterator<Item = (Range<&'a K>, &'a V)> + 'a {
+        // The range before the start of `range` could still reach into it.
+        let from = match self.get_range(&range.start) {
+            Some((found, _)) => found.start,
+            None => &range.start,
+        };
+        let to = if from < &range.end { &range.end } else { from };
+        self.tree
+            .range(from..to)
+            .map(|(start, (end, value))| (start..end, value))
+    }
+
+    /// Map every key in `range` to `value`.
+    pub fn insert(&mut self, range: Range<K>, value: V)
+    where
+        V: Clone + PartialEq,
+    {
+        if range.start >= range.end {
+            return;
+        }
+        self.remove(range.clone());
+        let Range { mut start, mut end } = range;
+
+        // Merge with a neighbour on the left which ends where we start.
+        let left = match self.tree.range(..&start).next_back() {
+            Some((left_start, (left_end, left_value)))
+                if left_end == &start && left_value == &value =>
+            {
+                Some(left_start.clone())
+            }
+            _ => None,
+        };
+        if let Some(left_start) = left {
+            self.tree.remove(&left_start);
+            start = left_start;
+        }
+
+        // And with one on the right which starts where we end.
+        let right = match self.tree.get(&end) {
+            Some((right_end, right_value)) if right_value == &value => Some(right_end.clone()),
+            _ => None,
+        };
+        if let Some(right_end) = right {
+            self.tree.remove(&end);
+            end = right_end;
+        }
+
+        self.tree.insert(start, (end, value));
+    }
+
+    /// Unmap every key in `range`, cutting short or splitting any ranges
+    /// which reach outside it.
+    pub fn remove(&mut self, range: Range<K>)
+    where
+        V: Clone,
+    {
+        if range.start >= range.end {
+            return;
+        }
+        let Range { start, end } = range;
+
+        // A range starting below `start` which reaches into it gets cut
+        // short, leaving whatever's left over past `end` as a new range.
+        let mut leftover = None;
+        if let Some((before, _)) = self.get_range(&start) {
+            let before = before.start.clone();
+            if before < start {
+                let (before_end, value) = self.tree.get_mut(&before).unwrap();
+                if *before_end > end {
+                    leftover = Some((end.clone(), before_end.clone(), value.clone()));
+                }
+                *before_end = start.clone();
+            }
+        }
+
+        // Ranges starting inside go, except for anything past `end`.
+        let inside: Vec<K> = self
+            .tree
+            .range(&start..&end)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in inside {
+            let (_, (inside_end, value)) = self.tree.remove(&key).unwrap();
+            if inside_end > end {
+                leftover = Some((end.clone(), inside_end, value));
+            }
+        }
+
+        if let Some((start, end, value)) = leftover {
+            self.tree.insert(start, (end, value));
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.tree = PalmTree::new();
+    }
+}
+
+impl<K, V, C> Default for RangeMap<K, V, C>
+where
+    K: Clone + Ord,
+    C: TreeConfig<K, (K, V)>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, C> Clone for RangeMap<K, V, C>
+where
+    K: Clone + Ord,
+    V: Clone,
+    C: TreeConfig<K, (K, V)>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            tree: self.tree.clone(),
+        }
+    }
+}
+
+impl<K, V, C> PartialEq for RangeMap<K, V, C>
+where
+    K: Clone + Ord,
+    V: PartialEq,
+    C: TreeConfig<K, (K, V)>,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.tree == other.tree
+    }
+}
+
+impl<K, V, C> Debug for RangeMap<K, V, C>
+where
+    K: Clone + Ord + Debug,
+    V: Debug,
+    C: TreeConfig<K, (K, V)>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// A set of non-overlapping ranges of keys.
+///
+/// Ranges which overlap or touch are merged on insert, and removing a
+/// range out of the middle of another splits it in two.
+pub struct RangeSet<K, C>
+where
+    C: TreeConfig<K, (K, ())>,
+{
+    map: RangeMap<K, (), C>,
+}
+
+impl<K, C> RangeSet<K, C>
+where
+    K: Clone + Ord,
+    C: TreeConfig<K, (K, ())>,
+{
+    pub fn new() -> Self {
+        Self {
+            map: RangeMap::new(),
+        }
+    }
+
+    /// The number of separate ranges in the set.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Find the range containing `key`.
+    pub fn get_range(&self, key: &K) -> Option<Range<&K>> {
+        self.map.get_range(key).map(|(range, _)| range)
+    }
+
+    /// Iterate over the ranges in the set, in order.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = Range<&K>> + '_ {
+        self.map.iter().map(|(range, _)| range)
+    }
+
+    /// Iterate over the ranges in the set which overlap `range`, in order.
+    pub fn overlapping<'a>(
+        &'a self,
+        range: &'a Range<K>,
+    ) -> impl DoubleEndedIterator<Item = Range<&'a K>> + 'a {
+        self.map.overlapping(range).map(|(range, _)| range)
+    }
+
+    pub fn insert(&mut self, range: Range<K>) {
+        self.map.insert(range, ())
+    }
+
+    pub fn remove(&mut self, range: Range<K>) {
+        self.map.remove(range)
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear()
+    }
+}
+
+impl<K, C> Default for RangeSet<K, C>
+where
+    K: Clone + Ord,
+    C: TreeConfig<K, (K, ())>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, C> Clone for RangeSet<K, C>
+where
+    K: Clone + Ord,
+    C: TreeConfig<K, (K, ())>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            map: self.map.clone(),
+        }
+    }
+}
+
+impl<K, C> PartialEq for RangeSet<K, C>
+where
+    K: Clone + Ord,
+    C: TreeConfig<K, (K, ())>,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map
+    }
+}
+
+impl<K, C> Debug for RangeSet<K, C>
+where
+    K: Clone + Ord + Debug,
+    C: TreeConfig<K, (K, ())>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Tree64, Unique};
+
+    type Map = RangeMap<u32, u8, Tree64<Unique>>;
+
+    /// Check `map` against a plain array of what every key maps to.
+    fn check(map: &Map, model: &[Option<u8>]) {
+        for (key, expected) in model.iter().enumerate() {
+            assert_eq!(expected.as_ref(), map.get(&(key as u32)), "key {}", key);
+        }
+        // Every range is as long as it can be.
+        let ranges: Vec<_> = map.iter().collect();
+        for pair in ranges.windows(2) {
+            let ((left, left_value), (right, right_value)) = (&pair[0], &pair[1]);
+            assert!(left.start < left.end);
+            assert!(left.end <= right.start);
+            assert!(left.end < right.start || left_value != right_value);
+        }
+    }
+
+    #[test]
+    fn insert_and_remove_ranges() {
+        let mut map = Map::new();
+        let mut model = vec![None; 1000];
+        let mut seed = 1u32;
+        let mut random = |limit: u32| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (seed >> 8) % limit
+        };
+        for round in 0..2000 {
+            let start = random(1000);
+            let end = (start + random(50) + 1).min(1000);
+            if round % 3 == 0 {
+                map.remove(start..end);
+                for slot in &mut model[start as usize..end as usize] {
+                    *slot = None;
+                }
+            } else {
+                let value = random(3) as u8;
+                map.insert(start..end, value);
+                for slot in &mut model[start as usize..end as usize] {
+                    *slot = Some(value);
+                }
+            }
+            if round % 50 == 0 {
+                check(&map, &model);
+            }
+        }
+        check(&map, &model);
+    }
+
+    #[test]
+    fn overlapping_ranges() {
+        let mut set: RangeSet<u32, Tree64<Unique>> = RangeSet::new();
+        set.insert(0..10);
+        set.insert(20..30);
+        set.insert(10..15);
+        set.insert(40..50);
+        assert_eq!(3, set.len());
+        let found: Vec<_> = set
+            .overlapping(&(12..25))
+            .map(|r| *r.start..*r.end)
+            .collect();
+        assert_eq!(vec![0..15, 20..30], found);
+        let found: Vec<_> = set.overlapping(&(15..20)).collect();
+        assert!(found.is_empty());
+        let found: Vec<_> = set
+            .overlapping(&(45..100))
+            .map(|r| *r.start..*r.end)
+            .collect();
+        assert_eq!(vec![40..50], found);
+
+        set.remove(5..45);
+        let ranges: Vec<_> = set.iter().map(|r| *r.start..*r.end).collect();
+        assert_eq!(vec![0..5, 45..50], ranges);
+        assert!(set.contains(&4));
+        assert!(!set.contains(&5));
+        assert!(!set.contains(&50));
+    }
+}
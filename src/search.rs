@@ -1,19 +1,128 @@
 use crate::{arch::prefetch, branch::Branch, config::TreeConfig, leaf::Leaf};
-use arrayvec::ArrayVec;
-use std::{
+use core::{
+    borrow::Borrow,
     fmt::{Debug, Error, Formatter},
     marker::PhantomData,
 };
+use typenum::Unsigned;
 
-// type PtrPath<K, V, C> = Chunk<(*const Branch<K, V, C>, isize), U16>; // FIXME hardcoded max height of 16
-type PtrPath<K, V, C> = ArrayVec<[(*const Branch<K, V, C>, isize); 16]>;
+/// The deepest a path can go before a cursor runs out of room for it.
+///
+/// FIXME this should come from the config, but a stack sized from its
+/// `BranchSize` would need const generic expressions, so it's as deep as a
+/// tree with branches of 16, the smallest we allow, can get. A stack deep
+/// enough for branches of 4 would be three times the size.
+const MAX_HEIGHT: usize = max_height(16);
+
+/// The most levels of branches a tree with branches of `branch_size` can
+/// have.
+///
+/// The root has at least two children, and every branch below it is at
+/// least half full, so each level at least halves the number of leaves we
+/// need to go one deeper. We stop once that's more leaves than there are
+/// addresses for.
+const fn max_height(branch_size: usize) -> usize {
+    let mut height = 1;
+    let mut leaves: usize = 2;
+    while let Some(more) = leaves.checked_mul(branch_size / 2) {
+        leaves = more;
+        height += 1;
+    }
+    height
+}
+
+/// The branches on the path from the root down to a cursor's leaf, along
+/// with the index of the child we took at each of them.
+///
+/// The indices are kept apart from the pointers as `u16`s, rather than
+/// in pairs where each would be padded out to a pointer's width.
+struct PtrPath<K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    branches: [*const Branch<K, V, C>; MAX_HEIGHT],
+    indices: [u16; MAX_HEIGHT],
+    len: usize,
+}
+
+impl<K, V, C> Clone for PtrPath<K, V, C>
+where
+    C: TreeConfig<K, V>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            branches: self.branches,
+            indices: self.indices,
+            len: self.len,
+        }
+    }
+}
 
-pub(crate) fn find_key_linear<K>(keys: &[K], target: &K) -> Option<usize>
+impl<K, V, C> PtrPath<K, V, C>
 where
-    K: Ord,
+    C: TreeConfig<K, V>,
+{
+    /// A `u16` index reaches one past the last child of a branch, which
+    /// `push` allows, only if branches hold no more than `u16::MAX`
+    /// children. A config which breaks that fails to compile.
+    const INDICES_FIT: () = assert!(
+        C::BranchSize::USIZE <= u16::MAX as usize,
+        "PtrPath: BranchSize is too big for u16 indices"
+    );
+
+    /// A tree built with the config can't get deeper than `MAX_HEIGHT`,
+    /// so `push` never runs out of room, only if its branches are big
+    /// enough. A config which breaks that fails to compile.
+    const HEIGHT_FITS: () = assert!(
+        max_height(C::BranchSize::USIZE) <= MAX_HEIGHT,
+        "PtrPath: BranchSize is too small for MAX_HEIGHT"
+    );
+
+    fn new() -> Self {
+        let () = Self::INDICES_FIT;
+        let () = Self::HEIGHT_FITS;
+        Self {
+            branches: [core::ptr::null(); MAX_HEIGHT],
+            indices: [0; MAX_HEIGHT],
+            len: 0,
+        }
+    }
+
+    /// Push a branch onto the path, with `index` pointing at the child we took.
+    ///
+    /// `index` may also be one past the last child, for `step_back` to count down from.
+    fn push(&mut self, branch: *const Branch<K, V, C>, index: usize) {
+        assert!(self.len < MAX_HEIGHT, "PathedPointer: tree is too deep");
+        self.branches[self.len] = branch;
+        self.indices[self.len] = index as u16;
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<(*const Branch<K, V, C>, usize)> {
+        if self.len == 0 {
+            None
+        } else {
+            self.len -= 1;
+            Some((self.branches[self.len], self.indices[self.len] as usize))
+        }
+    }
+
+    /// Iterate over the path from the root down.
+    fn iter(&self) -> impl DoubleEndedIterator<Item = (*const Branch<K, V, C>, usize)> + '_ {
+        self.branches[..self.len]
+            .iter()
+            .zip(&self.indices[..self.len])
+            .map(|(branch, index)| (*branch, *index as usize))
+    }
+}
+
+pub(crate) fn find_key_linear<K, Q>(keys: &[K], target: &Q) -> Option<usize>
+where
+    K: Borrow<Q>,
+    Q: Ord + ?Sized,
 {
     for (index, key) in keys.iter().enumerate() {
-        if target <= key {
+        if target <= key.borrow() {
             return Some(index);
         }
     }
@@ -26,9 +135,10 @@ where
 ///
 /// This is a checked version of `find_key_or_next`. No assumption about
 /// the content of `keys` is needed, and it will never panic.
-pub(crate) fn find_key<K>(keys: &[K], key: &K) -> Option<usize>
+pub(crate) fn find_key<K, Q>(keys: &[K], key: &Q) -> Option<usize>
 where
-    K: Ord,
+    K: Borrow<Q>,
+    Q: Ord + ?Sized,
 {
     let size = keys.len();
     if size == 0 {
@@ -39,13 +149,13 @@ where
     let mut high = size - 1;
     while low != high {
         let mid = (low + high) / 2;
-        if unsafe { keys.get_unchecked(mid) } < key {
+        if unsafe { keys.get_unchecked(mid) }.borrow() < key {
             low = mid + 1;
         } else {
             high = mid;
         }
     }
-    if low == size || unsafe { keys.get_unchecked(low) } < key {
+    if low == size || unsafe { keys.get_unchecked(low) }.borrow() < key {
         None
     } else {
         Some(low)
@@ -61,16 +171,17 @@ where
 /// index of the highest value will be returned.
 ///
 /// If `keys` is empty, this function will panic.
-pub(crate) fn find_key_or_next<K>(keys: &[K], key: &K) -> usize
+pub(crate) fn find_key_or_next<K, Q>(keys: &[K], key: &Q) -> usize
 where
-    K: Ord,
+    K: Borrow<Q>,
+    Q: Ord + ?Sized,
 {
     let size = keys.len();
     let mut low = 0;
     let mut high = size - 1;
     while low != high {
         let mid = (low + high) / 2;
-        if unsafe { keys.get_unchecked(mid) } < key {
+        if unsafe { keys.get_unchecked(mid) }.borrow() < key {
             low = mid + 1;
         } else {
             high = mid;
@@ -82,16 +193,17 @@ where
 /// Find `key` in `keys`, or the closest lower value.
 ///
 /// Invariants as in `find_or_next` above apply, but reversed.
-pub(crate) fn find_key_or_prev<K>(keys: &[K], key: &K) -> usize
+pub(crate) fn find_key_or_prev<K, Q>(keys: &[K], key: &Q) -> usize
 where
-    K: Ord,
+    K: Borrow<Q>,
+    Q: Ord + ?Sized,
 {
     let size = keys.len();
     let mut low = 0;
     let mut high = size - 1;
     while low != high {
-        let mid = (low + high + 1) / 2;
-        if unsafe { keys.get_unchecked(mid) } > key {
+        let mid = high - (high - low) / 2;
+        if unsafe { keys.get_unchecked(mid) }.borrow() > key {
             high = mid - 1;
         } else {
             low = mid;
@@ -125,39 +237,52 @@ where
     }
 }
 
-fn walk_path<'a, K, V, C>(
+/// Walk down from `branch` to the leaf where `key` belongs.
+///
+/// Only the top level of the walk can fail to find a branch key at least
+/// as high as `key`, meaning `key` is higher than anything under `branch`.
+/// Below that, branch keys can be left higher than the highest key under
+/// them after a remove, so the parent's key can lead us into a child whose
+/// keys are all lower than `key`. In that case, `key` belongs at the end of
+/// the child's last node, which is where we go.
+fn walk_path<'a, K, V, C, Q>(
     mut branch: &'a Branch<K, V, C>,
-    key: &K,
+    key: &Q,
     path: &mut PtrPath<K, V, C>,
 ) -> Option<&'a Leaf<K, V, C>>
 where
-    K: Clone + Ord,
+    K: Clone + Ord + Borrow<Q>,
+    Q: Ord + ?Sized,
     C: TreeConfig<K, V>,
 {
+    let mut top = true;
     loop {
-        if let Some(index) = find_key(branch.keys(), key) {
-            path.push((branch, index as isize));
-            if branch.has_branches() {
-                branch = unsafe { branch.get_branch_unchecked(index) };
-            } else {
-                return Some(unsafe { branch.get_leaf_unchecked(index) });
-            }
+        let index = match find_key(branch.keys(), key) {
+            Some(index) => index,
+            None if top => return None,
+            None => branch.len() - 1,
+        };
+        top = false;
+        path.push(branch, index);
+        if branch.has_branches() {
+            branch = unsafe { branch.get_branch_unchecked(index) };
         } else {
-            return None;
+            return Some(unsafe { branch.get_leaf_unchecked(index) });
         }
     }
 }
 
 /// Find the path to the leaf which contains `key` or the closest higher key.
-fn path_for<'a, K, V, C>(
+fn path_for<'a, K, V, C, Q>(
     tree: &'a Branch<K, V, C>,
-    key: &K,
+    key: &Q,
 ) -> Option<(PtrPath<K, V, C>, &'a Leaf<K, V, C>)>
 where
-    K: Clone + Ord,
+    K: Clone + Ord + Borrow<Q>,
+    Q: Ord + ?Sized,
     C: TreeConfig<K, V>,
 {
-    let mut path: PtrPath<K, V, C> = ArrayVec::new();
+    let mut path = PtrPath::new();
     walk_path(tree, key, &mut path).map(|leaf| (path, leaf))
 }
 
@@ -168,19 +293,35 @@ where
 {
     pub(crate) fn null() -> Self {
         Self {
-            stack: ArrayVec::new(),
-            leaf: std::ptr::null(),
+            stack: PtrPath::new(),
+            leaf: core::ptr::null(),
             index: 0,
             lifetime: PhantomData,
         }
     }
 
+    /// The same pointer with a different lifetime marker, for handing out a
+    /// read only pointer from a mutable one.
+    pub(crate) fn cast<Lifetime2>(self) -> PathedPointer<Lifetime2, K, V, C> {
+        PathedPointer {
+            stack: self.stack,
+            leaf: self.leaf,
+            index: self.index,
+            lifetime: PhantomData,
+        }
+    }
+
     /// Find `key` and return `Ok(path)` for a key match or `Err(path)` for an absent key with
     /// the path to the leaf it should be in. This path will be null if the key is larger than
     /// the tree's current highest key.
-    pub(crate) fn exact_key(tree: &Branch<K, V, C>, key: &K) -> Result<Self, Self> {
+    #[allow(clippy::result_large_err)]
+    pub(crate) fn exact_key<Q>(tree: &Branch<K, V, C>, key: &Q) -> Result<Self, Self>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
         if let Some((stack, leaf)) = path_for(tree, key) {
-            match leaf.keys().binary_search(key) {
+            match leaf.keys().binary_search_by(|k| k.borrow().cmp(key)) {
                 Ok(index) => Ok(Self {
                     stack,
                     leaf,
@@ -200,7 +341,11 @@ where
     }
 
     /// Find `key` or the first higher key.
-    pub(crate) fn key_or_higher(tree: &Branch<K, V, C>, key: &K) -> Self {
+    pub(crate) fn key_or_higher<Q>(tree: &Branch<K, V, C>, key: &Q) -> Self
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
         let mut ptr = Self::null();
         if let Some((path, leaf)) = path_for(tree, key) {
             ptr.stack = path;
@@ -212,7 +357,7 @@ where
             // If we do, we can depend on the next neighbour node containing the right key as its first
             // entry.
             unsafe {
-                if ptr.key_unchecked() < key && !ptr.step_forward() {
+                if ptr.key_unchecked().borrow() < key && !ptr.step_forward() {
                     // If we can't step forward, we were at the highest key already, so the iterator is empty.
                     ptr = Self::null();
                 }
@@ -224,14 +369,20 @@ where
     }
 
     /// Find the first key higher than `key`.
-    pub(crate) fn higher_than_key(tree: &Branch<K, V, C>, key: &K) -> Self {
+    pub(crate) fn higher_than_key<Q>(tree: &Branch<K, V, C>, key: &Q) -> Self
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
         let mut ptr = Self::null();
         if let Some((path, leaf)) = path_for(tree, key) {
             ptr.stack = path;
             ptr.index = find_key_or_next(leaf.keys(), key);
             ptr.leaf = leaf;
+            // As with `key_or_higher`, a stale branch key can lead us to a leaf where every
+            // key is lower than `key`, so we step forward on those as well as on a match.
             unsafe {
-                if leaf.keys().get_unchecked(ptr.index) == key && !ptr.step_forward() {
+                if leaf.keys().get_unchecked(ptr.index).borrow() <= key && !ptr.step_forward() {
                     // If we can't step forward, we were at the highest key already, so the iterator is empty.
                     return Self::null();
                 }
@@ -243,12 +394,24 @@ where
     }
 
     /// Find `key` or the first lower key.
-    pub(crate) fn key_or_lower(tree: &Branch<K, V, C>, key: &K) -> Self {
+    pub(crate) fn key_or_lower<Q>(tree: &Branch<K, V, C>, key: &Q) -> Self
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
         if let Some((path, leaf)) = path_for(tree, key) {
             let mut ptr = Self::null();
             ptr.stack = path;
-            ptr.index = find_key_or_next(leaf.keys(), key);
+            ptr.index = find_key_or_prev(leaf.keys(), key);
             ptr.leaf = leaf;
+            // If every key in the leaf is higher than `key`, the one we want is the last key
+            // before the leaf, so we step back.
+            unsafe {
+                if ptr.key_unchecked().borrow() > key && !ptr.step_back() {
+                    // If we can't step back, we were at the lowest key already, so the iterator is empty.
+                    return Self::null();
+                }
+            }
             ptr
         } else {
             // No target node for end bound means it's past the largest key, so get a path to the end of the tree.
@@ -257,7 +420,11 @@ where
     }
 
     /// Find the first key lower than `key`.
-    pub(crate) fn lower_than_key(tree: &Branch<K, V, C>, key: &K) -> Self {
+    pub(crate) fn lower_than_key<Q>(tree: &Branch<K, V, C>, key: &Q) -> Self
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
         if let Some((path, leaf)) = path_for(tree, key) {
             let mut ptr = Self::null();
             ptr.stack = path;
@@ -266,7 +433,7 @@ where
             // If we've found a value equal to key, we step back one key.
             // If we've found a value higher than key, we're one branch ahead of the target key and step back.
             unsafe {
-                if leaf.keys().get_unchecked(ptr.index) >= key && !ptr.step_back() {
+                if leaf.keys().get_unchecked(ptr.index).borrow() >= key && !ptr.step_back() {
                     // If we can't step back, we were at the lowest key already, so the iterator is empty.
                     return Self::null();
                 }
@@ -286,7 +453,7 @@ where
             if branch.is_empty() {
                 return Self::null();
             }
-            stack.push((branch, 0));
+            stack.push(branch, 0);
             if branch.has_branches() {
                 branch = unsafe { branch.get_branch_unchecked(0) };
             } else {
@@ -309,7 +476,7 @@ where
                 return Self::null();
             }
             let index = branch.len() - 1;
-            stack.push((branch, index as isize));
+            stack.push(branch, index);
             if branch.has_branches() {
                 branch = unsafe { branch.get_branch_unchecked(index) };
             } else {
@@ -334,27 +501,26 @@ where
             if self.index >= (*self.leaf).keys().len() {
                 loop {
                     // Pop a branch off the top of the stack and examine it.
-                    if let Some((branch, mut index)) = self.stack.pop() {
+                    if let Some((mut branch, mut index)) = self.stack.pop() {
                         index += 1;
-                        if index < (*branch).len() as isize {
+                        if index < (*branch).len() {
                             // If we're not at the end yet, push the branch back on the stack and look at the next child.
-                            self.stack.push((branch, index));
-                            if (*branch).has_branches() {
-                                // If it's a branch, push it on the stack and go through the loop again with this branch.
-                                self.stack
-                                    .push(((*branch).get_branch_unchecked(index as usize), -1));
-                                continue;
-                            } else {
-                                // If it's a leaf, this is our new leaf, we're done.
-                                self.leaf = (*branch).get_leaf_unchecked(index as usize);
-                                self.index = 0;
-                                // Prefetch the next leaf.
-                                let next_index = (index + 1) as usize;
-                                if next_index < (*branch).len() {
-                                    prefetch((*branch).get_leaf_unchecked(next_index));
-                                }
-                                break;
+                            self.stack.push(branch, index);
+                            // If it's a branch, walk down its left edge to its first leaf.
+                            while (*branch).has_branches() {
+                                branch = (*branch).get_branch_unchecked(index);
+                                index = 0;
+                                self.stack.push(branch, index);
+                            }
+                            // This is our new leaf, we're done.
+                            self.leaf = (*branch).get_leaf_unchecked(index);
+                            self.index = 0;
+                            // Prefetch the next leaf.
+                            let next_index = index + 1;
+                            if next_index < (*branch).len() {
+                                prefetch((*branch).get_leaf_unchecked(next_index));
                             }
+                            break;
                         } else {
                             // If this branch is exhausted, go round the loop again to look at its parent.
                             continue;
@@ -383,19 +549,19 @@ where
                         if index > 0 {
                             index -= 1;
                             // If we're not at the end yet, push the branch back on the stack and look at the next child.
-                            self.stack.push((branch, index));
+                            self.stack.push(branch, index);
                             if (*branch).has_branches() {
-                                let child = (*branch).get_branch_unchecked(index as usize);
+                                let child = (*branch).get_branch_unchecked(index);
                                 // If it's a branch, push it on the stack and go through the loop again with this branch.
-                                self.stack.push((child, child.len() as isize));
+                                self.stack.push(child, child.len());
                                 continue;
                             } else {
                                 // If it's a leaf, this is our new leaf, we're done.
-                                self.leaf = (*branch).get_leaf_unchecked(index as usize);
+                                self.leaf = (*branch).get_leaf_unchecked(index);
                                 self.index = (*self.leaf).keys().len() - 1;
                                 // Prefetch the next leaf.
                                 if index > 0 {
-                                    prefetch((*branch).get_leaf_unchecked(index as usize - 1));
+                                    prefetch((*branch).get_leaf_unchecked(index - 1));
                                 }
                                 break;
                             }
@@ -417,20 +583,20 @@ where
     ///
     /// You're responsible for ensuring there is indeed an entry being pointed at.
     pub(crate) unsafe fn remove(mut self) -> (K, V) {
-        // TODO need a strategy for rebalancing after remove
+        // We only drop the nodes this leaves empty. Evening out nodes which are
+        // merely sparse would move entries out from under other cursors, so
+        // it's left to `PalmTree::rebalance_path` once we're done.
         let index = self.index;
         let leaf = self.deref_mut_leaf().unwrap();
         let (key, value) = leaf.remove_unchecked(index);
         if leaf.is_empty() {
             while let Some((branch, index)) = self.stack.pop() {
                 let branch = &mut *(branch as *mut Branch<K, V, C>);
-                let index = index as usize;
-                if branch.has_leaves() {
-                    branch.remove_leaf(index);
-                } else {
-                    branch.remove_branch(index);
-                }
+                branch.remove_empty_child(index);
                 if !branch.is_empty() {
+                    if index == branch.len() {
+                        self.lower_branch_keys(branch.highest());
+                    }
                     break;
                 }
             }
@@ -451,55 +617,32 @@ where
         V: Clone,
     {
         let index = self.index;
-        let leaf = self.deref_mut_leaf().unwrap();
+        let leaf = self.deref_leaf().unwrap();
         if !leaf.is_full() {
-            leaf.insert_unchecked(index, key, value);
+            if index == leaf.len() {
+                self.raise_branch_keys(&key);
+            }
+            self.deref_mut_leaf_unchecked()
+                .insert_unchecked(index, key, value);
             Ok(self)
         } else {
-            // Walk up the tree to find somewhere to split.
+            // Walk up the tree to find somewhere to split. We're appending to
+            // each node we pass for as long as we came up from its last child.
+            let mut append = index == leaf.len();
             loop {
                 if let Some((branch, index)) = self.stack.pop() {
                     let branch = &mut *(branch as *mut Branch<K, V, C>);
-                    let index = index as usize;
                     if !branch.is_full() {
-                        let choose_index = if branch.has_branches() {
-                            let (removed_key, removed_branch) = branch.remove_branch(index);
-                            let (left, right) = Branch::split(removed_branch);
-                            let left_highest = left.highest();
-                            let choose_index = if &key <= left_highest {
-                                index
-                            } else {
-                                index + 1
-                            };
-                            branch.insert_branch_pair(
-                                index,
-                                left_highest.clone(),
-                                left,
-                                removed_key,
-                                right,
-                            );
-                            choose_index
+                        branch.split_child(index, append);
+                        let choose_index = if &key <= branch.keys().get_unchecked(index) {
+                            index
                         } else {
-                            let (removed_key, removed_leaf) = branch.remove_leaf(index);
-                            let (left, right) = Leaf::split(removed_leaf);
-                            let left_highest = left.highest();
-                            let choose_index = if &key <= left_highest {
-                                index
-                            } else {
-                                index + 1
-                            };
-                            branch.insert_leaf_pair(
-                                index,
-                                left_highest.clone(),
-                                left,
-                                removed_key,
-                                right,
-                            );
-                            choose_index
+                            index + 1
                         };
                         // We're going to walk down either the left or the right hand branch of our split.
                         // We're guaranteed to find a leaf, but it might be full if we split a higher branch,
                         // so we might have to go back up and split further.
+                        self.stack.push(branch, choose_index);
                         let leaf = if branch.has_branches() {
                             walk_path(
                                 branch.get_branch_unchecked(choose_index),
@@ -523,6 +666,9 @@ where
                                     index,
                                     leaf.len()
                                 );
+                                if index == leaf.len() {
+                                    self.raise_branch_keys(&key);
+                                }
                                 let leaf = self.deref_mut_leaf_unchecked();
                                 leaf.insert_unchecked(index, key, value);
                                 return Ok(self);
@@ -530,6 +676,8 @@ where
                         } else {
                             unreachable!("walk_path() failed to produce a leaf, even though the leaf should be there!")
                         }
+                    } else {
+                        append &= index + 1 == branch.len();
                     }
                 } else {
                     return Err((key, value));
@@ -538,6 +686,37 @@ where
         }
     }
 
+    /// Raise the branch keys on the path to the pointer's leaf up to `key`,
+    /// which is about to become the leaf's highest key.
+    ///
+    /// We only ever need to do this when `walk_path` led us past a branch key
+    /// left stale by a remove. The keys above that are still high enough.
+    unsafe fn raise_branch_keys(&self, key: &K) {
+        for (branch, index) in self.stack.iter().rev() {
+            let branch = &mut *(branch as *mut Branch<K, V, C>);
+            let branch_key = branch.keys_mut().get_unchecked_mut(index);
+            if &*branch_key >= key {
+                break;
+            }
+            *branch_key = key.clone();
+        }
+    }
+
+    /// Bring the branch keys on the path down to `key`, the new highest key
+    /// of the branch at the bottom of the path after it lost its last child.
+    ///
+    /// We go on up for as long as we're coming from the last child, since
+    /// each branch's highest key has to match its parent's separator for it.
+    unsafe fn lower_branch_keys(&self, key: &K) {
+        for (branch, index) in self.stack.iter().rev() {
+            let branch = &mut *(branch as *mut Branch<K, V, C>);
+            *branch.keys_mut().get_unchecked_mut(index) = key.clone();
+            if index + 1 != branch.len() {
+                break;
+            }
+        }
+    }
+
     /// Insert a value at the right edge of the tree.
     /// If it returns false, you need to split the root and try again.
     ///
@@ -558,7 +737,7 @@ where
             index = branch.len() - 1;
             debug_assert!(branch.highest() < &key);
             branch.keys_mut()[index] = key.clone();
-            self.stack.push((branch, index as isize));
+            self.stack.push(branch, index);
             if branch.has_branches() {
                 branch = branch.get_branch_mut(index);
             } else {
@@ -571,36 +750,64 @@ where
     }
 
     pub(crate) fn clear(&mut self) {
-        self.leaf = std::ptr::null();
+        self.leaf = core::ptr::null();
     }
 
     pub(crate) fn is_null(&self) -> bool {
         self.leaf.is_null()
     }
 
-    pub(crate) unsafe fn deref_leaf_unchecked<'a>(&'a self) -> &'a Leaf<K, V, C> {
+    pub(crate) unsafe fn deref_leaf_unchecked(&self) -> &Leaf<K, V, C> {
         &*self.leaf
     }
 
-    pub(crate) unsafe fn deref_mut_leaf_unchecked<'a>(&'a mut self) -> &'a mut Leaf<K, V, C> {
+    pub(crate) unsafe fn deref_mut_leaf_unchecked(&mut self) -> &mut Leaf<K, V, C> {
         let ptr = self.leaf as *mut Leaf<K, V, C>;
         &mut *ptr
     }
 
-    pub(crate) unsafe fn deref_leaf<'a>(&'a self) -> Option<&'a Leaf<K, V, C>> {
+    pub(crate) unsafe fn deref_leaf(&self) -> Option<&Leaf<K, V, C>> {
         self.leaf.as_ref()
     }
 
-    pub(crate) unsafe fn deref_mut_leaf<'a>(&'a mut self) -> Option<&'a mut Leaf<K, V, C>> {
+    pub(crate) unsafe fn deref_mut_leaf(&mut self) -> Option<&mut Leaf<K, V, C>> {
         (self.leaf as *mut Leaf<K, V, C>).as_mut()
     }
 
-    pub(crate) unsafe fn into_entry_mut<'a>(self) -> (&'a mut K, &'a mut V) {
+    pub(crate) unsafe fn into_entry<'a>(self) -> (&'a K, &'a V) {
+        let leaf = &*self.leaf;
+        let key: *const K = leaf.keys().get_unchecked(self.index);
+        let value: *const V = leaf.values().get_unchecked(self.index);
+        (&*key, &*value)
+    }
+
+    /// Get the entry with the value mutable. The key never is, since
+    /// changing it could put it out of order; see `replace_key`.
+    pub(crate) unsafe fn into_entry_mut<'a>(self) -> (&'a K, &'a mut V) {
         let index = self.index;
         let leaf = &mut *(self.leaf as *mut Leaf<K, V, C>);
-        let key: *mut K = &mut leaf.keys_mut()[index];
+        let key: *const K = &leaf.keys()[index];
         let value: *mut V = &mut leaf.values_mut()[index];
-        (&mut *key, &mut *value)
+        (&*key, &mut *value)
+    }
+
+    /// Swap the key being pointed at for `key`, which must compare equal to it,
+    /// along with any copies of it stored as separators on the path down to it.
+    pub(crate) unsafe fn replace_key(&mut self, key: K) -> K {
+        for (branch, index) in self.stack.iter() {
+            let branch = &mut *(branch as *mut Branch<K, V, C>);
+            let separator = branch.keys_mut().get_unchecked_mut(index);
+            if *separator == key {
+                *separator = key.clone();
+            }
+        }
+        let index = self.index;
+        core::mem::replace(
+            self.deref_mut_leaf_unchecked()
+                .keys_mut()
+                .get_unchecked_mut(index),
+            key,
+        )
     }
 
     pub(crate) unsafe fn key(&self) -> Option<&K> {
@@ -638,6 +845,28 @@ mod test {
     use super::*;
     use std::iter::FromIterator;
 
+    #[test]
+    fn cursor_size() {
+        use crate::{Tree64, Unique};
+        // A pointer and an index for each level, plus the leaf pointer and
+        // index, with no padding between the pairs.
+        let size = std::mem::size_of::<PathedPointer<&(), usize, usize, Tree64<Unique>>>();
+        let word = std::mem::size_of::<usize>();
+        let indices = (2 * MAX_HEIGHT).div_ceil(word) * word;
+        assert_eq!(word * (MAX_HEIGHT + 3) + indices, size);
+    }
+
+    #[test]
+    fn heights() {
+        // Two leaves under the root and a doubling at every level below,
+        // up to the number of leaves which doesn't overflow.
+        assert_eq!(usize::BITS as usize - 1, max_height(4));
+        assert_eq!(max_height(4), max_height(5));
+        assert!(max_height(15) > MAX_HEIGHT);
+        #[cfg(target_pointer_width = "64")]
+        assert_eq!((21, 13), (MAX_HEIGHT, max_height(64)));
+    }
+
     #[test]
     fn test_find_key() {
         let keys: Vec<usize> = Vec::from_iter(vec![2, 4, 6, 8]);
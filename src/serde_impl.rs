@@ -0,0 +1,192 @@
+use crate::{config::TreeConfig, PalmSet, PalmTree, Patch};
+use alloc::vec::Vec;
+use core::{
+    fmt::{Formatter, Result as FmtResult},
+    marker::PhantomData,
+};
+use serde::{
+    de::{Deserialize, Deserializer, Error as DeError, MapAccess, SeqAccess, Visitor},
+    ser::{Serialize, Serializer},
+};
+
+/// Build a tree from entries as they come in.
+///
+/// Data we've serialised ourselves comes back in order, so we collect it
+/// for `load`, but if a key turns up out of order, we load what we've got
+/// and insert the rest one at a time, the last value for a key winning.
+fn build<K, V, C, E, F>(size_hint: Option<usize>, mut next: F) -> Result<PalmTree<K, V, C>, E>
+where
+    K: Ord + Clone,
+    V: Clone,
+    C: TreeConfig<K, V>,
+    F: FnMut() -> Result<Option<(K, V)>, E>,
+{
+    // Don't trust the hint too far, it comes from the input.
+    let mut entries: Vec<(K, V)> = Vec::with_capacity(size_hint.unwrap_or(0).min(4096));
+    while let Some((key, value)) = next()? {
+        if entries.last().is_some_and(|(last, _)| last >= &key) {
+            let mut tree = PalmTree::load(entries);
+            tree.insert(key, value);
+            while let Some((key, value)) = next()? {
+                tree.insert(key, value);
+            }
+            return Ok(tree);
+        }
+        entries.push((key, value));
+    }
+    Ok(PalmTree::load(entries))
+}
+
+impl<K, V, C> Serialize for PalmTree<K, V, C>
+where
+    K: Ord + Clone + Serialize,
+    V: Serialize,
+    C: TreeConfig<K, V>,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+struct TreeVisitor<K, V, C>(PhantomData<(K, V, C)>);
+
+impl<'de, K, V, C> Visitor<'de> for TreeVisitor<K, V, C>
+where
+    K: Ord + Clone + Deserialize<'de>,
+    V: Clone + Deserialize<'de>,
+    C: TreeConfig<K, V>,
+{
+    type Value = PalmTree<K, V, C>;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str("a map")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        build(access.size_hint(), || access.next_entry())
+    }
+}
+
+impl<'de, K, V, C> Deserialize<'de> for PalmTree<K, V, C>
+where
+    K: Ord + Clone + Deserialize<'de>,
+    V: Clone + Deserialize<'de>,
+    C: TreeConfig<K, V>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(TreeVisitor(PhantomData))
+    }
+}
+
+impl<T, C> Serialize for PalmSet<T, C>
+where
+    T: Ord + Clone + Serialize,
+    C: TreeConfig<T, ()>,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+struct SetVisitor<T, C>(PhantomData<(T, C)>);
+
+impl<'de, T, C> Visitor<'de> for SetVisitor<T, C>
+where
+    T: Ord + Clone + Deserialize<'de>,
+    C: TreeConfig<T, ()>,
+{
+    type Value = PalmSet<T, C>;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str("a sequence")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        let tree = build(access.size_hint(), || {
+            Ok(access.next_element()?.map(|value| (value, ())))
+        })?;
+        Ok(PalmSet::from(tree))
+    }
+}
+
+impl<'de, T, C> Deserialize<'de> for PalmSet<T, C>
+where
+    T: Ord + Clone + Deserialize<'de>,
+    C: TreeConfig<T, ()>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_seq(SetVisitor(PhantomData))
+    }
+}
+
+/// A patch goes out as a pair of its upserts and its removals, and has to
+/// pass the same checks as `Patch::from_parts` on the way back in.
+impl<K, V> Serialize for Patch<K, V>
+where
+    K: Serialize,
+    V: Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (self.upserts(), self.removals()).serialize(serializer)
+    }
+}
+
+impl<'de, K, V> Deserialize<'de> for Patch<K, V>
+where
+    K: Ord + Deserialize<'de>,
+    V: Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (upserts, removals) = <(Vec<(K, V)>, Vec<K>)>::deserialize(deserializer)?;
+        Patch::from_parts(upserts, removals).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Patch, StdPalmSet, StdPalmTree};
+
+    #[test]
+    fn round_trip() {
+        let tree: StdPalmTree<u32, String> = (0..10_000).map(|i| (i, i.to_string())).collect();
+        let json = serde_json::to_string(&tree).unwrap();
+        assert!(json.starts_with(r#"{"0":"0","1":"1","#));
+        let back: StdPalmTree<u32, String> = serde_json::from_str(&json).unwrap();
+        back.check_invariants().unwrap();
+        assert_eq!(tree, back);
+
+        // Out of order input, with a duplicate key, falls back to inserting.
+        let tree: StdPalmTree<u32, u32> =
+            serde_json::from_str(r#"{"1":0,"2":0,"5":0,"3":1,"1":2,"4":3}"#).unwrap();
+        tree.check_invariants().unwrap();
+        assert_eq!(
+            vec![(1, 2), (2, 0), (3, 1), (4, 3), (5, 0)],
+            tree.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>()
+        );
+
+        let set: StdPalmSet<u32> = (0..1000).map(|i| i * 3).collect();
+        let json = serde_json::to_string(&set).unwrap();
+        let back: StdPalmSet<u32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(set, back);
+        let set: StdPalmSet<u32> = serde_json::from_str("[3, 1, 2, 1]").unwrap();
+        assert_eq!(vec![1, 2, 3], set.iter().copied().collect::<Vec<_>>());
+        assert!(serde_json::from_str::<StdPalmSet<u32>>("{}").is_err());
+    }
+
+    #[test]
+    fn patch_round_trip() {
+        let old: StdPalmTree<u32, String> = (0..100).map(|i| (i, i.to_string())).collect();
+        let mut new = old.clone();
+        new.remove(&10);
+        new.insert(20, "twenty".to_string());
+        let patch = old.diff(&new);
+        let json = serde_json::to_string(&patch).unwrap();
+        assert_eq!(r#"[[[20,"twenty"]],[10]]"#, json);
+        let back: Patch<u32, String> = serde_json::from_str(&json).unwrap();
+        assert_eq!(patch, back);
+
+        let error = serde_json::from_str::<Patch<u32, u32>>("[[[1,0]],[1]]").unwrap_err();
+        assert!(error.to_string().contains("removal 0 is also upserted"));
+        assert!(serde_json::from_str::<Patch<u32, u32>>("[[[2,0],[1,0]],[]]").is_err());
+    }
+}
@@ -0,0 +1,702 @@
+use crate::{config::TreeConfig, CoWalk, Iter, Merged, OwnedIter, PalmTree};
+use alloc::collections::BTreeSet;
+use core::{
+    borrow::Borrow,
+    cmp::Ordering,
+    fmt::{Debug, Error, Formatter},
+    hash::{Hash, Hasher},
+    iter::{FromIterator, FusedIterator},
+    ops::{BitAnd, BitOr, BitXor, RangeBounds, Sub},
+};
+
+/// An ordered set, kept as the keys of a `PalmTree` with `()` values.
+///
+/// `()` takes no space, so the leaves hold nothing but keys, and the set
+/// packs as densely as a tree of keys alone would.
+///
+/// ```
+/// # use palmtree::{PalmSet, Tree64, Unique};
+/// let odd: PalmSet<u32, Tree64<Unique>> = (0..10).filter(|i| i % 2 == 1).collect();
+/// let low: PalmSet<u32, Tree64<Unique>> = (0..5).collect();
+/// assert_eq!(vec![1, 3], odd.intersection(&low).copied().collect::<Vec<_>>());
+/// assert_eq!(vec![5, 7, 9], odd.difference(&low).copied().collect::<Vec<_>>());
+/// assert_eq!(vec![0, 1, 2, 3, 4, 5, 7, 9], (&odd | &low).into_iter().collect::<Vec<_>>());
+/// ```
+pub struct PalmSet<T, C>
+where
+    C: TreeConfig<T, ()>,
+{
+    tree: PalmTree<T, (), C>,
+}
+
+impl<T, C> PalmSet<T, C>
+where
+    T: Clone + Ord,
+    C: TreeConfig<T, ()>,
+{
+    pub fn new() -> Self {
+        Self {
+            tree: PalmTree::new(),
+        }
+    }
+
+    /// Construct a set efficiently from an iterator of values in ascending
+    /// order with no duplicates, as with `PalmTree::load`.
+    pub fn load<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+    {
+        Self {
+            tree: PalmTree::load(iter.into_iter().map(|value| (value, ()))),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.tree.contains_key(value)
+    }
+
+    /// Add `value` to the set, returning whether it wasn't there already.
+    ///
+    /// If it was, the value in the set is left as it was, like
+    /// `BTreeSet::insert`.
+    pub fn insert(&mut self, value: T) -> bool {
+        if self.tree.contains_key(&value) {
+            false
+        } else {
+            self.tree.insert(value, ());
+            true
+        }
+    }
+
+    /// Remove `value` from the set, returning whether it was there.
+    pub fn remove<Q>(&mut self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.tree.remove(value).is_some()
+    }
+
+    /// Remove `value` from the set and hand back the one which was in it.
+    pub fn take<Q>(&mut self, value: &Q) -> Option<T>
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.tree.remove(value).map(|(value, _)| value)
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.tree.first_key_value().map(|(value, _)| value)
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.tree.last_key_value().map(|(value, _)| value)
+    }
+
+    pub fn pop_first(&mut self) -> Option<T> {
+        self.tree.remove_lowest().map(|(value, _)| value)
+    }
+
+    pub fn pop_last(&mut self) -> Option<T> {
+        self.tree.remove_highest().map(|(value, _)| value)
+    }
+
+    pub fn iter(&self) -> SetIter<'_, T, C> {
+        SetIter {
+            iter: self.tree.iter(),
+        }
+    }
+
+    pub fn range<Q, R>(&self, range: R) -> SetIter<'_, T, C>
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        SetIter {
+            iter: self.tree.range(range),
+        }
+    }
+
+    /// Iterate over the values in either set, in order.
+    pub fn union<'a>(&'a self, other: &'a Self) -> Union<'a, T, C> {
+        Union {
+            walk: walk(self, other),
+        }
+    }
+
+    /// Iterate over the values in both sets, in order.
+    pub fn intersection<'a>(&'a self, other: &'a Self) -> Intersection<'a, T, C> {
+        Intersection {
+            walk: walk(self, other),
+        }
+    }
+
+    /// Iterate over the values in this set but not in `other`, in order.
+    pub fn difference<'a>(&'a self, other: &'a Self) -> Difference<'a, T, C> {
+        Difference {
+            walk: walk(self, other),
+        }
+    }
+
+    /// Iterate over the values in one set or the other but not both, in
+    /// order.
+    pub fn symmetric_difference<'a>(&'a self, other: &'a Self) -> SymmetricDifference<'a, T, C> {
+        SymmetricDifference {
+            walk: walk(self, other),
+        }
+    }
+
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        self.intersection(other).next().is_none()
+    }
+
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.len() <= other.len() && self.difference(other).next().is_none()
+    }
+
+    pub fn is_superset(&self, other: &Self) -> bool {
+        other.is_subset(self)
+    }
+
+    /// The tree underneath.
+    pub fn as_tree(&self) -> &PalmTree<T, (), C> {
+        &self.tree
+    }
+
+    pub fn into_tree(self) -> PalmTree<T, (), C> {
+        self.tree
+    }
+
+    pub fn clear(&mut self) {
+        self.tree = PalmTree::new();
+    }
+}
+
+/// An iterator over the values of a `PalmSet`.
+pub struct SetIter<'a, T, C>
+where
+    C: TreeConfig<T, ()>,
+{
+    iter: Iter<'a, T, (), C>,
+}
+
+impl<'a, T, C> Clone for SetIter<'a, T, C>
+where
+    T: Clone + Ord,
+    C: 'a + TreeConfig<T, ()>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            iter: self.iter.clone(),
+        }
+    }
+}
+
+impl<'a, T, C> Iterator for SetIter<'a, T, C>
+where
+    T: Clone + Ord,
+    C: 'a + TreeConfig<T, ()>,
+{
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(value, _)| value)
+    }
+}
+
+impl<'a, T, C> DoubleEndedIterator for SetIter<'a, T, C>
+where
+    T: Clone + Ord,
+    C: 'a + TreeConfig<T, ()>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back().map(|(value, _)| value)
+    }
+}
+
+impl<'a, T, C> FusedIterator for SetIter<'a, T, C>
+where
+    T: Clone + Ord,
+    C: 'a + TreeConfig<T, ()>,
+{
+}
+
+/// A consuming iterator over the values of a `PalmSet`.
+pub struct SetIntoIter<T, C>
+where
+    C: TreeConfig<T, ()>,
+{
+    iter: OwnedIter<T, (), C>,
+}
+
+impl<T, C> Iterator for SetIntoIter<T, C>
+where
+    T: Clone + Ord,
+    C: TreeConfig<T, ()>,
+{
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(value, _)| value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<T, C> DoubleEndedIterator for SetIntoIter<T, C>
+where
+    T: Clone + Ord,
+    C: TreeConfig<T, ()>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back().map(|(value, _)| value)
+    }
+}
+
+impl<T, C> ExactSizeIterator for SetIntoIter<T, C>
+where
+    T: Clone + Ord,
+    C: TreeConfig<T, ()>,
+{
+}
+
+impl<T, C> FusedIterator for SetIntoIter<T, C>
+where
+    T: Clone + Ord,
+    C: TreeConfig<T, ()>,
+{
+}
+
+/// The ordered walk over two sets which each of the set operations below
+/// picks its values from.
+type SetWalk<'a, T, C> =
+    CoWalk<SetIter<'a, T, C>, SetIter<'a, T, C>, fn(&&'a T, &&'a T) -> Ordering>;
+
+fn walk<'a, T, C>(left: &'a PalmSet<T, C>, right: &'a PalmSet<T, C>) -> SetWalk<'a, T, C>
+where
+    T: Clone + Ord,
+    C: 'a + TreeConfig<T, ()>,
+{
+    CoWalk::new(left.iter(), right.iter(), |left, right| left.cmp(right))
+}
+
+/// An iterator over the union of two `PalmSet`s. See `PalmSet::union`.
+pub struct Union<'a, T, C>
+where
+    T: Clone + Ord,
+    C: 'a + TreeConfig<T, ()>,
+{
+    walk: SetWalk<'a, T, C>,
+}
+
+impl<'a, T, C> Iterator for Union<'a, T, C>
+where
+    T: Clone + Ord,
+    C: 'a + TreeConfig<T, ()>,
+{
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.walk.next()? {
+            Merged::Left(value) | Merged::Right(value) | Merged::Both(value, _) => Some(value),
+        }
+    }
+}
+
+/// An iterator over the intersection of two `PalmSet`s. See
+/// `PalmSet::intersection`.
+pub struct Intersection<'a, T, C>
+where
+    T: Clone + Ord,
+    C: 'a + TreeConfig<T, ()>,
+{
+    walk: SetWalk<'a, T, C>,
+}
+
+impl<'a, T, C> Iterator for Intersection<'a, T, C>
+where
+    T: Clone + Ord,
+    C: 'a + TreeConfig<T, ()>,
+{
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.walk.find_map(|merged| match merged {
+            Merged::Both(value, _) => Some(value),
+            _ => None,
+        })
+    }
+}
+
+/// An iterator over the values in one `PalmSet` but not another. See
+/// `PalmSet::difference`.
+pub struct Difference<'a, T, C>
+where
+    T: Clone + Ord,
+    C: 'a + TreeConfig<T, ()>,
+{
+    walk: SetWalk<'a, T, C>,
+}
+
+impl<'a, T, C> Iterator for Difference<'a, T, C>
+where
+    T: Clone + Ord,
+    C: 'a + TreeConfig<T, ()>,
+{
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.walk.find_map(|merged| match merged {
+            Merged::Left(value) => Some(value),
+            _ => None,
+        })
+    }
+}
+
+/// An iterator over the values in exactly one of two `PalmSet`s. See
+/// `PalmSet::symmetric_difference`.
+pub struct SymmetricDifference<'a, T, C>
+where
+    T: Clone + Ord,
+    C: 'a + TreeConfig<T, ()>,
+{
+    walk: SetWalk<'a, T, C>,
+}
+
+impl<'a, T, C> Iterator for SymmetricDifference<'a, T, C>
+where
+    T: Clone + Ord,
+    C: 'a + TreeConfig<T, ()>,
+{
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.walk.find_map(|merged| match merged {
+            Merged::Left(value) | Merged::Right(value) => Some(value),
+            Merged::Both(..) => None,
+        })
+    }
+}
+
+macro_rules! impl_set_iter_traits {
+    ($($name:ident),*) => {
+        $(
+            impl<'a, T, C> FusedIterator for $name<'a, T, C>
+            where
+                T: Clone + Ord,
+                C: 'a + TreeConfig<T, ()>,
+            {
+            }
+
+            impl<'a, T, C> Debug for $name<'a, T, C>
+            where
+                T: Clone + Ord,
+                C: 'a + TreeConfig<T, ()>,
+            {
+                fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+                    write!(f, "{}", stringify!($name))
+                }
+            }
+        )*
+    };
+}
+
+impl_set_iter_traits!(Union, Intersection, Difference, SymmetricDifference);
+
+impl<'a, T, C> Debug for SetIter<'a, T, C>
+where
+    T: Clone + Ord + Debug,
+    C: 'a + TreeConfig<T, ()>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl<T, C> Debug for SetIntoIter<T, C>
+where
+    C: TreeConfig<T, ()>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "SetIntoIter")
+    }
+}
+
+impl<T, C> Default for PalmSet<T, C>
+where
+    T: Clone + Ord,
+    C: TreeConfig<T, ()>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, C> Clone for PalmSet<T, C>
+where
+    T: Clone + Ord,
+    C: TreeConfig<T, ()>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            tree: self.tree.clone(),
+        }
+    }
+}
+
+impl<T, C> PartialEq for PalmSet<T, C>
+where
+    T: Clone + Ord,
+    C: TreeConfig<T, ()>,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.tree == other.tree
+    }
+}
+
+impl<T, C> Eq for PalmSet<T, C>
+where
+    T: Clone + Ord,
+    C: TreeConfig<T, ()>,
+{
+}
+
+impl<T, C> PartialOrd for PalmSet<T, C>
+where
+    T: Clone + Ord,
+    C: TreeConfig<T, ()>,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T, C> Ord for PalmSet<T, C>
+where
+    T: Clone + Ord,
+    C: TreeConfig<T, ()>,
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+impl<T, C> Hash for PalmSet<T, C>
+where
+    T: Clone + Ord + Hash,
+    C: TreeConfig<T, ()>,
+{
+    fn hash<H>(&self, state: &mut H)
+    where
+        H: Hasher,
+    {
+        for value in self {
+            value.hash(state);
+        }
+    }
+}
+
+impl<T, C> Debug for PalmSet<T, C>
+where
+    T: Clone + Ord + Debug,
+    C: TreeConfig<T, ()>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T, C> FromIterator<T> for PalmSet<T, C>
+where
+    T: Clone + Ord,
+    C: TreeConfig<T, ()>,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self {
+            tree: iter.into_iter().map(|value| (value, ())).collect(),
+        }
+    }
+}
+
+impl<T, C> Extend<T> for PalmSet<T, C>
+where
+    T: Clone + Ord,
+    C: TreeConfig<T, ()>,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+impl<'a, T, C> Extend<&'a T> for PalmSet<T, C>
+where
+    T: 'a + Copy + Ord,
+    C: 'a + TreeConfig<T, ()>,
+{
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+impl<'a, T, C> IntoIterator for &'a PalmSet<T, C>
+where
+    T: Clone + Ord,
+    C: 'a + TreeConfig<T, ()>,
+{
+    type Item = &'a T;
+    type IntoIter = SetIter<'a, T, C>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T, C> IntoIterator for PalmSet<T, C>
+where
+    T: Clone + Ord,
+    C: TreeConfig<T, ()>,
+{
+    type Item = T;
+    type IntoIter = SetIntoIter<T, C>;
+    fn into_iter(self) -> Self::IntoIter {
+        SetIntoIter {
+            iter: self.tree.into_iter(),
+        }
+    }
+}
+
+impl<T, C> From<BTreeSet<T>> for PalmSet<T, C>
+where
+    T: Clone + Ord,
+    C: TreeConfig<T, ()>,
+{
+    fn from(set: BTreeSet<T>) -> Self {
+        Self::load(set)
+    }
+}
+
+impl<T, C> From<PalmTree<T, (), C>> for PalmSet<T, C>
+where
+    C: TreeConfig<T, ()>,
+{
+    fn from(tree: PalmTree<T, (), C>) -> Self {
+        Self { tree }
+    }
+}
+
+// The set operators build a new set by loading the values from the
+// matching iterator, which come out in order with no duplicates.
+macro_rules! impl_set_op {
+    ($trait:ident, $op:ident, $iter:ident) => {
+        impl<T, C> $trait<&PalmSet<T, C>> for &PalmSet<T, C>
+        where
+            T: Clone + Ord,
+            C: TreeConfig<T, ()>,
+        {
+            type Output = PalmSet<T, C>;
+            fn $op(self, other: &PalmSet<T, C>) -> Self::Output {
+                PalmSet::load(self.$iter(other).cloned())
+            }
+        }
+    };
+}
+
+impl_set_op!(BitOr, bitor, union);
+impl_set_op!(BitAnd, bitand, intersection);
+impl_set_op!(Sub, sub, difference);
+impl_set_op!(BitXor, bitxor, symmetric_difference);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Tree64, Unique};
+
+    type Set = PalmSet<u32, Tree64<Unique>>;
+
+    #[test]
+    fn set_operations() {
+        let threes: Set = (0..10_000).filter(|i| i % 3 == 0).collect();
+        let fives = Set::load((0..10_000).filter(|i| i % 5 == 0));
+        let expect = |f: fn(bool, bool) -> bool| -> Vec<u32> {
+            (0..10_000).filter(|i| f(i % 3 == 0, i % 5 == 0)).collect()
+        };
+        let collect = |iter: &mut dyn Iterator<Item = &u32>| iter.copied().collect::<Vec<_>>();
+
+        assert_eq!(expect(|a, b| a || b), collect(&mut threes.union(&fives)));
+        assert_eq!(
+            expect(|a, b| a && b),
+            collect(&mut threes.intersection(&fives))
+        );
+        assert_eq!(
+            expect(|a, b| a && !b),
+            collect(&mut threes.difference(&fives))
+        );
+        assert_eq!(
+            expect(|a, b| a != b),
+            collect(&mut threes.symmetric_difference(&fives))
+        );
+        assert_eq!(
+            expect(|a, b| a || b),
+            (&threes | &fives).into_iter().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            threes.intersection(&fives).count(),
+            (&threes & &fives).len()
+        );
+        assert_eq!(threes.difference(&fives).count(), (&threes - &fives).len());
+        assert_eq!(
+            threes.symmetric_difference(&fives).count(),
+            (&threes ^ &fives).len()
+        );
+
+        let fifteens = &threes & &fives;
+        assert!(fifteens.is_subset(&threes));
+        assert!(threes.is_superset(&fifteens));
+        assert!(!threes.is_subset(&fives));
+        assert!((&threes - &fives).is_disjoint(&fives));
+        assert!(!threes.is_disjoint(&fives));
+    }
+
+    #[test]
+    fn insert_and_remove() {
+        let mut set = Set::new();
+        assert!(set.insert(5));
+        assert!(set.insert(1));
+        assert!(!set.insert(5));
+        assert!(set.contains(&1));
+        assert_eq!(Some(&1), set.first());
+        assert_eq!(Some(&5), set.last());
+        assert!(set.remove(&1));
+        assert!(!set.remove(&1));
+        assert_eq!(None, set.take(&1));
+        assert_eq!(Some(5), set.take(&5));
+        assert!(set.is_empty());
+
+        set.extend(0..1000);
+        assert_eq!(
+            vec![10, 11, 12],
+            set.range(10..13).copied().collect::<Vec<_>>()
+        );
+        assert_eq!(Some(0), set.pop_first());
+        assert_eq!(Some(999), set.pop_last());
+        assert_eq!(998, set.len());
+        assert_eq!(set, Set::from((1..999).collect::<BTreeSet<_>>()));
+        assert!(set < Set::load(vec![2]));
+
+        let mut names: PalmSet<String, Tree64<Unique>> = PalmSet::new();
+        names.insert("cat".to_string());
+        assert!(names.contains("cat"));
+        assert!(names.remove("cat"));
+    }
+}
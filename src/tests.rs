@@ -1,17 +1,24 @@
+#![allow(non_local_definitions)] // proptest-derive generates these
+
 use std::collections::BTreeMap;
 use std::fmt::Debug;
 use std::iter::FromIterator;
+use std::ops::Bound;
 
-use crate::{config::TreeConfig, PalmTree};
+use crate::{config::TreeConfig, Entry, PalmTree};
 
-#[cfg(not(test))]
+#[cfg(all(not(test), feature = "test"))]
 use arbitrary::Arbitrary;
 #[cfg(test)]
 use proptest::proptest;
 #[cfg(test)]
 use proptest_derive::Arbitrary;
 
-#[derive(Arbitrary, Debug)]
+#[cfg(any(test, feature = "bench-util"))]
+pub mod workload;
+
+#[derive(Debug)]
+#[cfg_attr(any(test, feature = "test"), derive(Arbitrary))]
 pub enum Construct<K, V>
 where
     K: Ord,
@@ -22,17 +29,44 @@ where
     Load(BTreeMap<K, V>),
 }
 
-#[derive(Arbitrary, Debug)]
-pub enum Action<K, V> {
+#[derive(Debug)]
+#[cfg_attr(any(test, feature = "test"), derive(Arbitrary))]
+pub enum Action<K, V>
+where
+    K: Ord,
+{
     Insert(K, V),
     Lookup(K),
     Remove(K),
     Range(Option<K>, Option<K>),
     RangeMut(Option<K>, Option<K>),
+    EntryOrInsert(K, V),
+    EntryAndModify(K, V),
+    EntryRemove(K),
+    PopFirst,
+    PopLast,
+    SplitOff(K),
+    Append(BTreeMap<K, V>),
+    /// Put a cursor at the lowest key from the given one up, and step it
+    /// forward for `true` and back for `false`.
+    CursorMoves(Option<K>, Vec<bool>),
+    CursorRemove(K),
 }
 
 pub type Input<K, V> = (Construct<K, V>, Vec<Action<K, V>>);
 
+/// Scale down the size of a test which makes a lot of changes one at a
+/// time. The `validate` feature checks the whole tree after every change,
+/// which makes those tests quadratic, so they run on smaller trees under it.
+#[cfg(test)]
+pub(crate) const fn scaled(size: usize) -> usize {
+    if cfg!(feature = "validate") {
+        size / 16
+    } else {
+        size
+    }
+}
+
 pub fn integration_test<C>(input: Input<u8, u8>)
 where
     C: TreeConfig<u8, u8>,
@@ -49,7 +83,7 @@ where
         }
         Construct::FromIter(map) => {
             nat = map.clone();
-            set = PalmTree::from_iter(map.into_iter());
+            set = PalmTree::from_iter(map);
         }
         Construct::Insert(map) => {
             nat = map.clone();
@@ -60,14 +94,17 @@ where
         }
         Construct::Load(map) => {
             nat = map.clone();
-            set = PalmTree::load(map.into_iter());
+            set = PalmTree::load(map);
         }
     }
+    if let Err(error) = set.check_invariants() {
+        panic!("broken invariant after construction: {}", error);
+    }
 
     for action in actions {
         match action {
             Action::Insert(key, value) => {
-                let len = nat.len() + if nat.get(&key).is_some() { 0 } else { 1 };
+                let len = nat.len() + if nat.contains_key(&key) { 0 } else { 1 };
                 nat.insert(key, value);
                 set.insert(key, value);
                 assert_eq!(len, set.len());
@@ -77,7 +114,7 @@ where
                 assert_eq!(nat.get(&key), set.get(&key));
             }
             Action::Remove(key) => {
-                let len = nat.len() - if nat.get(&key).is_some() { 1 } else { 0 };
+                let len = nat.len() - if nat.contains_key(&key) { 1 } else { 0 };
                 let removed_from_nat = nat.remove(&key);
                 if let Some((removed_key, removed_value)) = set.remove(&key) {
                     assert_eq!(removed_key, key);
@@ -142,11 +179,104 @@ where
                 let actual: Vec<_> = set_iter.map(|(k, v)| (*k, *v)).collect();
                 assert_eq!(expected, actual);
             }
+            Action::EntryOrInsert(key, value) => {
+                let expected = *nat.entry(key).or_insert(value);
+                assert_eq!(expected, *set.entry(key).or_insert(value));
+            }
+            Action::EntryAndModify(key, value) => {
+                let expected = *nat
+                    .entry(key)
+                    .and_modify(|old| *old = old.wrapping_add(value))
+                    .or_insert(value);
+                let actual = *set
+                    .entry(key)
+                    .and_modify(|old| *old = old.wrapping_add(value))
+                    .or_insert(value);
+                assert_eq!(expected, actual);
+            }
+            Action::EntryRemove(key) => {
+                let expected = match nat.entry(key) {
+                    std::collections::btree_map::Entry::Occupied(entry) => {
+                        Some(entry.remove_entry())
+                    }
+                    std::collections::btree_map::Entry::Vacant(_) => None,
+                };
+                let actual = match set.entry(key) {
+                    Entry::Occupied(entry) => Some(entry.remove_entry()),
+                    Entry::Vacant(_) => None,
+                };
+                assert_eq!(expected, actual);
+            }
+            Action::PopFirst => {
+                assert_eq!(nat.pop_first(), set.pop_first());
+            }
+            Action::PopLast => {
+                assert_eq!(nat.pop_last(), set.pop_last());
+            }
+            Action::SplitOff(key) => {
+                let mut nat_high = nat.split_off(&key);
+                let set_high = set.split_off(&key);
+                if let Err(error) = set.check_invariants() {
+                    panic!("broken invariant in the low half of a split: {}", error);
+                }
+                if let Err(error) = set_high.check_invariants() {
+                    panic!("broken invariant in the high half of a split: {}", error);
+                }
+                assert_eq!(nat.len(), set.len());
+                assert_eq!(nat_high.len(), set_high.len());
+                assert!(nat_high.iter().eq(set_high.iter()));
+                nat.append(&mut nat_high);
+                set.append_right(set_high);
+            }
+            Action::Append(map) => {
+                set.append_right(PalmTree::load(map.clone()));
+                nat.extend(map);
+            }
+            Action::CursorMoves(key, moves) => {
+                let keys: Vec<u8> = nat.keys().copied().collect();
+                let bound = key.as_ref().map_or(Bound::Unbounded, Bound::Included);
+                let mut index = keys.iter().position(|k| key.is_none_or(|key| *k >= key));
+                let mut cursor = set.lower_bound(bound);
+                for forward in moves {
+                    assert_eq!(index.map(|index| keys[index]), cursor.key().copied());
+                    if forward {
+                        cursor.move_next();
+                        index = match index {
+                            None if keys.is_empty() => None,
+                            None => Some(0),
+                            Some(index) if index + 1 == keys.len() => None,
+                            Some(index) => Some(index + 1),
+                        };
+                    } else {
+                        cursor.move_prev();
+                        index = match index {
+                            None => keys.len().checked_sub(1),
+                            Some(0) => None,
+                            Some(index) => Some(index - 1),
+                        };
+                    }
+                }
+                assert_eq!(index.map(|index| keys[index]), cursor.key().copied());
+            }
+            Action::CursorRemove(key) => {
+                let expected = nat.range(key..).next().map(|(k, v)| (*k, *v));
+                if let Some((key, _)) = expected {
+                    nat.remove(&key);
+                }
+                let mut cursor = set.lower_bound_mut(Bound::Included(&key));
+                assert_eq!(expected, cursor.remove_current());
+                let next = nat.range(key..).next().map(|(k, _)| *k);
+                assert_eq!(next, cursor.key().copied());
+            }
         }
 
         // Check len()
         assert_eq!(nat.len(), set.len());
 
+        if let Err(error) = set.check_invariants() {
+            panic!("broken invariant: {}", error);
+        }
+
         // Immutable ref iterator
         let expected: Vec<_> = nat.iter().map(|(k, v)| (*k, *v)).collect();
         let actual: Vec<_> = set.iter().map(|(k, v)| (*k, *v)).collect();
@@ -171,4 +301,18 @@ proptest! {
         use crate::{config::Tree64, pointer::Unique};
         integration_test::<Tree64<Unique>>(input);
     }
+
+    // Leaves this small make every few removes merge something, and put
+    // enough levels above them for branches to merge too.
+    #[test]
+    fn integration_proptest_small_nodes(input: Input<u8,u8>) {
+        use crate::pointer::Unique;
+        struct SmallNodes;
+        impl<K, V> TreeConfig<K, V> for SmallNodes {
+            type BranchSize = typenum::U16;
+            type LeafSize = typenum::U4;
+            type PointerKind = Unique;
+        }
+        integration_test::<SmallNodes>(input);
+    }
 }
@@ -0,0 +1,349 @@
+//! Generators for realistic streams of `Action`s, for benchmarks and load
+//! tests to share instead of each writing their own loops.
+//!
+//! A `Workload` is an endless iterator: take as many actions as you need
+//! from it. It's seeded, so the same arguments always produce the same
+//! actions.
+//!
+//! ```
+//! # use palmtree::tests::{workload::{Keys, Mix, Workload}, Action};
+//! let actions: Vec<_> = Workload::new(Keys::Zipfian(0.99), 1_000_000, Mix::READ_HEAVY, 1)
+//!     .take(10_000)
+//!     .collect();
+//! let reads = actions.iter().filter(|action| matches!(action, Action::Lookup(_))).count();
+//! assert!(reads > 9000);
+//! ```
+
+use super::Action;
+
+/// How a `Workload` picks the keys it touches out of its key space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Keys {
+    /// Inserts go in ascending order from zero, and everything else picks
+    /// uniformly from the keys inserted so far, like a log or a time series.
+    Sequential,
+    /// Every key is equally likely.
+    Uniform,
+    /// A few keys are much more likely than the rest, with the skew given
+    /// by the exponent, which must be positive and not 1. YCSB uses 0.99.
+    /// Key 0 is the most popular, then key 1, and so on.
+    Zipfian(f64),
+    /// Keys fall uniformly within `spread` of one of `clusters` randomly
+    /// placed starting points.
+    Clustered { clusters: usize, spread: u64 },
+}
+
+/// The relative weights of each kind of action in a `Workload`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Mix {
+    pub insert: u32,
+    pub lookup: u32,
+    pub remove: u32,
+    pub range: u32,
+    /// How many keys each range action spans.
+    pub range_len: u64,
+}
+
+impl Mix {
+    pub const WRITE_ONLY: Mix = Mix {
+        insert: 1,
+        lookup: 0,
+        remove: 0,
+        range: 0,
+        range_len: 0,
+    };
+
+    /// YCSB workload B: 95% lookups, 5% inserts.
+    pub const READ_HEAVY: Mix = Mix {
+        insert: 5,
+        lookup: 95,
+        remove: 0,
+        range: 0,
+        range_len: 0,
+    };
+
+    /// YCSB workload A: half lookups, half inserts.
+    pub const BALANCED: Mix = Mix {
+        insert: 50,
+        lookup: 50,
+        remove: 0,
+        range: 0,
+        range_len: 0,
+    };
+
+    /// YCSB workload E: 95% short scans, 5% inserts.
+    pub const SCAN_HEAVY: Mix = Mix {
+        insert: 5,
+        lookup: 0,
+        remove: 0,
+        range: 95,
+        range_len: 100,
+    };
+
+    /// Inserts and removes in equal measure, with some lookups, for a
+    /// tree which stays about the same size while its contents churn.
+    pub const CHURN: Mix = Mix {
+        insert: 40,
+        lookup: 20,
+        remove: 40,
+        range: 0,
+        range_len: 0,
+    };
+
+    fn total(&self) -> u64 {
+        self.insert as u64 + self.lookup as u64 + self.remove as u64 + self.range as u64
+    }
+}
+
+/// An endless stream of `Action`s over keys in `0..key_space`, drawn from
+/// a `Keys` distribution in the proportions given by a `Mix`. Inserts use
+/// the key as the value.
+#[derive(Clone, Debug)]
+pub struct Workload {
+    keys: Keys,
+    key_space: u64,
+    mix: Mix,
+    rng: SplitMix,
+    next_key: u64,
+    zipf: Option<Zipf>,
+    clusters: Vec<u64>,
+}
+
+impl Workload {
+    pub fn new(keys: Keys, key_space: u64, mix: Mix, seed: u64) -> Self {
+        assert!(key_space > 0, "Workload::new: empty key space");
+        assert!(mix.total() > 0, "Workload::new: all the weights are zero");
+        let mut rng = SplitMix(seed);
+        let zipf = match keys {
+            Keys::Zipfian(theta) => Some(Zipf::new(key_space, theta)),
+            _ => None,
+        };
+        let clusters = match keys {
+            Keys::Clustered { clusters, .. } => {
+                assert!(clusters > 0, "Workload::new: no clusters");
+                (0..clusters).map(|_| rng.below(key_space)).collect()
+            }
+            _ => Vec::new(),
+        };
+        Self {
+            keys,
+            key_space,
+            mix,
+            rng,
+            next_key: 0,
+            zipf,
+            clusters,
+        }
+    }
+
+    fn key(&mut self, inserting: bool) -> u64 {
+        match self.keys {
+            Keys::Sequential if inserting => {
+                let key = self.next_key;
+                self.next_key = (self.next_key + 1) % self.key_space;
+                key
+            }
+            Keys::Sequential => self.rng.below(self.next_key.max(1)),
+            Keys::Uniform => self.rng.below(self.key_space),
+            Keys::Zipfian(_) => {
+                let u = self.rng.unit();
+                self.zipf.as_ref().unwrap().sample(u)
+            }
+            Keys::Clustered { spread, .. } => {
+                let centre = self.clusters[self.rng.below(self.clusters.len() as u64) as usize];
+                (centre + self.rng.below(spread.max(1))).min(self.key_space - 1)
+            }
+        }
+    }
+}
+
+impl Iterator for Workload {
+    type Item = Action<u64, u64>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut pick = self.rng.below(self.mix.total());
+        if pick < self.mix.insert as u64 {
+            let key = self.key(true);
+            return Some(Action::Insert(key, key));
+        }
+        pick -= self.mix.insert as u64;
+        if pick < self.mix.lookup as u64 {
+            return Some(Action::Lookup(self.key(false)));
+        }
+        pick -= self.mix.lookup as u64;
+        if pick < self.mix.remove as u64 {
+            return Some(Action::Remove(self.key(false)));
+        }
+        let start = self.key(false);
+        Some(Action::Range(
+            Some(start),
+            Some(start.saturating_add(self.mix.range_len)),
+        ))
+    }
+}
+
+/// Sebastiano Vigna's SplitMix64, which is plenty for picking keys.
+#[derive(Clone, Debug)]
+struct SplitMix(u64);
+
+impl SplitMix {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, limit: u64) -> u64 {
+        ((self.next() as u128 * limit as u128) >> 64) as u64
+    }
+
+    /// A float in `0.0..1.0`.
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// The Zipfian generator from Gray et al., "Quickly Generating
+/// Billion-Record Synthetic Databases", as used by YCSB.
+#[derive(Clone, Debug)]
+struct Zipf {
+    items: u64,
+    theta: f64,
+    zeta_n: f64,
+    alpha: f64,
+    eta: f64,
+}
+
+impl Zipf {
+    fn new(items: u64, theta: f64) -> Self {
+        assert!(
+            theta > 0.0 && (theta - 1.0).abs() > f64::EPSILON,
+            "Workload::new: Zipfian exponent must be positive and not 1"
+        );
+        let zeta = |n: u64| (1..=n).map(|i| 1.0 / (i as f64).powf(theta)).sum::<f64>();
+        let zeta_n = zeta(items);
+        let zeta_2 = zeta(2.min(items));
+        Self {
+            items,
+            theta,
+            zeta_n,
+            alpha: 1.0 / (1.0 - theta),
+            eta: (1.0 - (2.0 / items as f64).powf(1.0 - theta)) / (1.0 - zeta_2 / zeta_n),
+        }
+    }
+
+    fn sample(&self, u: f64) -> u64 {
+        let uz = u * self.zeta_n;
+        if uz < 1.0 {
+            0
+        } else if uz < 1.0 + 0.5f64.powf(self.theta) {
+            1.min(self.items - 1)
+        } else {
+            let rank = self.items as f64 * (self.eta * u - self.eta + 1.0).powf(self.alpha);
+            (rank as u64).min(self.items - 1)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::StdPalmTree;
+
+    fn keys(workload: Workload, count: usize) -> Vec<u64> {
+        workload
+            .take(count)
+            .map(|action| match action {
+                Action::Insert(key, _) | Action::Lookup(key) | Action::Remove(key) => key,
+                Action::Range(Some(key), _) => key,
+                action => panic!("unexpected {:?}", action),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn key_distributions() {
+        let sequential = keys(
+            Workload::new(Keys::Sequential, 100, Mix::WRITE_ONLY, 1),
+            250,
+        );
+        assert!(sequential.iter().copied().eq((0..250).map(|i| i % 100)));
+
+        let uniform = keys(
+            Workload::new(Keys::Uniform, 1000, Mix::BALANCED, 2),
+            100_000,
+        );
+        assert!(uniform.iter().all(|key| *key < 1000));
+        let low = uniform.iter().filter(|key| **key < 500).count();
+        assert!((45_000..55_000).contains(&low));
+
+        // The hottest ten keys out of a million get a good share of the hits.
+        let zipf = keys(
+            Workload::new(Keys::Zipfian(0.99), 1_000_000, Mix::READ_HEAVY, 3),
+            100_000,
+        );
+        assert!(zipf.iter().all(|key| *key < 1_000_000));
+        let hot = zipf.iter().filter(|key| **key < 10).count();
+        assert!(hot > 15_000, "{} hot keys", hot);
+
+        let clustered = Keys::Clustered {
+            clusters: 4,
+            spread: 100,
+        };
+        let mut clustered = keys(Workload::new(clustered, 1_000_000, Mix::CHURN, 4), 10_000);
+        clustered.sort_unstable();
+        clustered.dedup();
+        assert!(clustered.len() <= 400);
+
+        // The same seed gives the same actions.
+        assert_eq!(
+            keys(Workload::new(Keys::Uniform, 1000, Mix::SCAN_HEAVY, 5), 1000),
+            keys(Workload::new(Keys::Uniform, 1000, Mix::SCAN_HEAVY, 5), 1000),
+        );
+    }
+
+    #[test]
+    fn action_mix() {
+        let mix = Mix {
+            insert: 1,
+            lookup: 2,
+            remove: 3,
+            range: 4,
+            range_len: 10,
+        };
+        let mut counts = [0; 4];
+        let mut tree: StdPalmTree<u64, u64> = StdPalmTree::new();
+        for action in Workload::new(Keys::Uniform, 1000, mix, 6).take(100_000) {
+            match action {
+                Action::Insert(key, value) => {
+                    counts[0] += 1;
+                    tree.insert(key, value);
+                }
+                Action::Lookup(key) => {
+                    counts[1] += 1;
+                    tree.get(&key);
+                }
+                Action::Remove(key) => {
+                    counts[2] += 1;
+                    tree.remove(&key);
+                }
+                Action::Range(Some(start), Some(end)) => {
+                    counts[3] += 1;
+                    assert_eq!(10, end - start);
+                    assert!(tree.range(start..end).count() <= 10);
+                }
+                action => panic!("unexpected {:?}", action),
+            }
+        }
+        for (index, count) in counts.iter().enumerate() {
+            let expected = (index + 1) * 10_000;
+            assert!(
+                (expected * 9 / 10..expected * 11 / 10).contains(count),
+                "{:?}",
+                counts
+            );
+        }
+    }
+}